@@ -1,5 +1,10 @@
-use simcore::proto::ParticleSimulator;
-use visualize::proto::RaylibVisualizer;
+use simcore::{
+    fluid::proto::FluidSimulator,
+    lattice::proto::LatticeBoltzmannSimulator,
+    proto::ParticleSimulator,
+    SimulationKind,
+};
+use visualize::proto::{FluidView, LatticeView, RaylibVisualizer};
 
 #[test]
 fn default_config() {
@@ -16,32 +21,81 @@ fn throw_error(title: &str, message: &str) {
         .show();
 }
 
-fn main() {
-    let mut raylib_instance = RaylibVisualizer::new();
+fn throw_load_error(e: &simcore::Error) {
+    let title = match e {
+        simcore::Error::Io(_) => "Unable to read configuration",
+        simcore::Error::Parse { .. } => "Unable to parse configuration",
+        simcore::Error::Schema(_) => "Configuration violates schema",
+        simcore::Error::Semantic { .. } => "Invalid configuration",
+    };
 
-    // request for configuration
-    let config_file = rfd::FileDialog::new()
-        .add_filter("json", &["json"])
-        .set_title("Select configuration file")
-        .set_directory("~")
-        .pick_file();
+    // Snippets line up in a monospace terminal, unlike in the dialog
+    eprintln!("{title}:\n{e}");
+    throw_error(
+        title,
+        &format!("Error occured while loading configuration:\n{e}")
+    );
+}
 
-    let config_file = if let Some(c) = config_file { c } else {
-        eprintln!("No config file chosen!");
-        return;
+/// Runs particle simulation until the window closes. Returns None if loading failed
+fn run_particles(raylib_instance: &mut RaylibVisualizer, config_file: &str) -> Option<ParticleSimulator> {
+    let mut engine = match ParticleSimulator::load(config_file) {
+        Ok(v) => v,
+        Err(e) => {
+            throw_load_error(&e);
+            return None;
+        }
     };
 
-    let config_file = config_file.to_str()
-        .expect("Unable to parse config path as utf-8 string");
+    engine.start_recording_statistics();
+    while raylib_instance.is_looping() {
+        raylib_instance.camera_control();
 
-    let mut engine = match ParticleSimulator::load(config_file) {
+        if let Err(e) = engine.step() {
+            throw_error("Simulation failed", &format!("Error occured while stepping simulation:\n{e}"));
+            break;
+        }
+
+        raylib_instance.draw_particles(
+            engine.particles(),
+            engine.rigid_bodies(),
+            engine.boundary(),
+            engine.obstacles(),
+            engine.sim_name(),
+            engine.time(),
+        );
+    }
+
+    Some(engine)
+}
+
+fn run_fluid(raylib_instance: &mut RaylibVisualizer, config_file: &str) -> Option<FluidSimulator> {
+    let mut engine = match FluidSimulator::load(config_file) {
         Ok(v) => v,
         Err(e) => {
-            throw_error(
-                "Unable to load configuration",
-                &format!("Error occured while loading configuration: {e}")
-            );
-            return;
+            throw_load_error(&e);
+            return None;
+        }
+    };
+
+    engine.start_recording_statistics();
+    while raylib_instance.is_looping() {
+        raylib_instance.camera_control();
+
+        engine.step();
+
+        raylib_instance.draw_fluid(&engine, FluidView::Dye);
+    }
+
+    Some(engine)
+}
+
+fn run_lattice(raylib_instance: &mut RaylibVisualizer, config_file: &str) -> Option<LatticeBoltzmannSimulator> {
+    let mut engine = match LatticeBoltzmannSimulator::load(config_file) {
+        Ok(v) => v,
+        Err(e) => {
+            throw_load_error(&e);
+            return None;
         }
     };
 
@@ -51,9 +105,15 @@ fn main() {
 
         engine.step();
 
-        raylib_instance.draw_particles(engine.particles(), engine.sim_name(), engine.time());
+        // Vorticity shows vortex streets best
+        raylib_instance.draw_lattice(&engine, LatticeView::Vorticity);
     }
 
+    Some(engine)
+}
+
+/// Asks for a statistics file and writes statistics with `save`
+fn save_statistics(save: impl FnOnce(&str) -> std::io::Result<()>) {
     let stats_file = rfd::FileDialog::new()
         .add_filter("json", &["json"])
         .set_title("Select configuration file")
@@ -68,7 +128,7 @@ fn main() {
     let stats_file = stats_file.to_str()
         .expect("Unable to parse config path as utf-8 string");
 
-    match engine.save_statistics(stats_file) {
+    match save(stats_file) {
         Ok(_) => {},
         Err(e) => {
             throw_error(
@@ -78,3 +138,42 @@ fn main() {
         }
     };
 }
+
+fn main() {
+    let mut raylib_instance = RaylibVisualizer::new();
+
+    // request for configuration
+    let config_file = rfd::FileDialog::new()
+        .add_filter("configuration", &["json", "toml", "yaml", "yml", "ron"])
+        .set_title("Select configuration file")
+        .set_directory("~")
+        .pick_file();
+
+    let config_file = if let Some(c) = config_file { c } else {
+        eprintln!("No config file chosen!");
+        return;
+    };
+
+    let config_file = config_file.to_str()
+        .expect("Unable to parse config path as utf-8 string");
+
+    let kind = match SimulationKind::from_file(config_file) {
+        Ok(kind) => kind,
+        Err(e) => {
+            throw_load_error(&e);
+            return;
+        }
+    };
+
+    match kind {
+        SimulationKind::Particles => if let Some(engine) = run_particles(&mut raylib_instance, config_file) {
+            save_statistics(|file| engine.save_statistics(file));
+        },
+        SimulationKind::Fluid => if let Some(engine) = run_fluid(&mut raylib_instance, config_file) {
+            save_statistics(|file| engine.save_statistics(file));
+        },
+        SimulationKind::Lattice => if let Some(engine) = run_lattice(&mut raylib_instance, config_file) {
+            save_statistics(|file| engine.save_statistics(file));
+        },
+    }
+}
@@ -0,0 +1,186 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::{Property, PropertyError, PropertyMatrix, SimFloat};
+
+/// Result of arithmetic on properties
+pub type PropertyResult = Result<Property, PropertyError>;
+
+struct Operation {
+    name: &'static str,
+    float: fn(SimFloat, SimFloat) -> SimFloat,
+    /// Integer version, None when the result should be Float
+    int: Option<fn(i64, i64) -> Option<i64>>,
+}
+
+const ADD: Operation = Operation { name: "add", float: |a, b| a + b, int: Some(i64::checked_add) };
+const SUBTRACT: Operation = Operation { name: "subtract", float: |a, b| a - b, int: Some(i64::checked_sub) };
+const MULTIPLY: Operation = Operation { name: "multiply", float: |a, b| a * b, int: Some(i64::checked_mul) };
+const DIVIDE: Operation = Operation { name: "divide", float: |a, b| a / b, int: None };
+
+impl Property {
+    /// Applies `f` to every number in a numeric property
+    pub fn map_numbers(&self, f: &impl Fn(SimFloat) -> SimFloat) -> Option<Property> {
+        Some(match self {
+            Property::Int(v) => Property::Float(f(*v as SimFloat)),
+            Property::Float(v) => Property::Float(f(*v)),
+            Property::Vector2(v) => Property::Vector2(v.map(f)),
+            Property::Vector3(v) => Property::Vector3(v.map(f)),
+            Property::Vector4(v) => Property::Vector4(v.map(f)),
+            Property::Matrix(m) => Property::Matrix(PropertyMatrix::from_rows(
+                (0..m.rows()).map(|r| m.row(r).iter().map(|x| f(*x)).collect()).collect(),
+            ).ok()?),
+            Property::Array(a) => Property::Array(
+                a.iter().map(|v| v.map_numbers(f)).collect::<Option<_>>()?,
+            ),
+            _ => return None,
+        })
+    }
+
+    fn is_scalar(&self) -> bool {
+        matches!(self, Property::Int(_) | Property::Float(_))
+    }
+
+    fn combine(&self, other: &Property, operation: &Operation) -> PropertyResult {
+        let incompatible = || PropertyError::Incompatible {
+            operation: operation.name,
+            left: self.type_name(),
+            right: other.type_name(),
+        };
+        let f = operation.float;
+
+        match (self, other) {
+            (Property::Int(a), Property::Int(b)) => Ok(match operation.int.and_then(|op| op(*a, *b)) {
+                Some(v) => Property::Int(v),
+                None => Property::Float(f(*a as SimFloat, *b as SimFloat)),
+            }),
+            // Scalars are broadcast over the other operand
+            (a, b) if a.is_scalar() => {
+                let a = a.try_float().unwrap();
+                b.map_numbers(&|b| f(a, b)).ok_or_else(incompatible)
+            }
+            (a, b) if b.is_scalar() => {
+                let b = b.try_float().unwrap();
+                a.map_numbers(&|a| f(a, b)).ok_or_else(incompatible)
+            }
+            (Property::Vector2(a), Property::Vector2(b)) => Ok(Property::Vector2(std::array::from_fn(|i| f(a[i], b[i])))),
+            (Property::Vector3(a), Property::Vector3(b)) => Ok(Property::Vector3(std::array::from_fn(|i| f(a[i], b[i])))),
+            (Property::Vector4(a), Property::Vector4(b)) => Ok(Property::Vector4(std::array::from_fn(|i| f(a[i], b[i])))),
+            (Property::Matrix(a), Property::Matrix(b)) if (a.rows(), a.columns()) == (b.rows(), b.columns()) => {
+                let rows = (0..a.rows())
+                    .map(|r| a.row(r).iter().zip(b.row(r)).map(|(x, y)| f(*x, *y)).collect())
+                    .collect();
+                PropertyMatrix::from_rows(rows).map(Property::Matrix).map_err(|_| incompatible())
+            }
+            (Property::Array(a), Property::Array(b)) if a.len() == b.len() => a.iter()
+                .zip(b)
+                .map(|(x, y)| x.combine(y, operation))
+                .collect::<Result<_, _>>()
+                .map(Property::Array),
+            _ => Err(incompatible()),
+        }
+    }
+}
+
+macro_rules! binary_operator {
+    ($trait:ident, $method:ident, $operation:expr) => {
+        /// Element-wise. Matrices are multiplied element by element too, use
+        /// nalgebra conversions for matrix products
+        impl $trait<&Property> for &Property {
+            type Output = PropertyResult;
+
+            fn $method(self, other: &Property) -> PropertyResult {
+                self.combine(other, &$operation)
+            }
+        }
+
+        impl $trait for Property {
+            type Output = PropertyResult;
+
+            fn $method(self, other: Property) -> PropertyResult {
+                self.combine(&other, &$operation)
+            }
+        }
+
+        impl $trait<SimFloat> for &Property {
+            type Output = PropertyResult;
+
+            fn $method(self, other: SimFloat) -> PropertyResult {
+                self.combine(&Property::Float(other), &$operation)
+            }
+        }
+    };
+}
+
+binary_operator!(Add, add, ADD);
+binary_operator!(Sub, sub, SUBTRACT);
+binary_operator!(Mul, mul, MULTIPLY);
+binary_operator!(Div, div, DIVIDE);
+
+impl Neg for &Property {
+    type Output = PropertyResult;
+
+    fn neg(self) -> PropertyResult {
+        match self {
+            Property::Int(v) => Ok(v.checked_neg().map(Property::Int).unwrap_or(Property::Float(-(*v as SimFloat)))),
+            _ => self.map_numbers(&|v| -v).ok_or_else(|| PropertyError::WrongType {
+                path: String::new(),
+                expected: "number",
+                found: self.type_name(),
+            }),
+        }
+    }
+}
+
+impl Neg for Property {
+    type Output = PropertyResult;
+
+    fn neg(self) -> PropertyResult {
+        -&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Property, PropertyError, PropertyMatrix};
+
+    #[test]
+    fn integers_stay_integers_unless_divided_or_overflowing() {
+        assert_eq!(&Property::Int(2) + &Property::Int(3), Ok(Property::Int(5)));
+        assert_eq!(&Property::Int(3) / &Property::Int(2), Ok(Property::Float(1.5)));
+        assert_eq!(&Property::Int(i64::MAX) * &Property::Int(2), Ok(Property::Float(i64::MAX as f64 * 2.0)));
+        assert_eq!(-Property::Int(i64::MIN), Ok(Property::Float(-(i64::MIN as f64))));
+    }
+
+    #[test]
+    fn scalars_broadcast_over_vectors_and_arrays() {
+        assert_eq!(&Property::Vector2([1.0, 2.0]) * 2.0, Ok(Property::Vector2([2.0, 4.0])));
+        assert_eq!(
+            &Property::Float(1.0) - &Property::Array(vec![Property::Int(1), Property::Float(0.5)]),
+            Ok(Property::Array(vec![Property::Float(0.0), Property::Float(0.5)])),
+        );
+    }
+
+    #[test]
+    fn operands_combine_element_wise() {
+        assert_eq!(
+            Property::Vector3([1.0, 2.0, 3.0]) + Property::Vector3([1.0, 1.0, 1.0]),
+            Ok(Property::Vector3([2.0, 3.0, 4.0])),
+        );
+
+        let matrix = |rows: Vec<Vec<f64>>| Property::Matrix(PropertyMatrix::from_rows(rows).unwrap());
+        assert_eq!(
+            &matrix(vec![vec![1.0, 2.0], vec![3.0, 4.0]]) * &matrix(vec![vec![2.0, 0.0], vec![1.0, 1.0]]),
+            Ok(matrix(vec![vec![2.0, 0.0], vec![3.0, 4.0]])),
+        );
+    }
+
+    #[test]
+    fn incompatible_operands_are_rejected() {
+        assert_eq!(
+            &Property::Vector2([0.0; 2]) + &Property::Vector3([0.0; 3]),
+            Err(PropertyError::Incompatible { operation: "add", left: "Vector2", right: "Vector3" }),
+        );
+        assert!(matches!(&Property::String("a".to_string()) * 2.0, Err(PropertyError::Incompatible { .. })));
+        assert!(matches!(-Property::Bool(true), Err(PropertyError::WrongType { .. })));
+    }
+}
@@ -0,0 +1,656 @@
+pub mod proto {
+    use std::{
+        collections::HashMap,
+        io::{Error as IoError, ErrorKind, Result as IoResult},
+    };
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        field::proto::{FieldRegion, FieldSolverConfig, Grid, GridConfig},
+        random::Rng,
+        stats::Timeseries,
+        Error, Format, Property, PropertyError, PropertyMap, SimFloat,
+    };
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct AutomatonConfiguration {
+        /// `rule` selects the model and holds its parameters
+        simulation_config: HashMap<String, Property>,
+        /// Time of one generation
+        solver_config: FieldSolverConfig,
+        grid: GridConfig,
+        /// Axes wrapping around. Cells outside of other edges are in `boundary_state`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        periodic: Vec<bool>,
+        #[serde(default)]
+        boundary_state: i32,
+        #[serde(default)]
+        initial_state: i32,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        initial_regions: Vec<FieldRegion>,
+        /// Seed of random initial states and stochastic rules. Defaults to 0
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seed: Option<u64>,
+    }
+
+    impl AutomatonConfiguration {
+        pub fn save(&self, filename: &str) -> IoResult<()> {
+            let stringified = serde_json::to_string_pretty(self)?;
+
+            std::fs::write(filename, stringified)
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum AutomatonRule {
+        /// Outer totalistic two-state rule on the Moore neighbourhood, e.g. B3/S23
+        LifeLike {
+            birth: Vec<bool>,
+            survive: Vec<bool>,
+        },
+        /// Abelian sandpile. A grain is dropped every step and cells holding
+        /// `threshold` grains topple one grain to each nearest neighbour
+        Sandpile {
+            threshold: i32,
+            /// Fixed cell grains are dropped on, or random cells if None
+            drop: Option<[usize; 3]>,
+        },
+        /// Metropolis sweep of +-1 spins with nearest neighbour `coupling` and external `field`
+        Ising {
+            temperature: SimFloat,
+            coupling: SimFloat,
+            field: SimFloat,
+        },
+        /// New state looked up by current state and number of Moore neighbours in
+        /// `count_state` (any non-zero state if None). Unlisted cases go to `default`
+        /// or keep their state
+        Table {
+            rules: HashMap<(i32, usize), i32>,
+            count_state: Option<i32>,
+            default: Option<i32>,
+        },
+    }
+
+    impl AutomatonRule {
+        /// Parses `B<counts>/S<counts>` notation
+        pub fn life_like(rule: &str, neighbours: usize) -> IoResult<Self> {
+            let error = || IoError::new(
+                ErrorKind::InvalidData,
+                format!("Life-like rule should look like `B3/S23`, got `{rule}`"),
+            );
+
+            let (birth, survive) = rule.split_once('/').ok_or_else(error)?;
+            let counts = |part: &str, prefix: char| -> IoResult<Vec<bool>> {
+                let digits = part.strip_prefix(prefix)
+                    .or_else(|| part.strip_prefix(prefix.to_ascii_lowercase()))
+                    .ok_or_else(error)?;
+
+                let mut set = vec![false; neighbours + 1];
+                for d in digits.chars() {
+                    let count = d.to_digit(10).ok_or_else(error)? as usize;
+                    *set.get_mut(count).ok_or_else(error)? = true;
+                }
+                Ok(set)
+            };
+
+            Ok(AutomatonRule::LifeLike {
+                birth: counts(birth, 'B')?,
+                survive: counts(survive, 'S')?,
+            })
+        }
+
+        /// Reads `rule` (`life`, `sandpile`, `ising` or `table`) and its parameters from simulation config
+        pub fn from_config(config: &HashMap<String, Property>, grid: &Grid) -> IoResult<Self> {
+            let neighbours = 3usize.pow(grid.dimension as u32) - 1;
+
+            match config.get_str_opt("rule")?.unwrap_or_default() {
+                "life" => {
+                    let rule = config.get_str_opt("rule_string")?.unwrap_or("B3/S23");
+                    Self::life_like(rule, neighbours)
+                }
+                "sandpile" => {
+                    let drop = match config.get("drop") {
+                        None => Some(grid.shape.map(|s| s / 2)),
+                        Some(Property::String(s)) if s == "random" => None,
+                        Some(p) => {
+                            let position = p.try_components().unwrap_or_default();
+                            if position.len() != grid.dimension {
+                                return Err(IoError::new(
+                                    ErrorKind::InvalidData,
+                                    "Sandpile `drop` should be a cell index or \"random\"",
+                                ));
+                            }
+
+                            let mut cell = [0; 3];
+                            for (axis, x) in position.iter().enumerate() {
+                                cell[axis] = (*x as usize).min(grid.shape[axis] - 1);
+                            }
+                            Some(cell)
+                        }
+                    };
+
+                    // A toppling cell must lose at least the grains it hands out,
+                    // otherwise avalanches never end
+                    let neighbours = 2 * grid.dimension;
+                    let threshold = config.get_count_or("threshold", neighbours)?;
+                    if threshold < neighbours {
+                        return Err(IoError::new(
+                            ErrorKind::InvalidData,
+                            format!("Sandpile `threshold` should be at least {neighbours}, got {threshold}"),
+                        ));
+                    }
+
+                    Ok(AutomatonRule::Sandpile { threshold: threshold as i32, drop })
+                }
+                "ising" => Ok(AutomatonRule::Ising {
+                    temperature: config.get_float("temperature")?,
+                    coupling: config.get_float_or("coupling", 1.0)?,
+                    field: config.get_float_or("field", 0.0)?,
+                }),
+                "table" => {
+                    let table = config.get("rules").and_then(|r| r.try_nested()).ok_or_else(|| IoError::new(
+                        ErrorKind::InvalidData,
+                        "Rule table requires `rules` mapping \"state:count\" to new state",
+                    ))?;
+
+                    // States, unlike counts, may be negative
+                    let state = |key: &str| -> IoResult<Option<i32>> {
+                        match config.get_int(key) {
+                            Err(PropertyError::Missing { .. }) => Ok(None),
+                            state => Ok(Some(state? as i32)),
+                        }
+                    };
+
+                    let mut rules = HashMap::new();
+                    for (key, value) in table.iter() {
+                        let parsed = key.split_once(':')
+                            .and_then(|(s, c)| Some((s.trim().parse().ok()?, c.trim().parse().ok()?)));
+                        let (Some(key), Some(state)) = (parsed, value.try_int()) else {
+                            return Err(IoError::new(
+                                ErrorKind::InvalidData,
+                                format!("Invalid rule `{key}`, expected \"state:count\": new state"),
+                            ));
+                        };
+                        rules.insert(key, state as i32);
+                    }
+
+                    Ok(AutomatonRule::Table {
+                        rules,
+                        count_state: state("count_state")?,
+                        default: state("default")?,
+                    })
+                }
+                rule => Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown automaton rule `{rule}`"),
+                )),
+            }
+        }
+    }
+
+    /// Discrete state grid updated by an automaton rule
+    pub struct AutomatonSimulator {
+        solver_config: FieldSolverConfig,
+        sim_config: HashMap<String, Property>,
+        rule: AutomatonRule,
+        grid: Grid,
+        periodic: [bool; 3],
+        boundary_state: i32,
+        states: Vec<i32>,
+        rng: Rng,
+        /// Topplings of last sandpile avalanche
+        avalanche_size: usize,
+        steps: usize,
+        stats: Option<Timeseries<HashMap<String, Property>>>,
+    }
+
+    impl AutomatonSimulator {
+        /// `initial_density` in simulation config fills that fraction of cells with
+        /// state 1 at random before regions are applied. Ising spins start random if not given
+        pub fn load(filename: &str) -> Result<Self, Error> {
+            let source = std::fs::read_to_string(filename)?;
+            Self::from_source(&source, Format::from_path(filename))
+        }
+
+        /// Loads configuration from text. Errors carry snippets of `source`
+        pub fn from_source(source: &str, format: Format) -> Result<Self, Error> {
+            format.deserialize::<AutomatonConfiguration>(source)
+                .and_then(|config| Self::from_configuration(config).map_err(Error::from_setup))
+                .map_err(|e| e.located(source, format))
+        }
+
+        fn from_configuration(config: AutomatonConfiguration) -> IoResult<Self> {
+            config.simulation_config.get_str("name").map_err(|e| e.within("simulation_config"))?;
+
+            let grid = Grid::new(&config.grid.shape, config.grid.spacing, &config.grid.origin)?;
+            let rule = AutomatonRule::from_config(&config.simulation_config, &grid)?;
+            let mut rng = Rng::new(config.seed.unwrap_or(0));
+
+            let mut states = vec![config.initial_state; grid.len()];
+            let density = config.simulation_config.get_float_opt("initial_density")
+                .map_err(|e| e.within("simulation_config"))?;
+            match (&rule, density) {
+                (AutomatonRule::Ising { .. }, density) => {
+                    let up = density.unwrap_or(0.5);
+                    for s in states.iter_mut() {
+                        *s = if rng.uniform() < up { 1 } else { -1 };
+                    }
+                }
+                (_, Some(density)) => {
+                    for s in states.iter_mut() {
+                        if rng.uniform() < density { *s = 1 }
+                    }
+                }
+                (_, None) => {}
+            }
+
+            for region in config.initial_regions.iter() {
+                let state = region.value.try_int().ok_or_else(|| IoError::new(
+                    ErrorKind::InvalidData,
+                    "Automaton region value should be an integer",
+                ))? as i32;
+
+                if region.min.len() != grid.dimension || region.max.len() != grid.dimension {
+                    return Err(IoError::new(
+                        ErrorKind::InvalidData,
+                        format!("Region bounds should have {} components", grid.dimension),
+                    ));
+                }
+
+                for (index, cell) in grid.cells().enumerate() {
+                    if (0..grid.dimension).all(|a| cell[a] >= region.min[a] && cell[a] < region.max[a]) {
+                        states[index] = state;
+                    }
+                }
+            }
+
+            let periodic = std::array::from_fn(|a| config.periodic.get(a).copied().unwrap_or(false));
+            if matches!(rule, AutomatonRule::Sandpile { .. }) && periodic[..grid.dimension].iter().all(|p| *p) {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    "Sandpile needs a non-periodic axis for grains to fall off, otherwise it never settles",
+                ));
+            }
+
+            Ok(Self {
+                solver_config: config.solver_config,
+                sim_config: config.simulation_config,
+                rule,
+                grid,
+                periodic,
+                boundary_state: config.boundary_state,
+                states,
+                rng,
+                avalanche_size: 0,
+                steps: 0,
+                stats: None,
+            })
+        }
+
+        pub fn start_recording_statistics(&mut self) {
+            self.stats = Some(Timeseries::new());
+        }
+
+        pub fn save_statistics(&self, filename: &str) -> IoResult<()> {
+            #[derive(Serialize)]
+            struct Metadata<'a> {
+                rng: &'a Rng,
+            }
+
+            if let Some(stats) = self.stats.as_ref() {
+                stats.save_with_metadata(filename, Metadata { rng: &self.rng })?
+            }
+
+            Ok(())
+        }
+
+        pub fn sim_name(&self) -> &str {
+            self.sim_config.get("name").and_then(|n| n.try_str()).unwrap_or_default()
+        }
+
+        pub fn rule(&self) -> &AutomatonRule {
+            &self.rule
+        }
+
+        pub fn grid(&self) -> &Grid {
+            &self.grid
+        }
+
+        pub fn states(&self) -> &[i32] {
+            &self.states
+        }
+
+        pub fn state(&self, cell: [usize; 3]) -> i32 {
+            self.states[self.grid.index(cell)]
+        }
+
+        /// Index of cell shifted by `offset`, or None outside of a non-periodic edge
+        fn shifted(&self, cell: [usize; 3], offset: [isize; 3]) -> Option<usize> {
+            let mut target = [0; 3];
+            for axis in 0..3 {
+                let size = self.grid.shape[axis] as isize;
+                let mut x = cell[axis] as isize + offset[axis];
+                if x < 0 || x >= size {
+                    if !self.periodic[axis] { return None }
+                    x = x.rem_euclid(size);
+                }
+                target[axis] = x as usize;
+            }
+
+            Some(self.grid.index(target))
+        }
+
+        fn neighbour_state(&self, states: &[i32], cell: [usize; 3], offset: [isize; 3]) -> i32 {
+            self.shifted(cell, offset).map(|i| states[i]).unwrap_or(self.boundary_state)
+        }
+
+        /// Offsets of all cells around the center in the used dimensions
+        fn moore_offsets(&self) -> Vec<[isize; 3]> {
+            let dimension = self.grid.dimension;
+            (0..3usize.pow(dimension as u32))
+                .map(|n| std::array::from_fn(|a| if a < dimension {
+                    (n / 3usize.pow(a as u32) % 3) as isize - 1
+                } else { 0 }))
+                .filter(|o: &[isize; 3]| *o != [0, 0, 0])
+                .collect()
+        }
+
+        fn von_neumann_offsets(&self) -> Vec<[isize; 3]> {
+            (0..self.grid.dimension)
+                .flat_map(|a| [-1, 1].map(|d| {
+                    let mut o = [0; 3];
+                    o[a] = d;
+                    o
+                }))
+                .collect()
+        }
+
+        fn step_synchronous(&mut self) {
+            let offsets = self.moore_offsets();
+            let states = &self.states;
+
+            let next = self.grid.cells().enumerate().map(|(index, cell)| {
+                let state = states[index];
+                let count_state = match &self.rule {
+                    AutomatonRule::Table { count_state, .. } => *count_state,
+                    _ => Some(1),
+                };
+                let count = offsets.iter()
+                    .map(|o| self.neighbour_state(states, cell, *o))
+                    .filter(|s| match count_state {
+                        Some(c) => *s == c,
+                        None => *s != 0,
+                    })
+                    .count();
+
+                match &self.rule {
+                    AutomatonRule::LifeLike { birth, survive } => {
+                        let alive = if state == 0 { birth[count] } else { survive[count] };
+                        alive as i32
+                    }
+                    AutomatonRule::Table { rules, default, .. } => rules.get(&(state, count))
+                        .copied()
+                        .unwrap_or(default.unwrap_or(state)),
+                    _ => state,
+                }
+            }).collect();
+
+            self.states = next;
+        }
+
+        fn step_sandpile(&mut self, threshold: i32, drop: Option<[usize; 3]>) {
+            let cell = drop.unwrap_or_else(|| {
+                let index = (self.rng.uniform() * self.grid.len() as SimFloat) as usize;
+                self.grid.cell(index.min(self.grid.len() - 1))
+            });
+            let index = self.grid.index(cell);
+            self.states[index] += 1;
+
+            let offsets = self.von_neumann_offsets();
+            let mut unstable = vec![index];
+            let mut topplings = 0;
+            while let Some(index) = unstable.pop() {
+                if self.states[index] < threshold { continue }
+
+                let cell = self.grid.cell(index);
+                self.states[index] -= offsets.len() as i32;
+                topplings += 1;
+                if self.states[index] >= threshold { unstable.push(index) }
+
+                // Grains falling off a non-periodic edge leave the pile
+                for offset in offsets.iter() {
+                    if let Some(neighbour) = self.shifted(cell, *offset) {
+                        self.states[neighbour] += 1;
+                        if self.states[neighbour] >= threshold { unstable.push(neighbour) }
+                    }
+                }
+            }
+
+            self.avalanche_size = topplings;
+        }
+
+        /// Change of energy when flipping spin at `index`
+        fn ising_flip_energy(&self, index: usize, coupling: SimFloat, field: SimFloat, offsets: &[[isize; 3]]) -> SimFloat {
+            let cell = self.grid.cell(index);
+            let spin = self.states[index] as SimFloat;
+            let neighbours = offsets.iter()
+                .map(|o| self.neighbour_state(&self.states, cell, *o) as SimFloat)
+                .sum::<SimFloat>();
+
+            2.0 * spin * (coupling * neighbours + field)
+        }
+
+        fn step_ising(&mut self, temperature: SimFloat, coupling: SimFloat, field: SimFloat) {
+            let offsets = self.von_neumann_offsets();
+            for _ in 0..self.grid.len() {
+                let index = ((self.rng.uniform() * self.grid.len() as SimFloat) as usize).min(self.grid.len() - 1);
+                let change = self.ising_flip_energy(index, coupling, field, &offsets);
+                if change <= 0.0 || self.rng.uniform() < (-change / temperature).exp() {
+                    self.states[index] = -self.states[index];
+                }
+            }
+        }
+
+        fn record_stats(&mut self) {
+            if self.stats.is_none() { return; }
+
+            let population = self.states.iter().filter(|s| **s != 0).count();
+            let mut hashmap = HashMap::new();
+            hashmap.insert("population".to_string(), Property::Float(population as SimFloat));
+
+            match self.rule {
+                AutomatonRule::Sandpile { .. } => {
+                    let grains = self.states.iter().map(|s| *s as SimFloat).sum::<SimFloat>();
+                    hashmap.insert("grains".to_string(), Property::Float(grains));
+                    hashmap.insert("avalanche_size".to_string(), Property::Float(self.avalanche_size as SimFloat));
+                }
+                AutomatonRule::Ising { coupling, field, .. } => {
+                    let offsets = self.von_neumann_offsets();
+                    let n = self.grid.len() as SimFloat;
+                    let magnetization = self.states.iter().map(|s| *s as SimFloat).sum::<SimFloat>() / n;
+
+                    // Every bond is seen from both ends
+                    let energy = self.grid.cells().enumerate().map(|(index, cell)| {
+                        let spin = self.states[index] as SimFloat;
+                        let neighbours = offsets.iter()
+                            .map(|o| self.neighbour_state(&self.states, cell, *o) as SimFloat)
+                            .sum::<SimFloat>();
+                        -spin * (0.5 * coupling * neighbours + field)
+                    }).sum::<SimFloat>() / n;
+
+                    hashmap.insert("magnetization".to_string(), Property::Float(magnetization));
+                    hashmap.insert("energy".to_string(), Property::Float(energy));
+                }
+                _ => {}
+            }
+
+            let time = self.time();
+            self.stats.as_mut().unwrap().record(hashmap, Some(time));
+        }
+
+        pub fn step(&mut self) {
+            self.record_stats();
+
+            match self.rule {
+                AutomatonRule::LifeLike { .. } | AutomatonRule::Table { .. } => self.step_synchronous(),
+                AutomatonRule::Sandpile { threshold, drop } => self.step_sandpile(threshold, drop),
+                AutomatonRule::Ising { temperature, coupling, field } => {
+                    self.step_ising(temperature, coupling, field)
+                }
+            }
+
+            self.steps += 1;
+        }
+
+        pub fn time(&self) -> SimFloat {
+            self.steps as SimFloat * self.solver_config.timestep
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::proto::*;
+    use crate::{field::proto::Grid, Error, Format, Property};
+
+    fn load(simulation: &str, extra: &str) -> Result<AutomatonSimulator, Error> {
+        let config = format!(r#"{{
+            "simulation_config": {{"name": "automaton"{simulation}}},
+            "solver_config": {{"timestep": 1.0}},
+            "grid": {{"shape": [5, 5], "spacing": 1.0}}{extra}
+        }}"#);
+        AutomatonSimulator::from_source(&config, Format::Json)
+    }
+
+    fn alive(simulator: &AutomatonSimulator) -> Vec<[usize; 2]> {
+        simulator.grid().cells()
+            .filter(|c| simulator.state(*c) != 0)
+            .map(|c| [c[0], c[1]])
+            .collect()
+    }
+
+    #[test]
+    fn life_like_notation_is_parsed() {
+        let rule = AutomatonRule::life_like("B36/s23", 8).unwrap();
+        let AutomatonRule::LifeLike { birth, survive } = rule else { unreachable!() };
+        let counts = |set: Vec<bool>| set.iter().enumerate().filter(|(_, b)| **b).map(|(i, _)| i).collect::<Vec<_>>();
+        assert_eq!(counts(birth), [3, 6]);
+        assert_eq!(counts(survive), [2, 3]);
+
+        assert!(AutomatonRule::life_like("B3S23", 8).is_err());
+        assert!(AutomatonRule::life_like("B9/S23", 8).is_err());
+        assert!(AutomatonRule::life_like("X3/S23", 8).is_err());
+    }
+
+    #[test]
+    fn blinker_oscillates_with_period_two() {
+        let blinker = r#", "initial_regions": [{"min": [1, 2], "max": [4, 3], "value": 1}]"#;
+        let mut simulator = load(r#", "rule": "life""#, blinker).unwrap();
+        let horizontal = alive(&simulator);
+
+        simulator.step();
+        assert_eq!(alive(&simulator), [[2, 1], [2, 2], [2, 3]]);
+        simulator.step();
+        assert_eq!(alive(&simulator), horizontal);
+    }
+
+    #[test]
+    fn sandpile_settles_below_threshold() {
+        let mut simulator = load(r#", "rule": "sandpile""#, "").unwrap();
+        for _ in 0..200 {
+            simulator.step();
+        }
+
+        assert!(simulator.states().iter().all(|s| (0..4).contains(s)));
+        // Grains only leave through the edges
+        let grains = simulator.states().iter().sum::<i32>();
+        assert!(grains > 0 && grains < 200, "{grains}");
+    }
+
+    #[test]
+    fn sandpile_that_cant_settle_is_rejected() {
+        let grid = Grid::new(&[5, 5], 1.0, &[]).unwrap();
+        let config = |threshold: i64| HashMap::from([
+            ("rule".to_string(), Property::String("sandpile".to_string())),
+            ("threshold".to_string(), Property::Int(threshold)),
+        ]);
+        assert!(AutomatonRule::from_config(&config(3), &grid).is_err());
+        assert!(AutomatonRule::from_config(&config(-4), &grid).is_err());
+        assert!(AutomatonRule::from_config(&config(5), &grid).is_ok());
+
+        let periodic = r#", "periodic": [true, true]"#;
+        assert!(load(r#", "rule": "sandpile""#, periodic).is_err());
+        let open_axis = r#", "periodic": [true, false]"#;
+        assert!(load(r#", "rule": "sandpile""#, open_axis).is_ok());
+    }
+
+    #[test]
+    fn cold_ising_model_orders_and_hot_one_does_not() {
+        let magnetization = |temperature: f64| {
+            let simulation = format!(r#", "rule": "ising", "temperature": {temperature}, "initial_density": 0.9"#);
+            let mut simulator = load(&simulation, r#", "periodic": [true, true], "seed": 3"#).unwrap();
+            for _ in 0..200 {
+                simulator.step();
+            }
+            simulator.states().iter().sum::<i32>().abs() as f64 / 25.0
+        };
+
+        assert!(magnetization(0.5) > 0.9);
+        assert!(magnetization(100.0) < 0.6);
+    }
+
+    #[test]
+    fn table_rule_counts_chosen_state() {
+        // State 2 spreads to empty cells next to exactly one cell in state 2
+        let simulation = r#", "rule": "table", "count_state": 2, "rules": {"0:1": 2}"#;
+        let seed = r#", "initial_regions": [{"min": [2, 2], "max": [3, 3], "value": 2}]"#;
+        let mut simulator = load(simulation, seed).unwrap();
+
+        simulator.step();
+
+        assert_eq!(alive(&simulator).len(), 9);
+        assert!(load(r#", "rule": "table", "rules": {"0-1": 2}"#, "").is_err());
+    }
+
+    #[test]
+    fn setup_errors_point_into_configuration() {
+        let source = r#"{
+            "simulation_config": {"name": "automaton", "rule": "life", "initial_density": "half"},
+            "solver_config": {"timestep": 1.0},
+            "grid": {"shape": [5, 5], "spacing": 1.0}
+        }"#;
+        let Err(Error::Schema(violations)) = AutomatonSimulator::from_source(source, Format::Json) else {
+            panic!("density of wrong type should be a schema violation")
+        };
+        assert_eq!(violations[0].0.path(), "simulation_config.initial_density");
+        assert_eq!(violations[0].1.as_ref().map(|s| s.line), Some(2));
+
+        let periodic = r#", "periodic": [true, true]"#;
+        assert!(matches!(load(r#", "rule": "sandpile""#, periodic), Err(Error::Semantic { .. })));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn configuration_is_read_from_toml() {
+        let source = r#"
+            seed = 1
+            periodic = [true, true]
+
+            [simulation_config]
+            name = "automaton"
+            rule = "life"
+
+            [solver_config]
+            timestep = 1.0
+
+            [grid]
+            shape = [5, 5]
+            spacing = 1.0
+        "#;
+        let simulator = AutomatonSimulator::from_source(source, Format::Toml).unwrap();
+        assert_eq!(simulator.grid().len(), 25);
+    }
+}
@@ -0,0 +1,176 @@
+pub mod proto {
+    use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+
+    use crate::{
+        boundary::proto::Boundary,
+        particle::proto::ParticleProto,
+        Property, PropertyMap, SimFloat,
+    };
+
+    /// Largest relative change of volume in a single Berendsen step
+    const MAX_VOLUME_CHANGE: SimFloat = 0.1;
+
+    /// Pressure from the virial theorem: P = (sum m v^2 + sum r_ij . F_ij) / (d V),
+    /// where `virial` is the pair sum over i < j of every particle in `objects`
+    pub fn pressure<'a, const N: usize>(
+        objects: impl IntoIterator<Item = &'a ParticleProto<N>>,
+        virial: SimFloat,
+        volume: SimFloat,
+    ) -> SimFloat {
+        let twice_kinetic = objects.into_iter()
+            .fold(0.0, |a, p| a + p.mass() * p.velocity.magnitude_squared());
+        (twice_kinetic + virial) / (N as SimFloat * volume)
+    }
+
+    pub enum Barostat {
+        /// Weak coupling of box size to target pressure
+        Berendsen {
+            target_pressure: SimFloat,
+            tau: SimFloat,
+            compressibility: SimFloat,
+        },
+        /// Isotropic Parrinello-Rahman-style box dynamics with box "mass" `mass`
+        ParrinelloRahman {
+            target_pressure: SimFloat,
+            mass: SimFloat,
+            strain_rate: SimFloat,
+        },
+    }
+
+    impl Barostat {
+        /// Reads barostat from `barostat` entry of simulation config
+        pub fn from_property(property: &Property) -> IoResult<Self> {
+            let config = property.try_nested().ok_or_else(|| IoError::new(
+                ErrorKind::InvalidData,
+                "Barostat should be an object",
+            ))?;
+
+            let kind = config.get_str_opt("type")?.unwrap_or_default();
+            let target_pressure = config.get_float("target_pressure")?;
+
+            match kind {
+                "berendsen" => Ok(Barostat::Berendsen {
+                    target_pressure,
+                    tau: config.get_float("tau")?,
+                    compressibility: config.get_float_or("compressibility", 1.0)?,
+                }),
+                "parrinello_rahman" => Ok(Barostat::ParrinelloRahman {
+                    target_pressure,
+                    mass: config.get_float("mass")?,
+                    strain_rate: 0.0,
+                }),
+                _ => Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown barostat type `{kind}`"),
+                )),
+            }
+        }
+
+        pub fn target_pressure(&self) -> SimFloat {
+            match self {
+                Barostat::Berendsen { target_pressure, .. } => *target_pressure,
+                Barostat::ParrinelloRahman { target_pressure, .. } => *target_pressure,
+            }
+        }
+
+        /// Rescales box and particle positions given current `pressure`
+        pub fn apply<'a, const N: usize>(
+            &mut self,
+            boundary: &mut Boundary<N>,
+            objects: impl IntoIterator<Item = &'a mut ParticleProto<N>>,
+            pressure: SimFloat,
+            delta: SimFloat,
+        ) {
+            let (length_scale, velocity_scale) = match self {
+                Barostat::Berendsen { target_pressure, tau, compressibility } => {
+                    // Far from the target the linear response would collapse or blow up the box
+                    let volume_scale = (1.0 - *compressibility * delta / *tau * (*target_pressure - pressure))
+                        .clamp(1.0 - MAX_VOLUME_CHANGE, 1.0 + MAX_VOLUME_CHANGE);
+                    (volume_scale.powf(1.0 / N as SimFloat), 1.0)
+                }
+                Barostat::ParrinelloRahman { target_pressure, mass, strain_rate } => {
+                    *strain_rate += delta * boundary.volume() * (pressure - *target_pressure) / *mass;
+                    ((*strain_rate * delta).exp(), (-*strain_rate * delta).exp())
+                }
+            };
+
+            boundary.max = boundary.min + boundary.size() * length_scale;
+            for p in objects {
+                p.position = boundary.min + (p.position - boundary.min) * length_scale;
+                p.velocity *= velocity_scale;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra as na;
+
+    use super::proto::*;
+    use crate::{
+        boundary::proto::{Boundary, BoundaryKind},
+        particle::proto::ParticleProto,
+        Property,
+    };
+
+    fn particle(position: [f64; 2], velocity: [f64; 2]) -> ParticleProto<2> {
+        let mut particle = ParticleProto::new();
+        particle.position = position.into();
+        particle.velocity = velocity.into();
+        particle.additional_properties.insert("mass".to_string(), Property::Float(2.0));
+        particle
+    }
+
+    fn periodic_box() -> Boundary<2> {
+        Boundary::new(BoundaryKind::Periodic, na::Point2::new(0.0, 0.0), na::Point2::new(2.0, 2.0))
+    }
+
+    #[test]
+    fn pressure_counts_kinetic_term_of_every_particle() {
+        let objects = [particle([0.5, 0.5], [1.0, 0.0]), particle([1.5, 1.5], [0.0, 1.0])];
+
+        // (2 * 1 + 2 * 1 + 4) / (2 * 4)
+        assert_eq!(pressure(&objects, 4.0, 4.0), 1.0);
+        assert_eq!(pressure(&objects[..1], 0.0, 4.0), 0.25);
+    }
+
+    #[test]
+    fn berendsen_scales_box_and_positions_together() {
+        let mut boundary = periodic_box();
+        let mut objects = [particle([1.0, 1.0], [1.0, 0.0])];
+        let mut barostat = Barostat::Berendsen { target_pressure: 1.0, tau: 1.0, compressibility: 1.0 };
+
+        // Pressure above target expands the box by 1 + 0.02 in volume
+        barostat.apply(&mut boundary, objects.iter_mut(), 1.2, 0.1);
+
+        assert!((boundary.volume() - 4.0 * 1.02).abs() < 1e-12);
+        assert!((objects[0].position - na::Point2::new(1.0, 1.0) * 1.02f64.sqrt()).norm() < 1e-12);
+        assert_eq!(objects[0].velocity, na::Vector2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn berendsen_limits_volume_change_per_step() {
+        let mut barostat = Barostat::Berendsen { target_pressure: 1e6, tau: 1.0, compressibility: 1.0 };
+        let mut boundary = periodic_box();
+        barostat.apply(&mut boundary, [], 0.0, 1.0);
+        assert!((boundary.volume() - 4.0 * 0.9).abs() < 1e-12);
+
+        let mut boundary = periodic_box();
+        barostat.apply(&mut boundary, [], 2e6, 1.0);
+        assert!((boundary.volume() - 4.0 * 1.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn parrinello_rahman_expands_box_and_cools_particles_above_target() {
+        let mut boundary = periodic_box();
+        let mut objects = [particle([1.0, 1.0], [1.0, 0.0])];
+        let mut barostat = Barostat::ParrinelloRahman { target_pressure: 0.0, mass: 4.0, strain_rate: 0.0 };
+
+        barostat.apply(&mut boundary, objects.iter_mut(), 1.0, 0.1);
+
+        assert!(boundary.volume() > 4.0);
+        assert!(objects[0].velocity.x < 1.0);
+        assert!(matches!(barostat, Barostat::ParrinelloRahman { strain_rate, .. } if strain_rate > 0.0));
+    }
+}
@@ -0,0 +1,484 @@
+pub mod proto {
+    use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+
+    use nalgebra as na;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{boundary::proto::Boundary, particle::proto::ParticleProto, SimFloat};
+
+    /// Bonded interaction between specific particles, referenced by their `name` property.
+    /// Rest values are taken from initial positions if not specified.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    pub enum BondConfig {
+        /// Hookean spring, optionally damped along its direction
+        Spring {
+            between: [String; 2],
+            stiffness: SimFloat,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            rest_length: Option<SimFloat>,
+            #[serde(default)]
+            damping: SimFloat,
+        },
+        /// Bending spring keeping the angle at the middle particle
+        Angle {
+            between: [String; 3],
+            stiffness: SimFloat,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            rest_angle: Option<SimFloat>,
+        },
+        /// Keeps area of a closed polygon made of particles (2D only)
+        Area {
+            ring: Vec<String>,
+            stiffness: SimFloat,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            rest_area: Option<SimFloat>,
+        },
+        /// Keeps volume of a closed triangle mesh made of particles (3D only).
+        /// Triangles index into `vertices` and should be consistently oriented
+        Volume {
+            vertices: Vec<String>,
+            triangles: Vec<[usize; 3]>,
+            stiffness: SimFloat,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            rest_volume: Option<SimFloat>,
+        },
+    }
+
+    #[derive(Clone, Debug)]
+    pub enum Bond {
+        Spring {
+            particles: [usize; 2],
+            stiffness: SimFloat,
+            rest_length: SimFloat,
+            damping: SimFloat,
+        },
+        Angle {
+            particles: [usize; 3],
+            stiffness: SimFloat,
+            rest_angle: SimFloat,
+        },
+        Area {
+            ring: Vec<usize>,
+            stiffness: SimFloat,
+            rest_area: SimFloat,
+        },
+        Volume {
+            vertices: Vec<usize>,
+            triangles: Vec<[usize; 3]>,
+            stiffness: SimFloat,
+            rest_volume: SimFloat,
+        },
+    }
+
+    fn separation<const N: usize>(
+        boundary: Option<&Boundary<N>>,
+        from: &na::Point<SimFloat, N>,
+        to: &na::Point<SimFloat, N>,
+    ) -> na::SVector<SimFloat, N> {
+        let h = to - from;
+        match boundary {
+            Some(boundary) => boundary.minimum_image(h),
+            None => h,
+        }
+    }
+
+    /// Positions of given particles, unwrapped around the first one
+    fn unwrapped<const N: usize>(
+        objects: &[ParticleProto<N>],
+        indices: &[usize],
+        boundary: Option<&Boundary<N>>,
+    ) -> Vec<na::Point<SimFloat, N>> {
+        let origin = objects[indices[0]].position;
+        indices.iter()
+            .map(|i| origin + separation(boundary, &origin, &objects[*i].position))
+            .collect()
+    }
+
+    fn signed_area(ring: &[na::Point2<SimFloat>]) -> SimFloat {
+        (0..ring.len()).fold(0.0, |a, i| {
+            let p = ring[i];
+            let q = ring[(i + 1) % ring.len()];
+            a + 0.5 * (p.x * q.y - q.x * p.y)
+        })
+    }
+
+    fn signed_volume(vertices: &[na::Point3<SimFloat>], triangles: &[[usize; 3]]) -> SimFloat {
+        triangles.iter().fold(0.0, |a, [i, j, k]| {
+            a + vertices[*i].coords.dot(&vertices[*j].coords.cross(&vertices[*k].coords)) / 6.0
+        })
+    }
+
+    impl Bond {
+        /// Current value of the quantity the bond keeps: length, angle, area or volume
+        pub fn measure<const N: usize>(
+            &self,
+            objects: &[ParticleProto<N>],
+            boundary: Option<&Boundary<N>>,
+        ) -> SimFloat {
+            match self {
+                Bond::Spring { particles: [a, b], .. } => {
+                    separation(boundary, &objects[*a].position, &objects[*b].position).magnitude()
+                }
+                Bond::Angle { particles: [a, b, c], .. } => {
+                    let u = separation(boundary, &objects[*b].position, &objects[*a].position);
+                    let w = separation(boundary, &objects[*b].position, &objects[*c].position);
+                    u.angle(&w)
+                }
+                Bond::Area { ring, .. } => {
+                    let ring = unwrapped(objects, ring, boundary);
+                    signed_area(&ring.iter().map(|p| na::Point2::new(p[0], p[1])).collect::<Vec<_>>())
+                }
+                Bond::Volume { vertices, triangles, .. } => {
+                    let vertices = unwrapped(objects, vertices, boundary);
+                    let vertices = vertices.iter()
+                        .map(|p| na::Point3::new(p[0], p[1], p[2]))
+                        .collect::<Vec<_>>();
+                    signed_volume(&vertices, triangles)
+                }
+            }
+        }
+
+        pub fn energy<const N: usize>(
+            &self,
+            objects: &[ParticleProto<N>],
+            boundary: Option<&Boundary<N>>,
+        ) -> SimFloat {
+            let (stiffness, rest) = match self {
+                Bond::Spring { stiffness, rest_length, .. } => (stiffness, rest_length),
+                Bond::Angle { stiffness, rest_angle, .. } => (stiffness, rest_angle),
+                Bond::Area { stiffness, rest_area, .. } => (stiffness, rest_area),
+                Bond::Volume { stiffness, rest_volume, .. } => (stiffness, rest_volume),
+            };
+
+            let deviation = self.measure(objects, boundary) - rest;
+            0.5 * stiffness * deviation * deviation
+        }
+
+        /// Sets rest value to the currently measured one
+        fn relax<const N: usize>(&mut self, objects: &[ParticleProto<N>], boundary: Option<&Boundary<N>>) {
+            let measured = self.measure(objects, boundary);
+            match self {
+                Bond::Spring { rest_length, .. } => *rest_length = measured,
+                Bond::Angle { rest_angle, .. } => *rest_angle = measured,
+                Bond::Area { rest_area, .. } => *rest_area = measured,
+                Bond::Volume { rest_volume, .. } => *rest_volume = measured,
+            }
+        }
+
+        fn particles_mut(&mut self) -> &mut [usize] {
+            match self {
+                Bond::Spring { particles, .. } => particles,
+                Bond::Angle { particles, .. } => particles,
+                Bond::Area { ring, .. } => ring,
+                Bond::Volume { vertices, .. } => vertices,
+            }
+        }
+
+        /// Updates particle indices after particles were removed.
+        /// Returns false if bond references a removed particle.
+        pub fn remap(&mut self, new_indices: &[Option<usize>]) -> bool {
+            for index in self.particles_mut() {
+                match new_indices[*index] {
+                    Some(new) => *index = new,
+                    None => return false,
+                }
+            }
+
+            true
+        }
+
+        fn add_pair_forces<const N: usize>(
+            &self,
+            objects: &[ParticleProto<N>],
+            forces: &mut [na::SVector<SimFloat, N>],
+            boundary: Option<&Boundary<N>>,
+        ) {
+            match self {
+                Bond::Spring { particles: [a, b], stiffness, rest_length, damping } => {
+                    let h = separation(boundary, &objects[*a].position, &objects[*b].position);
+                    let dst = h.magnitude();
+                    if dst == 0.0 { return }
+                    let dir = h / dst;
+
+                    let relative_velocity = (objects[*b].velocity - objects[*a].velocity).dot(&dir);
+                    let force = (stiffness * (dst - rest_length) + damping * relative_velocity) * dir;
+
+                    forces[*a] += force;
+                    forces[*b] -= force;
+                }
+                Bond::Angle { particles: [a, b, c], stiffness, rest_angle } => {
+                    let u = separation(boundary, &objects[*b].position, &objects[*a].position);
+                    let w = separation(boundary, &objects[*b].position, &objects[*c].position);
+                    let (u_len, w_len) = (u.magnitude(), w.magnitude());
+                    if u_len == 0.0 || w_len == 0.0 { return }
+
+                    let (u, w) = (u / u_len, w / w_len);
+                    let cos = u.dot(&w).clamp(-1.0, 1.0);
+                    let sin = (1.0 - cos * cos).sqrt().max(1e-8);
+                    let angle = cos.acos();
+
+                    let magnitude = stiffness * (angle - rest_angle) / sin;
+                    let force_a = magnitude * (w - cos * u) / u_len;
+                    let force_c = magnitude * (u - cos * w) / w_len;
+
+                    forces[*a] += force_a;
+                    forces[*c] += force_c;
+                    forces[*b] -= force_a + force_c;
+                }
+                Bond::Area { .. } | Bond::Volume { .. } => {}
+            }
+        }
+    }
+
+    pub trait BondForces<const N: usize> {
+        /// Accumulates forces of the bond into `forces`
+        fn add_forces(
+            &self,
+            objects: &[ParticleProto<N>],
+            forces: &mut [na::SVector<SimFloat, N>],
+            boundary: Option<&Boundary<N>>,
+        );
+    }
+
+    impl BondForces<2> for Bond {
+        fn add_forces(
+            &self,
+            objects: &[ParticleProto<2>],
+            forces: &mut [na::Vector2<SimFloat>],
+            boundary: Option<&Boundary<2>>,
+        ) {
+            match self {
+                Bond::Area { ring, stiffness, rest_area } => {
+                    let points = unwrapped(objects, ring, boundary);
+                    let magnitude = -stiffness * (signed_area(&points) - rest_area);
+
+                    for i in 0..ring.len() {
+                        let next = points[(i + 1) % ring.len()];
+                        let prev = points[(i + ring.len() - 1) % ring.len()];
+                        let gradient = 0.5 * na::Vector2::new(next.y - prev.y, prev.x - next.x);
+                        forces[ring[i]] += magnitude * gradient;
+                    }
+                }
+                Bond::Volume { .. } => {}
+                _ => self.add_pair_forces(objects, forces, boundary),
+            }
+        }
+    }
+
+    impl BondForces<3> for Bond {
+        fn add_forces(
+            &self,
+            objects: &[ParticleProto<3>],
+            forces: &mut [na::Vector3<SimFloat>],
+            boundary: Option<&Boundary<3>>,
+        ) {
+            match self {
+                Bond::Volume { vertices, triangles, stiffness, rest_volume } => {
+                    let points = unwrapped(objects, vertices, boundary);
+                    let magnitude = -stiffness * (signed_volume(&points, triangles) - rest_volume);
+
+                    for [i, j, k] in triangles.iter() {
+                        let (pi, pj, pk) = (points[*i].coords, points[*j].coords, points[*k].coords);
+                        forces[vertices[*i]] += magnitude * pj.cross(&pk) / 6.0;
+                        forces[vertices[*j]] += magnitude * pk.cross(&pi) / 6.0;
+                        forces[vertices[*k]] += magnitude * pi.cross(&pj) / 6.0;
+                    }
+                }
+                Bond::Area { .. } => {}
+                _ => self.add_pair_forces(objects, forces, boundary),
+            }
+        }
+    }
+
+    impl BondConfig {
+        /// Resolves particle names into indices with `find` and fills in missing rest values
+        pub fn resolve<const N: usize>(
+            &self,
+            objects: &[ParticleProto<N>],
+            boundary: Option<&Boundary<N>>,
+            find: impl Fn(&str) -> IoResult<usize>,
+        ) -> IoResult<Bond> {
+            match self {
+                BondConfig::Area { .. } if N != 2 => return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    "Area bonds are only supported in 2D simulations",
+                )),
+                BondConfig::Volume { .. } if N != 3 => return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    "Volume bonds are only supported in 3D simulations",
+                )),
+                BondConfig::Area { ring, .. } if ring.len() < 3 => return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Area bond ring should have at least 3 particles, found {}", ring.len()),
+                )),
+                BondConfig::Volume { vertices, triangles, .. } => {
+                    if triangles.is_empty() {
+                        return Err(IoError::new(ErrorKind::InvalidData, "Volume bond should have triangles"));
+                    }
+                    if let Some((i, index)) = triangles.iter()
+                        .enumerate()
+                        .find_map(|(i, t)| t.iter().find(|v| **v >= vertices.len()).map(|v| (i, v)))
+                    {
+                        return Err(IoError::new(
+                            ErrorKind::InvalidData,
+                            format!("Volume bond triangle {i} uses vertex {index}, bond has {} vertices", vertices.len()),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+
+            let resolve_all = |names: &[String]| {
+                names.iter().map(|n| find(n)).collect::<IoResult<Vec<_>>>()
+            };
+
+            let (mut bond, rest) = match self {
+                BondConfig::Spring { between: [a, b], stiffness, rest_length, damping } => (
+                    Bond::Spring {
+                        particles: [find(a)?, find(b)?],
+                        stiffness: *stiffness,
+                        rest_length: rest_length.unwrap_or_default(),
+                        damping: *damping,
+                    },
+                    rest_length,
+                ),
+                BondConfig::Angle { between: [a, b, c], stiffness, rest_angle } => (
+                    Bond::Angle {
+                        particles: [find(a)?, find(b)?, find(c)?],
+                        stiffness: *stiffness,
+                        rest_angle: rest_angle.unwrap_or_default(),
+                    },
+                    rest_angle,
+                ),
+                BondConfig::Area { ring, stiffness, rest_area } => (
+                    Bond::Area {
+                        ring: resolve_all(ring)?,
+                        stiffness: *stiffness,
+                        rest_area: rest_area.unwrap_or_default(),
+                    },
+                    rest_area,
+                ),
+                BondConfig::Volume { vertices, triangles, stiffness, rest_volume } => (
+                    Bond::Volume {
+                        vertices: resolve_all(vertices)?,
+                        triangles: triangles.clone(),
+                        stiffness: *stiffness,
+                        rest_volume: rest_volume.unwrap_or_default(),
+                    },
+                    rest_volume,
+                ),
+            };
+
+            if rest.is_none() {
+                bond.relax(objects, boundary);
+            }
+
+            Ok(bond)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+
+    use nalgebra as na;
+
+    use super::proto::*;
+    use crate::particle::proto::ParticleProto;
+
+    fn particles<const N: usize>(positions: &[[f64; N]]) -> Vec<ParticleProto<N>> {
+        positions.iter()
+            .map(|p| {
+                let mut particle = ParticleProto::new();
+                particle.position = (*p).into();
+                particle
+            })
+            .collect()
+    }
+
+    /// Particles are named by their index
+    fn find(name: &str) -> IoResult<usize> {
+        name.parse().map_err(|_| IoError::new(ErrorKind::InvalidData, "bad name"))
+    }
+
+    fn names(range: std::ops::Range<usize>) -> Vec<String> {
+        range.map(|i| i.to_string()).collect()
+    }
+
+    #[test]
+    fn missing_rest_values_are_taken_from_positions() {
+        let objects = particles(&[[0.0, 0.0], [3.0, 4.0]]);
+        let config = BondConfig::Spring { between: ["0".into(), "1".into()], stiffness: 2.0, rest_length: None, damping: 0.0 };
+
+        let bond = config.resolve(&objects, None, find).unwrap();
+        assert!(matches!(bond, Bond::Spring { rest_length, .. } if (rest_length - 5.0).abs() < 1e-12));
+        assert_eq!(bond.energy(&objects, None), 0.0);
+    }
+
+    #[test]
+    fn stretched_spring_pulls_particles_together() {
+        let objects = particles(&[[0.0, 0.0], [2.0, 0.0]]);
+        let config = BondConfig::Spring { between: ["0".into(), "1".into()], stiffness: 3.0, rest_length: Some(1.0), damping: 0.0 };
+        let bond = config.resolve(&objects, None, find).unwrap();
+
+        let mut forces = vec![na::Vector2::zeros(); 2];
+        bond.add_forces(&objects, &mut forces, None);
+        assert_eq!(forces[0], na::Vector2::new(3.0, 0.0));
+        assert_eq!(forces[1], na::Vector2::new(-3.0, 0.0));
+    }
+
+    #[test]
+    fn compressed_area_bond_pushes_outwards() {
+        let objects = particles(&[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+        let config = BondConfig::Area { ring: names(0..4), stiffness: 1.0, rest_area: Some(2.0) };
+        let bond = config.resolve(&objects, None, find).unwrap();
+        assert!((bond.measure(&objects, None) - 1.0).abs() < 1e-12);
+
+        let mut forces = vec![na::Vector2::zeros(); 4];
+        bond.add_forces(&objects, &mut forces, None);
+        let center = na::Point2::new(0.5, 0.5);
+        for (particle, force) in objects.iter().zip(forces) {
+            assert!(force.dot(&(particle.position - center)) > 0.0);
+        }
+    }
+
+    #[test]
+    fn volume_forces_are_gradient_of_volume() {
+        let objects = particles(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+        let triangles = vec![[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]];
+        let config = BondConfig::Volume { vertices: names(0..4), triangles, stiffness: 1.0, rest_volume: Some(1.0) };
+        let bond = config.resolve(&objects, None, find).unwrap();
+        assert!((bond.measure(&objects, None) - 1.0 / 6.0).abs() < 1e-12);
+
+        let mut forces = vec![na::Vector3::zeros(); 4];
+        bond.add_forces(&objects, &mut forces, None);
+        let total = forces.iter().sum::<na::Vector3<f64>>();
+        assert!(total.norm() < 1e-12);
+        assert!(forces[3].z > 0.0);
+    }
+
+    #[test]
+    fn malformed_rings_and_meshes_are_rejected() {
+        let flat = particles(&[[0.0, 0.0], [1.0, 0.0]]);
+        for ring in [vec![], names(0..2)] {
+            let config = BondConfig::Area { ring, stiffness: 1.0, rest_area: None };
+            assert!(config.resolve(&flat, None, find).is_err());
+        }
+
+        let solid = particles(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+        for triangles in [vec![], vec![[0, 1, 3]]] {
+            let config = BondConfig::Volume { vertices: names(0..3), triangles, stiffness: 1.0, rest_volume: None };
+            assert!(config.resolve(&solid, None, find).is_err());
+        }
+    }
+
+    #[test]
+    fn remap_fails_for_removed_particles() {
+        let mut bond = Bond::Angle { particles: [0, 1, 2], stiffness: 1.0, rest_angle: 1.0 };
+        assert!(bond.remap(&[Some(2), Some(1), Some(0)]));
+        assert!(matches!(bond, Bond::Angle { particles: [2, 1, 0], .. }));
+        assert!(!bond.remap(&[Some(0), None, Some(1)]));
+    }
+}
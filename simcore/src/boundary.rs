@@ -0,0 +1,173 @@
+pub mod proto {
+    use std::io::{Error as IoError, ErrorKind};
+
+    use nalgebra as na;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{particle::proto::ParticleProto, SimFloat};
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum BoundaryKind {
+        /// Particles bounce off the walls of the domain
+        Reflecting,
+        /// Particles leaving the domain are removed from simulation
+        Absorbing,
+        /// Particles leaving the domain reappear on the opposite side
+        Periodic,
+    }
+
+    #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+    pub struct BoundaryConfig {
+        pub kind: BoundaryKind,
+        pub min: [SimFloat; 2],
+        pub max: [SimFloat; 2],
+    }
+
+    /// Axis-aligned box the simulation takes place in
+    #[derive(Clone, Copy, Debug)]
+    pub struct Boundary<const N: usize> {
+        pub kind: BoundaryKind,
+        pub min: na::Point<SimFloat, N>,
+        pub max: na::Point<SimFloat, N>,
+    }
+
+    impl<const N: usize> Boundary<N> {
+        pub fn new(
+            kind: BoundaryKind,
+            min: na::Point<SimFloat, N>,
+            max: na::Point<SimFloat, N>,
+        ) -> Self {
+            Self { kind, min, max }
+        }
+
+        pub fn size(&self) -> na::SVector<SimFloat, N> {
+            self.max - self.min
+        }
+
+        pub fn volume(&self) -> SimFloat {
+            self.size().product()
+        }
+
+        pub fn contains(&self, position: &na::Point<SimFloat, N>) -> bool {
+            (0..N).all(|i| position[i] >= self.min[i] && position[i] <= self.max[i])
+        }
+
+        /// Applies boundary to particle. Returns false if particle was absorbed
+        pub fn apply(&self, particle: &mut ParticleProto<N>) -> bool {
+            match self.kind {
+                BoundaryKind::Absorbing => self.contains(&particle.position),
+                BoundaryKind::Periodic => {
+                    let size = self.size();
+                    for i in 0..N {
+                        particle.position[i] = self.min[i]
+                            + (particle.position[i] - self.min[i]).rem_euclid(size[i]);
+                    }
+                    true
+                }
+                BoundaryKind::Reflecting => {
+                    for i in 0..N {
+                        if particle.position[i] < self.min[i] {
+                            particle.position[i] = 2.0 * self.min[i] - particle.position[i];
+                            particle.velocity[i] = particle.velocity[i].abs();
+                        } else if particle.position[i] > self.max[i] {
+                            particle.position[i] = 2.0 * self.max[i] - particle.position[i];
+                            particle.velocity[i] = -particle.velocity[i].abs();
+                        }
+
+                        // Particle was fast enough to cross the whole domain in one step
+                        particle.position[i] = particle.position[i].clamp(self.min[i], self.max[i]);
+                    }
+                    true
+                }
+            }
+        }
+
+        /// Minimum image of a displacement vector. Only changes anything for periodic boundaries
+        pub fn minimum_image(&self, displacement: na::SVector<SimFloat, N>) -> na::SVector<SimFloat, N> {
+            if self.kind != BoundaryKind::Periodic {
+                return displacement;
+            }
+
+            let size = self.size();
+            let mut result = displacement;
+            for i in 0..N {
+                result[i] -= size[i] * (displacement[i] / size[i]).round();
+            }
+            result
+        }
+    }
+
+    /// Fails unless `min` is below `max` on every axis
+    impl TryFrom<BoundaryConfig> for Boundary<2> {
+        type Error = IoError;
+
+        fn try_from(config: BoundaryConfig) -> Result<Self, IoError> {
+            if (0..2).any(|i| config.min[i].partial_cmp(&config.max[i]) != Some(std::cmp::Ordering::Less)) {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Boundary min {:?} should be below max {:?} on every axis", config.min, config.max),
+                ));
+            }
+
+            Ok(Self::new(config.kind, config.min.into(), config.max.into()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra as na;
+
+    use super::proto::*;
+    use crate::particle::proto::ParticleProto;
+
+    fn boundary(kind: BoundaryKind) -> Boundary<2> {
+        Boundary::try_from(BoundaryConfig { kind, min: [0.0, 0.0], max: [10.0, 5.0] }).unwrap()
+    }
+
+    fn particle(position: [f64; 2], velocity: [f64; 2]) -> ParticleProto<2> {
+        let mut particle = ParticleProto::new();
+        particle.position = position.into();
+        particle.velocity = velocity.into();
+        particle
+    }
+
+    #[test]
+    fn periodic_wraps_to_opposite_side() {
+        let mut p = particle([10.5, -1.0], [1.0, -1.0]);
+        assert!(boundary(BoundaryKind::Periodic).apply(&mut p));
+        assert!((p.position - na::Point2::new(0.5, 4.0)).norm() < 1e-12);
+        assert_eq!(p.velocity, na::Vector2::new(1.0, -1.0));
+    }
+
+    #[test]
+    fn reflecting_mirrors_position_and_velocity() {
+        let mut p = particle([11.0, 2.0], [3.0, 1.0]);
+        assert!(boundary(BoundaryKind::Reflecting).apply(&mut p));
+        assert_eq!(p.position, na::Point2::new(9.0, 2.0));
+        assert_eq!(p.velocity, na::Vector2::new(-3.0, 1.0));
+    }
+
+    #[test]
+    fn absorbing_removes_particles_outside() {
+        let b = boundary(BoundaryKind::Absorbing);
+        assert!(b.apply(&mut particle([5.0, 2.0], [0.0, 0.0])));
+        assert!(!b.apply(&mut particle([5.0, 6.0], [0.0, 0.0])));
+    }
+
+    #[test]
+    fn minimum_image_only_for_periodic() {
+        let h = na::Vector2::new(9.0, -4.0);
+        assert_eq!(boundary(BoundaryKind::Periodic).minimum_image(h), na::Vector2::new(-1.0, 1.0));
+        assert_eq!(boundary(BoundaryKind::Reflecting).minimum_image(h), h);
+    }
+
+    #[test]
+    fn empty_or_inverted_box_is_rejected() {
+        for (min, max) in [([0.0, 0.0], [0.0, 1.0]), ([0.0, 2.0], [1.0, 1.0]), ([0.0, f64::NAN], [1.0, 1.0])] {
+            let config = BoundaryConfig { kind: BoundaryKind::Periodic, min, max };
+            assert!(Boundary::try_from(config).is_err());
+        }
+    }
+}
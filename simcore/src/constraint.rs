@@ -0,0 +1,224 @@
+pub mod proto {
+    use nalgebra as na;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{boundary::proto::Boundary, particle::proto::ParticleProto, SimFloat};
+
+    fn default_tolerance() -> SimFloat {
+        1e-10
+    }
+
+    fn default_max_iterations() -> usize {
+        100
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct DistanceConstraintConfig {
+        /// `name` properties of the two constrained particles
+        pub between: [String; 2],
+        /// Distance to keep. Taken from initial positions if not specified
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub length: Option<SimFloat>,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct ConstraintsConfig {
+        /// Maximum relative deviation of constrained distance
+        #[serde(default = "default_tolerance")]
+        pub tolerance: SimFloat,
+        #[serde(default = "default_max_iterations")]
+        pub max_iterations: usize,
+        #[serde(default)]
+        pub distance: Vec<DistanceConstraintConfig>,
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    pub struct DistanceConstraint {
+        pub first: usize,
+        pub second: usize,
+        pub length: SimFloat,
+    }
+
+    /// Enforces holonomic distance constraints with SHAKE/RATTLE iterations
+    pub struct ConstraintSolver {
+        tolerance: SimFloat,
+        max_iterations: usize,
+        constraints: Vec<DistanceConstraint>,
+    }
+
+    fn inverse_mass<const N: usize>(particle: &ParticleProto<N>) -> SimFloat {
+        let mass = particle.mass();
+
+        1.0 / mass
+    }
+
+    fn separation<const N: usize>(
+        boundary: Option<&Boundary<N>>,
+        from: &na::Point<SimFloat, N>,
+        to: &na::Point<SimFloat, N>,
+    ) -> na::SVector<SimFloat, N> {
+        let h = to - from;
+        match boundary {
+            Some(boundary) => boundary.minimum_image(h),
+            None => h,
+        }
+    }
+
+    impl ConstraintSolver {
+        pub fn new(
+            tolerance: SimFloat,
+            max_iterations: usize,
+            constraints: Vec<DistanceConstraint>,
+        ) -> Self {
+            Self {
+                tolerance,
+                max_iterations,
+                constraints,
+            }
+        }
+
+        pub fn constraints(&self) -> &[DistanceConstraint] {
+            &self.constraints
+        }
+
+        /// Updates particle indices after particles were removed.
+        /// Constraints referencing removed particles are dropped.
+        pub fn remap(&mut self, new_indices: &[Option<usize>]) {
+            self.constraints.retain_mut(|c| {
+                match (new_indices[c.first], new_indices[c.second]) {
+                    (Some(first), Some(second)) => {
+                        c.first = first;
+                        c.second = second;
+                        true
+                    }
+                    _ => false,
+                }
+            });
+        }
+
+        /// SHAKE stage. Corrects positions after an unconstrained step along
+        /// the bond directions at `old_positions`, and velocities accordingly.
+        pub fn shake<const N: usize>(
+            &self,
+            objects: &mut [ParticleProto<N>],
+            old_positions: &[na::Point<SimFloat, N>],
+            boundary: Option<&Boundary<N>>,
+            delta: SimFloat,
+        ) {
+            for _ in 0..self.max_iterations {
+                let mut converged = true;
+
+                for c in self.constraints.iter() {
+                    let r = separation(boundary, &objects[c.second].position, &objects[c.first].position);
+                    let r_old = separation(boundary, &old_positions[c.second], &old_positions[c.first]);
+
+                    let length_squared = c.length * c.length;
+                    let diff = length_squared - r.magnitude_squared();
+                    if diff.abs() <= 2.0 * self.tolerance * length_squared { continue }
+                    converged = false;
+
+                    let w1 = inverse_mass(&objects[c.first]);
+                    let w2 = inverse_mass(&objects[c.second]);
+                    let g = diff / (2.0 * (w1 + w2) * r.dot(&r_old));
+
+                    objects[c.first].position += g * w1 * r_old;
+                    objects[c.second].position -= g * w2 * r_old;
+                    objects[c.first].velocity += g * w1 * r_old / delta;
+                    objects[c.second].velocity -= g * w2 * r_old / delta;
+                }
+
+                if converged { break }
+            }
+        }
+
+        /// RATTLE stage. Removes relative velocity components along constrained bonds.
+        pub fn rattle<const N: usize>(
+            &self,
+            objects: &mut [ParticleProto<N>],
+            boundary: Option<&Boundary<N>>,
+        ) {
+            for _ in 0..self.max_iterations {
+                let mut converged = true;
+
+                for c in self.constraints.iter() {
+                    let r = separation(boundary, &objects[c.second].position, &objects[c.first].position);
+                    let v = objects[c.first].velocity - objects[c.second].velocity;
+
+                    let rv = r.dot(&v);
+                    if rv.abs() <= self.tolerance * c.length * v.magnitude() { continue }
+                    converged = false;
+
+                    let w1 = inverse_mass(&objects[c.first]);
+                    let w2 = inverse_mass(&objects[c.second]);
+                    let k = rv / ((w1 + w2) * r.magnitude_squared());
+
+                    objects[c.first].velocity -= k * w1 * r;
+                    objects[c.second].velocity += k * w2 * r;
+                }
+
+                if converged { break }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra as na;
+
+    use super::proto::*;
+    use crate::{particle::proto::ParticleProto, Property};
+
+    fn particle(position: [f64; 2], velocity: [f64; 2], mass: f64) -> ParticleProto<2> {
+        let mut particle = ParticleProto::new();
+        particle.position = position.into();
+        particle.velocity = velocity.into();
+        particle.additional_properties.insert("mass".to_string(), Property::Float(mass));
+        particle
+    }
+
+    fn solver() -> ConstraintSolver {
+        ConstraintSolver::new(1e-12, 100, vec![DistanceConstraint { first: 0, second: 1, length: 1.0 }])
+    }
+
+    #[test]
+    fn shake_restores_length_and_conserves_momentum() {
+        let old = [na::Point2::new(0.0, 0.0), na::Point2::new(1.0, 0.0)];
+        // Positions after an unconstrained step that stretched the bond
+        let mut objects = [particle([-0.1, 0.05], [-1.0, 0.5], 1.0), particle([1.2, 0.0], [2.0, 0.0], 3.0)];
+        let momentum = |o: &[ParticleProto<2>]| o.iter().map(|p| p.velocity * p.mass()).sum::<na::Vector2<f64>>();
+        let before = momentum(&objects);
+
+        solver().shake(&mut objects, &old, None, 0.1);
+
+        let length = (objects[1].position - objects[0].position).magnitude();
+        assert!((length - 1.0).abs() < 1e-10);
+        assert!((momentum(&objects) - before).norm() < 1e-10);
+    }
+
+    #[test]
+    fn rattle_removes_velocity_along_bond() {
+        let mut objects = [particle([0.0, 0.0], [1.0, 1.0], 1.0), particle([1.0, 0.0], [-1.0, 0.0], 1.0)];
+
+        solver().rattle(&mut objects, None);
+
+        let r = objects[1].position - objects[0].position;
+        let v = objects[1].velocity - objects[0].velocity;
+        assert!(r.dot(&v).abs() < 1e-10);
+        // Perpendicular motion is untouched
+        assert!((objects[0].velocity.y - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn remap_drops_constraints_of_removed_particles() {
+        let mut solver = ConstraintSolver::new(1e-10, 10, vec![
+            DistanceConstraint { first: 0, second: 2, length: 1.0 },
+            DistanceConstraint { first: 1, second: 2, length: 1.0 },
+        ]);
+
+        solver.remap(&[None, Some(0), Some(1)]);
+
+        assert_eq!(solver.constraints().len(), 1);
+        assert_eq!((solver.constraints()[0].first, solver.constraints()[0].second), (0, 1));
+    }
+}
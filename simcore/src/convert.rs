@@ -0,0 +1,146 @@
+use nalgebra as na;
+
+use crate::{Property, PropertyError, PropertyMatrix, SimFloat};
+
+/// Numbers and vectors are column vectors, a Matrix keeps its shape
+impl<const R: usize, const C: usize> TryFrom<&Property> for na::SMatrix<SimFloat, R, C> {
+    type Error = PropertyError;
+
+    fn try_from(property: &Property) -> Result<Self, PropertyError> {
+        if let Property::Matrix(m) = property {
+            if (m.rows(), m.columns()) != (R, C) {
+                return Err(PropertyError::WrongShape {
+                    path: String::new(),
+                    expected: (R, C),
+                    found: (m.rows(), m.columns()),
+                });
+            }
+            return Ok(Self::from_row_slice(m.data()));
+        }
+
+        let components = property.try_components().ok_or_else(|| PropertyError::WrongType {
+            path: String::new(),
+            expected: if C == 1 { "vector" } else { "Matrix" },
+            found: property.type_name(),
+        })?;
+
+        // Row vectors are accepted where a single row is expected
+        let found = (components.len(), 1);
+        if found == (R, C) || (R == 1 && components.len() == C) {
+            Ok(Self::from_column_slice(&components))
+        } else {
+            Err(PropertyError::WrongShape { path: String::new(), expected: (R, C), found })
+        }
+    }
+}
+
+impl<const N: usize> TryFrom<&Property> for na::Point<SimFloat, N> {
+    type Error = PropertyError;
+
+    fn try_from(property: &Property) -> Result<Self, PropertyError> {
+        na::SVector::<SimFloat, N>::try_from(property).map(na::Point::from)
+    }
+}
+
+/// Column vectors of up to four components become Float or VectorN, longer ones
+/// an Array of Floats. Anything else becomes a Matrix
+impl<const R: usize, const C: usize> From<na::SMatrix<SimFloat, R, C>> for Property {
+    fn from(matrix: na::SMatrix<SimFloat, R, C>) -> Self {
+        if C != 1 {
+            let rows = matrix.row_iter().map(|r| r.iter().copied().collect()).collect();
+            return match PropertyMatrix::from_rows(rows) {
+                Ok(m) => Property::Matrix(m),
+                Err(_) => Property::Array(vec![]),
+            };
+        }
+
+        let v = matrix.as_slice();
+        match R {
+            1 => Property::Float(v[0]),
+            2 => Property::Vector2([v[0], v[1]]),
+            3 => Property::Vector3([v[0], v[1], v[2]]),
+            4 => Property::Vector4([v[0], v[1], v[2], v[3]]),
+            _ => Property::Array(v.iter().map(|x| Property::Float(*x)).collect()),
+        }
+    }
+}
+
+impl<const N: usize> From<na::Point<SimFloat, N>> for Property {
+    fn from(point: na::Point<SimFloat, N>) -> Self {
+        point.coords.into()
+    }
+}
+
+impl From<SimFloat> for Property {
+    fn from(value: SimFloat) -> Self {
+        Property::Float(value)
+    }
+}
+
+impl From<i64> for Property {
+    fn from(value: i64) -> Self {
+        Property::Int(value)
+    }
+}
+
+impl From<bool> for Property {
+    fn from(value: bool) -> Self {
+        Property::Bool(value)
+    }
+}
+
+impl From<&str> for Property {
+    fn from(value: &str) -> Self {
+        Property::String(value.to_string())
+    }
+}
+
+impl From<String> for Property {
+    fn from(value: String) -> Self {
+        Property::String(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra as na;
+
+    use crate::{Property, PropertyError, PropertyMatrix};
+
+    #[test]
+    fn vectors_convert_to_points_and_back() {
+        let property = Property::Vector3([1.0, 2.0, 3.0]);
+        let point = na::Point3::try_from(&property).unwrap();
+        assert_eq!(point, na::Point3::new(1.0, 2.0, 3.0));
+        assert_eq!(Property::from(point), property);
+
+        // Integers and longer lists are accepted as components
+        let long = Property::Array((0..5).map(Property::Int).collect());
+        let vector = na::SVector::<f64, 5>::try_from(&long).unwrap();
+        assert_eq!(vector[4], 4.0);
+        assert_eq!(Property::from(vector).type_name(), "Array");
+    }
+
+    #[test]
+    fn matrices_keep_their_shape() {
+        let property = Property::Matrix(PropertyMatrix::from_rows(vec![vec![1.0, 2.0], vec![3.0, 4.0]]).unwrap());
+        let matrix = na::Matrix2::try_from(&property).unwrap();
+        assert_eq!(matrix[(0, 1)], 2.0);
+        assert_eq!(Property::from(matrix), property);
+
+        let row = na::RowVector2::try_from(&Property::Vector2([5.0, 6.0])).unwrap();
+        assert_eq!(row[1], 6.0);
+    }
+
+    #[test]
+    fn mismatched_shapes_and_types_are_reported() {
+        assert!(matches!(
+            na::Point2::try_from(&Property::Vector3([0.0; 3])),
+            Err(PropertyError::WrongShape { expected: (2, 1), found: (3, 1), .. }),
+        ));
+        assert!(matches!(
+            na::Point2::try_from(&Property::String("x".to_string())),
+            Err(PropertyError::WrongType { found: "String", .. }),
+        ));
+    }
+}
@@ -0,0 +1,302 @@
+pub mod proto {
+    use std::{
+        collections::HashMap,
+        io::{Error as IoError, ErrorKind, Result as IoResult},
+    };
+
+    use crate::{
+        field::proto::{scalar_map, Field, FieldMapConfig, FieldSolver, FieldValue},
+        Property, PropertyMap, SimFloat,
+    };
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum DiffusionScheme {
+        /// Forward time, centered space. Only stable below `stable_timestep`
+        Explicit,
+        /// Implicit trapezoidal rule, solved with conjugate gradients
+        CrankNicolson,
+    }
+
+    /// Diffusivity between two cells. Harmonic mean keeps flux continuous across
+    /// jumps in diffusivity and blocks flux into non-diffusing cells
+    pub(crate) fn face_diffusivity(a: SimFloat, b: SimFloat) -> SimFloat {
+        if a + b == 0.0 { 0.0 } else { 2.0 * a * b / (a + b) }
+    }
+
+    /// Writes div(D grad u) of every cell of `field` to `out`
+    pub fn diffusion_operator<T: FieldValue>(
+        field: &Field<T>,
+        diffusivity: &Field<SimFloat>,
+        out: &mut [T],
+    ) {
+        let grid = field.grid;
+        let inv_h2 = 1.0 / (grid.spacing * grid.spacing);
+
+        for (index, cell) in grid.cells().enumerate() {
+            let center = field.get(cell);
+            let d_center = diffusivity.get(cell);
+
+            let mut sum = T::zero();
+            for axis in 0..grid.dimension {
+                for offset in [-1, 1] {
+                    let d_face = face_diffusivity(d_center, diffusivity.neighbour(cell, axis, offset));
+                    sum += (field.neighbour(cell, axis, offset) - center) * d_face;
+                }
+            }
+
+            out[index] = sum * inv_h2;
+        }
+    }
+
+    fn dot<T: FieldValue>(a: &[T], b: &[T]) -> SimFloat {
+        a.iter().zip(b).fold(0.0, |s, (x, y)| s + x.dot(y))
+    }
+
+    pub struct DiffusionSolver {
+        scheme: DiffusionScheme,
+        diffusivity: Field<SimFloat>,
+        /// Relative residual at which conjugate gradients stop
+        tolerance: SimFloat,
+        max_iterations: usize,
+        last_iterations: usize,
+        last_residual: SimFloat,
+    }
+
+    impl DiffusionSolver {
+        /// Fails if explicit scheme would be unstable with timestep `delta`
+        pub fn new(
+            scheme: DiffusionScheme,
+            diffusivity: Field<SimFloat>,
+            delta: SimFloat,
+        ) -> IoResult<Self> {
+            let solver = Self {
+                scheme,
+                diffusivity,
+                tolerance: 1e-10,
+                max_iterations: 1000,
+                last_iterations: 0,
+                last_residual: 0.0,
+            };
+
+            let limit = solver.stable_timestep();
+            if scheme == DiffusionScheme::Explicit && delta > limit {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Timestep {delta} exceeds explicit diffusion stability limit {limit}"),
+                ));
+            }
+
+            Ok(solver)
+        }
+
+        /// Reads `scheme`, `tolerance` and `max_iterations` from simulation config and
+        /// diffusivity from `diffusivity` map or float
+        pub fn from_config<T>(
+            field: &Field<T>,
+            maps: &HashMap<String, FieldMapConfig>,
+            simulation_properties: &HashMap<String, Property>,
+            delta: SimFloat,
+        ) -> IoResult<Self> {
+            let scheme = match simulation_properties.get_str_opt("scheme")? {
+                None | Some("explicit") => DiffusionScheme::Explicit,
+                Some("crank_nicolson") => DiffusionScheme::CrankNicolson,
+                Some(scheme) => return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown diffusion scheme `{scheme}`"),
+                )),
+            };
+
+            let diffusivity = scalar_map(
+                field.grid,
+                &field.boundaries,
+                maps,
+                simulation_properties,
+                "diffusivity",
+            )?;
+
+            let mut solver = Self::new(scheme, diffusivity, delta)?;
+            solver.tolerance = simulation_properties.get_float_or("tolerance", solver.tolerance)?;
+            solver.max_iterations = simulation_properties.get_count_or("max_iterations", solver.max_iterations)?;
+
+            Ok(solver)
+        }
+
+        pub fn scheme(&self) -> DiffusionScheme {
+            self.scheme
+        }
+
+        pub fn diffusivity(&self) -> &Field<SimFloat> {
+            &self.diffusivity
+        }
+
+        /// Largest stable timestep of explicit scheme, h^2 / (2 d D_max)
+        pub fn stable_timestep(&self) -> SimFloat {
+            let grid = self.diffusivity.grid;
+            let max = self.diffusivity.data().iter().fold(0.0, |m: SimFloat, d| m.max(*d));
+
+            grid.spacing * grid.spacing / (2.0 * grid.dimension as SimFloat * max)
+        }
+
+        /// Solves (I - delta/2 L0) u' = u + delta/2 (L u + b) with conjugate gradients.
+        /// L0 is the operator with homogeneous boundaries and b = L(0) its constant boundary part
+        fn step_crank_nicolson<T: FieldValue>(&mut self, field: &mut Field<T>, delta: SimFloat) {
+            let len = field.grid.len();
+            let mut lu = vec![T::zero(); len];
+            diffusion_operator(field, &self.diffusivity, &mut lu);
+
+            let mut boundary_term = vec![T::zero(); len];
+            diffusion_operator(&field.filled_like(T::zero()), &self.diffusivity, &mut boundary_term);
+
+            let rhs = field.data().iter().zip(lu.iter().zip(boundary_term.iter()))
+                .map(|(u, (l, b))| *u + (*l + *b) * (0.5 * delta))
+                .collect::<Vec<_>>();
+
+            let mut scratch = field.homogeneous();
+            let mut apply = |x: &[T], out: &mut [T]| {
+                scratch.data_mut().copy_from_slice(x);
+                diffusion_operator(&scratch, &self.diffusivity, out);
+                for (o, x) in out.iter_mut().zip(x) {
+                    *o = *x - *o * (0.5 * delta);
+                }
+            };
+
+            let mut x = field.data().to_vec();
+            let mut ax = vec![T::zero(); len];
+            apply(&x, &mut ax);
+
+            let mut r = rhs.iter().zip(ax.iter()).map(|(b, a)| *b - *a).collect::<Vec<_>>();
+            let mut p = r.clone();
+            let mut rs = dot(&r, &r);
+            let target = self.tolerance * self.tolerance * dot(&rhs, &rhs);
+
+            let mut iterations = 0;
+            while rs > target && iterations < self.max_iterations {
+                apply(&p, &mut ax);
+                let alpha = rs / dot(&p, &ax);
+                for i in 0..len {
+                    x[i] += p[i] * alpha;
+                    r[i] = r[i] - ax[i] * alpha;
+                }
+
+                let rs_new = dot(&r, &r);
+                for i in 0..len {
+                    p[i] = r[i] + p[i] * (rs_new / rs);
+                }
+                rs = rs_new;
+                iterations += 1;
+            }
+
+            self.last_iterations = iterations;
+            self.last_residual = rs.sqrt();
+            field.data_mut().copy_from_slice(&x);
+        }
+    }
+
+    impl<T: FieldValue> FieldSolver<T> for DiffusionSolver {
+        fn step(
+            &mut self,
+            field: &mut Field<T>,
+            _simulation_properties: &HashMap<String, Property>,
+            delta: SimFloat,
+        ) {
+            match self.scheme {
+                DiffusionScheme::Explicit => {
+                    let mut rates = vec![T::zero(); field.grid.len()];
+                    diffusion_operator(field, &self.diffusivity, &mut rates);
+                    for (value, rate) in field.data_mut().iter_mut().zip(rates) {
+                        *value += rate * delta;
+                    }
+                }
+                DiffusionScheme::CrankNicolson => self.step_crank_nicolson(field, delta),
+            }
+        }
+
+        fn record_stats(&self, _field: &Field<T>, stats: &mut HashMap<String, Property>) {
+            if self.scheme == DiffusionScheme::CrankNicolson {
+                stats.insert("solver_iterations".to_string(), Property::Float(self.last_iterations as SimFloat));
+                stats.insert("solver_residual".to_string(), Property::Float(self.last_residual));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, f64::consts::PI};
+
+    use super::proto::*;
+    use crate::field::proto::{Field, FieldBoundary, FieldSolver, Grid};
+
+    fn field(values: &[f64], boundary: FieldBoundary<f64>) -> Field<f64> {
+        let grid = Grid::new(&[values.len()], 1.0, &[]).unwrap();
+        let mut field = Field::new(grid, [[boundary; 2]; 3], 0.0);
+        field.data_mut().copy_from_slice(values);
+        field
+    }
+
+    fn solver(scheme: DiffusionScheme, diffusivity: &[f64], delta: f64) -> DiffusionSolver {
+        let diffusivity = field(diffusivity, FieldBoundary::Neumann(0.0));
+        DiffusionSolver::new(scheme, diffusivity, delta).unwrap()
+    }
+
+    fn run(solver: &mut DiffusionSolver, field: &mut Field<f64>, delta: f64, steps: usize) {
+        for _ in 0..steps {
+            solver.step(field, &HashMap::new(), delta);
+        }
+    }
+
+    #[test]
+    fn explicit_scheme_rejects_unstable_timestep() {
+        let diffusivity = field(&[1.0, 2.0, 1.0], FieldBoundary::Neumann(0.0));
+        // h^2 / (2 d D_max) = 0.25
+        assert!(DiffusionSolver::new(DiffusionScheme::Explicit, diffusivity.clone(), 0.3).is_err());
+        assert!(DiffusionSolver::new(DiffusionScheme::CrankNicolson, diffusivity, 0.3).is_ok());
+    }
+
+    #[test]
+    fn insulated_diffusion_conserves_total() {
+        for scheme in [DiffusionScheme::Explicit, DiffusionScheme::CrankNicolson] {
+            let mut u = field(&[0.0, 0.0, 4.0, 0.0, 1.0], FieldBoundary::Neumann(0.0));
+            run(&mut solver(scheme, &[1.0; 5], 0.1), &mut u, 0.1, 50);
+
+            let total = u.data().iter().sum::<f64>();
+            assert!((total - 5.0).abs() < 1e-8, "{scheme:?}: {total}");
+            assert!(u.data().iter().all(|v| (v - 1.0).abs() < 0.5));
+        }
+    }
+
+    #[test]
+    fn zero_diffusivity_blocks_flux() {
+        let mut u = field(&[1.0, 1.0, 0.0, 0.0, 0.0], FieldBoundary::Neumann(0.0));
+        run(&mut solver(DiffusionScheme::Explicit, &[1.0, 1.0, 0.0, 1.0, 1.0], 0.1), &mut u, 0.1, 20);
+
+        assert_eq!(u.data()[2..], [0.0; 3]);
+    }
+
+    #[test]
+    fn schemes_decay_fourier_mode_at_analytic_rate() {
+        let n = 32;
+        let k = 2.0 * PI / n as f64;
+        let initial = (0..n).map(|i| (k * i as f64).cos()).collect::<Vec<_>>();
+        // Decay of the discrete Laplacian eigenmode: exp(-D t (2 - 2 cos k) / h^2)
+        let expected = (-(2.0 - 2.0 * k.cos()) * 2.0).exp();
+
+        for (scheme, delta) in [(DiffusionScheme::Explicit, 0.01), (DiffusionScheme::CrankNicolson, 0.1)] {
+            let mut u = field(&initial, FieldBoundary::Periodic);
+            run(&mut solver(scheme, &vec![1.0; n], delta), &mut u, delta, (2.0 / delta).round() as usize);
+
+            assert!((u.data()[0] - expected).abs() < 1e-3, "{scheme:?}: {} vs {expected}", u.data()[0]);
+        }
+    }
+
+    #[test]
+    fn fixed_ends_relax_to_linear_profile() {
+        let mut u = field(&[0.0; 4], FieldBoundary::Periodic);
+        u.boundaries[0] = [FieldBoundary::Dirichlet(0.0), FieldBoundary::Dirichlet(5.0)];
+        run(&mut solver(DiffusionScheme::CrankNicolson, &[1.0; 4], 1.0), &mut u, 1.0, 200);
+
+        for (i, v) in u.data().iter().enumerate() {
+            assert!((v - (i + 1) as f64).abs() < 1e-6, "{:?}", u.data());
+        }
+    }
+}
@@ -0,0 +1,403 @@
+use std::fmt;
+
+use crate::{format::Format, schema::SchemaViolations, units::UnitError, PropertyError};
+
+/// Line of a configuration with the column an error points to
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snippet {
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in characters
+    pub column: usize,
+    pub text: String,
+}
+
+impl Snippet {
+    /// Snippet at `line` and `column` of `source`. None if the line doesn't exist
+    pub fn at(source: &str, line: usize, column: usize) -> Option<Self> {
+        let text = source.lines().nth(line.checked_sub(1)?)?;
+        Some(Self { line, column: column.max(1), text: text.to_string() })
+    }
+
+    /// Snippet at the value under key path `path` of JSON `source`, e.g.
+    /// `initial_objects[2].mass`. Points to the deepest enclosing value when the
+    /// path doesn't exist. None for an empty path
+    pub fn locate(source: &str, path: &str) -> Option<Self> {
+        if path.is_empty() { return None }
+        let offset = JsonScanner { source: source.as_bytes(), position: 0 }.find(path)?;
+        let (line, column) = line_column(source, offset);
+        Self::at(source, line, column)
+    }
+}
+
+/// 1-based line and column of byte `offset` in `source`
+pub(crate) fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+impl fmt::Display for Snippet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gutter = " ".repeat(self.line.to_string().len());
+        // Tabs are kept so the caret lines up with the text
+        let indent = self.text.chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        writeln!(f, "{gutter}--> line {}, column {}", self.line, self.column)?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{} | {}", self.line, self.text)?;
+        write!(f, "{gutter} | {indent}^")
+    }
+}
+
+/// Walks JSON text without building values, just far enough to find a key path
+struct JsonScanner<'a> {
+    source: &'a [u8],
+    position: usize,
+}
+
+impl JsonScanner<'_> {
+    /// Byte offset of the value at `path`
+    fn find(&mut self, path: &str) -> Option<usize> {
+        self.skip_whitespace();
+        let mut found = self.position;
+
+        for segment in path.split('.').filter(|s| !s.is_empty()) {
+            let (key, indices) = match segment.find('[') {
+                Some(i) => (&segment[..i], &segment[i..]),
+                None => (segment, ""),
+            };
+
+            if !key.is_empty() {
+                if !self.enter_key(key) { return Some(found) }
+                found = self.position;
+            }
+
+            for index in indices.split(['[', ']']).filter(|s| !s.is_empty()) {
+                let Ok(index) = index.parse() else { return Some(found) };
+                if !self.enter_index(index) { return Some(found) }
+                found = self.position;
+            }
+        }
+
+        (found < self.source.len()).then_some(found)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.source.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    /// Skips a string starting at the current quote and returns its raw contents
+    fn string(&mut self) -> Option<&[u8]> {
+        let start = self.position + 1;
+        self.position = start;
+        while let Some(c) = self.peek() {
+            self.position += 1;
+            match c {
+                b'\\' => self.position += 1,
+                b'"' => return Some(&self.source[start..self.position - 1]),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'"' => { self.string()?; }
+            b'{' | b'[' => {
+                let mut depth = 0;
+                loop {
+                    match self.peek()? {
+                        b'"' => { self.string()?; continue }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => depth -= 1,
+                        _ => {}
+                    }
+                    self.position += 1;
+                    if depth == 0 { break }
+                }
+            }
+            _ => while self.peek().is_some_and(|c| !matches!(c, b',' | b'}' | b']') && !c.is_ascii_whitespace()) {
+                self.position += 1;
+            },
+        }
+        self.skip_whitespace();
+        Some(())
+    }
+
+    /// Moves to the value of `key` in the object at the current position
+    fn enter_key(&mut self, key: &str) -> bool {
+        let start = self.position;
+        if self.peek() != Some(b'{') { return false }
+        self.position += 1;
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') { break }
+            let Some(name) = self.string() else { break };
+            let matched = name == key.as_bytes();
+
+            self.skip_whitespace();
+            if self.peek() != Some(b':') { break }
+            self.position += 1;
+            self.skip_whitespace();
+            if matched { return true }
+
+            if self.skip_value().is_none() || self.peek() != Some(b',') { break }
+            self.position += 1;
+        }
+
+        self.position = start;
+        false
+    }
+
+    /// Moves to element `index` of the array at the current position
+    fn enter_index(&mut self, index: usize) -> bool {
+        let start = self.position;
+        if self.peek() != Some(b'[') { return false }
+        self.position += 1;
+        self.skip_whitespace();
+
+        for _ in 0..index {
+            if self.skip_value().is_none() || self.peek() != Some(b',') {
+                self.position = start;
+                return false;
+            }
+            self.position += 1;
+            self.skip_whitespace();
+        }
+
+        if matches!(self.peek(), None | Some(b']')) {
+            self.position = start;
+            return false;
+        }
+        true
+    }
+}
+
+/// Failure to load a configuration
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    /// Malformed configuration text, or a value of the wrong shape for the configuration.
+    /// Line and column are 1-based, 0 when unknown
+    Parse {
+        message: String,
+        line: usize,
+        column: usize,
+        snippet: Option<Snippet>,
+    },
+    /// Every property violating the schemas
+    Schema(Vec<(PropertyError, Option<Snippet>)>),
+    /// Well-formed configuration that can't be simulated, e.g. a particle without velocity
+    Semantic {
+        path: String,
+        message: String,
+        snippet: Option<Snippet>,
+    },
+}
+
+impl Error {
+    pub fn semantic(path: &str, message: impl fmt::Display) -> Self {
+        Error::Semantic { path: path.to_string(), message: message.to_string(), snippet: None }
+    }
+
+    /// Error of a grid engine set up from a parsed configuration. Wrapped property
+    /// errors keep their path, anything else applies to the whole configuration
+    pub(crate) fn from_setup(error: std::io::Error) -> Self {
+        match error.get_ref().and_then(|e| e.downcast_ref::<PropertyError>()) {
+            Some(e) => e.clone().into(),
+            None => Error::semantic("", error),
+        }
+    }
+
+    /// Attaches snippets of `source` the error was found in. Key paths are
+    /// only located in JSON
+    pub fn located(mut self, source: &str, format: Format) -> Self {
+        let locate = |path: &str| match format {
+            Format::Json => Snippet::locate(source, path),
+            _ => None,
+        };
+
+        match &mut self {
+            Error::Io(_) => {}
+            Error::Parse { line, column, snippet, .. } => *snippet = Snippet::at(source, *line, *column),
+            Error::Schema(violations) => for (violation, snippet) in violations.iter_mut() {
+                *snippet = locate(violation.path());
+            },
+            Error::Semantic { path, snippet, .. } => *snippet = locate(path),
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write_snippet = |f: &mut fmt::Formatter<'_>, snippet: &Option<Snippet>, indent: &str| {
+            match snippet {
+                Some(snippet) => snippet.to_string().lines().try_for_each(|l| write!(f, "\n{indent}{l}")),
+                None => Ok(()),
+            }
+        };
+
+        match self {
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Parse { message, line: 0, .. } => write!(f, "Parse error: {message}"),
+            Error::Parse { message, line, column, snippet } => {
+                write!(f, "Parse error at line {line}, column {column}: {message}")?;
+                write_snippet(f, snippet, "")
+            }
+            Error::Schema(violations) => {
+                write!(f, "{} schema violation(s):", violations.len())?;
+                for (violation, snippet) in violations {
+                    write!(f, "\n  {violation}")?;
+                    write_snippet(f, snippet, "    ")?;
+                }
+                Ok(())
+            }
+            Error::Semantic { path, message, snippet } => {
+                match path.as_str() {
+                    "" => write!(f, "{message}")?,
+                    path => write!(f, "`{path}`: {message}")?,
+                }
+                write_snippet(f, snippet, "")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        if error.is_io() {
+            return Error::Io(error.into());
+        }
+
+        let message = error.to_string();
+        let location = format!(" at line {} column {}", error.line(), error.column());
+        Error::Parse {
+            message: message.strip_suffix(&location).unwrap_or(&message).to_string(),
+            line: error.line(),
+            column: error.column(),
+            snippet: None,
+        }
+    }
+}
+
+impl From<PropertyError> for Error {
+    fn from(error: PropertyError) -> Self {
+        Error::Schema(vec![(error, None)])
+    }
+}
+
+impl From<SchemaViolations> for Error {
+    fn from(violations: SchemaViolations) -> Self {
+        Error::Schema(violations.0.into_iter().map(|v| (v, None)).collect())
+    }
+}
+
+impl From<UnitError> for Error {
+    fn from(error: UnitError) -> Self {
+        Error::semantic(&error.path, error.message)
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(e) => e,
+            e => std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"{
+    "name": "a \"quoted\" [name]",
+    "initial_objects": [
+        {"mass": 1.0},
+        {"mass": {"value": 2.0}, "radius": [1, 2]}
+    ]
+}"#;
+
+    #[test]
+    fn key_paths_are_located_in_json() {
+        let snippet = Snippet::locate(SOURCE, "initial_objects[1].radius").unwrap();
+        assert_eq!((snippet.line, snippet.column), (5, 44));
+
+        let snippet = Snippet::locate(SOURCE, "initial_objects[0].mass").unwrap();
+        assert_eq!((snippet.line, snippet.column), (4, 18));
+
+        // Strings with escapes and brackets are skipped whole
+        let snippet = Snippet::locate(SOURCE, "initial_objects").unwrap();
+        assert_eq!(snippet.line, 3);
+    }
+
+    #[test]
+    fn missing_paths_point_to_deepest_enclosing_value() {
+        let snippet = Snippet::locate(SOURCE, "initial_objects[1].velocity").unwrap();
+        assert_eq!((snippet.line, snippet.column), (5, 9));
+
+        let snippet = Snippet::locate(SOURCE, "initial_objects[7]").unwrap();
+        assert_eq!((snippet.line, snippet.column), (3, 24));
+
+        assert!(Snippet::locate(SOURCE, "").is_none());
+    }
+
+    #[test]
+    fn snippet_renders_caret_under_column() {
+        let snippet = Snippet::at("a\n\tbad: 1", 2, 3).unwrap();
+        assert_eq!(snippet.to_string(), " --> line 2, column 3\n  |\n2 | \tbad: 1\n  | \t ^");
+        assert!(Snippet::at("a", 2, 1).is_none());
+    }
+
+    #[test]
+    fn errors_render_path_message_and_snippet() {
+        let error = Error::semantic("initial_objects[0]", "Particle has no `velocity`").located(SOURCE, Format::Json);
+        let text = error.to_string();
+        assert!(text.starts_with("`initial_objects[0]`: Particle has no `velocity`\n"), "{text}");
+        assert!(text.ends_with("4 |         {\"mass\": 1.0},\n  |         ^"), "{text}");
+
+        let error = Error::from(PropertyError::Missing { path: "name".to_string() });
+        assert_eq!(error.to_string(), "1 schema violation(s):\n  Missing property `name`");
+
+        let error = Error::Parse { message: "EOF".to_string(), line: 0, column: 0, snippet: None };
+        assert_eq!(error.to_string(), "Parse error: EOF");
+    }
+
+    #[test]
+    fn parse_errors_carry_line_and_column() {
+        let source = "{\n  \"a\": 1,,\n}";
+        let error = Error::from(serde_json::from_str::<serde_json::Value>(source).unwrap_err()).located(source, Format::Json);
+        let Error::Parse { line, snippet, .. } = &error else { panic!("{error}") };
+        assert_eq!(*line, 2);
+        assert_eq!(snippet.as_ref().unwrap().text, "  \"a\": 1,,");
+    }
+}
@@ -0,0 +1,337 @@
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+
+use crate::SimFloat;
+
+/// Meaning of a name appearing in an expression
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Symbol {
+    /// Value taken from the slice passed to `Expression::evaluate`
+    Variable(usize),
+    Constant(SimFloat),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Function {
+    Exp,
+    Ln,
+    Sqrt,
+    Abs,
+    Sin,
+    Cos,
+    Tan,
+    Tanh,
+    Min,
+    Max,
+    Pow,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<(Self, usize)> {
+        Some(match name {
+            "exp" => (Function::Exp, 1),
+            "ln" | "log" => (Function::Ln, 1),
+            "sqrt" => (Function::Sqrt, 1),
+            "abs" => (Function::Abs, 1),
+            "sin" => (Function::Sin, 1),
+            "cos" => (Function::Cos, 1),
+            "tan" => (Function::Tan, 1),
+            "tanh" => (Function::Tanh, 1),
+            "min" => (Function::Min, 2),
+            "max" => (Function::Max, 2),
+            "pow" => (Function::Pow, 2),
+            _ => return None,
+        })
+    }
+
+    fn apply(&self, a: SimFloat, b: SimFloat) -> SimFloat {
+        match self {
+            Function::Exp => a.exp(),
+            Function::Ln => a.ln(),
+            Function::Sqrt => a.sqrt(),
+            Function::Abs => a.abs(),
+            Function::Sin => a.sin(),
+            Function::Cos => a.cos(),
+            Function::Tan => a.tan(),
+            Function::Tanh => a.tanh(),
+            Function::Min => a.min(b),
+            Function::Max => a.max(b),
+            Function::Pow => a.powf(b),
+        }
+    }
+}
+
+/// Arithmetic expression compiled from text, e.g. `-u*v^2 + feed*(1 - u)`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Number(SimFloat),
+    Variable(usize),
+    Negate(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    Power(Box<Expression>, Box<Expression>),
+    Call(Function, Vec<Expression>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(SimFloat),
+    Name(String),
+    Operator(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, String> {
+    let chars = text.char_indices().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (position, c) = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].1.is_ascii_digit() || chars[i].1 == '.') {
+                i += 1;
+            }
+            // Exponent, e.g. 1e-3
+            if i < chars.len() && matches!(chars[i].1, 'e' | 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && matches!(chars[j].1, '+' | '-') { j += 1 }
+                if j < chars.len() && chars[j].1.is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].1.is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+
+            let literal = chars[start..i].iter().map(|(_, c)| c).collect::<String>();
+            let value = literal.parse().map_err(|_| format!("invalid number `{literal}` at {position}"))?;
+            tokens.push((position, Token::Number(value)));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].1.is_alphanumeric() || chars[i].1 == '_') {
+                i += 1;
+            }
+            tokens.push((position, Token::Name(chars[start..i].iter().map(|(_, c)| c).collect())));
+        } else if "+-*/^(),".contains(c) {
+            tokens.push((position, Token::Operator(c)));
+            i += 1;
+        } else {
+            return Err(format!("unexpected character `{c}` at {position}"));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a, F> {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    end: usize,
+    resolve: &'a F,
+}
+
+impl<F: Fn(&str) -> Option<Symbol>> Parser<'_, F> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, t)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.next).map(|(p, _)| *p).unwrap_or(self.end)
+    }
+
+    fn eat(&mut self, operator: char) -> bool {
+        if self.peek() == Some(&Token::Operator(operator)) {
+            self.next += 1;
+            true
+        } else { false }
+    }
+
+    fn expect(&mut self, operator: char) -> Result<(), String> {
+        if self.eat(operator) {
+            Ok(())
+        } else {
+            Err(format!("expected `{operator}` at {}", self.position()))
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expression, String> {
+        let mut left = self.product()?;
+        loop {
+            if self.eat('+') {
+                left = Expression::Add(Box::new(left), Box::new(self.product()?));
+            } else if self.eat('-') {
+                left = Expression::Subtract(Box::new(left), Box::new(self.product()?));
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Expression, String> {
+        let mut left = self.unary()?;
+        loop {
+            if self.eat('*') {
+                left = Expression::Multiply(Box::new(left), Box::new(self.unary()?));
+            } else if self.eat('/') {
+                left = Expression::Divide(Box::new(left), Box::new(self.unary()?));
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.eat('-') {
+            Ok(Expression::Negate(Box::new(self.unary()?)))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    /// Right associative, binds tighter than unary minus on its left: -x^2 = -(x^2)
+    fn power(&mut self) -> Result<Expression, String> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            Ok(Expression::Power(Box::new(base), Box::new(self.unary()?)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expression, String> {
+        let position = self.position();
+        let token = self.tokens.get(self.next).map(|(_, t)| t.clone());
+        self.next += 1;
+
+        match token {
+            Some(Token::Number(v)) => Ok(Expression::Number(v)),
+            Some(Token::Operator('(')) => {
+                let inner = self.sum()?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            Some(Token::Name(name)) => {
+                if self.eat('(') {
+                    let (function, arity) = Function::from_name(&name)
+                        .ok_or_else(|| format!("unknown function `{name}` at {position}"))?;
+
+                    let mut arguments = vec![self.sum()?];
+                    while self.eat(',') {
+                        arguments.push(self.sum()?);
+                    }
+                    self.expect(')')?;
+
+                    if arguments.len() != arity {
+                        return Err(format!("`{name}` takes {arity} arguments, got {}", arguments.len()));
+                    }
+                    return Ok(Expression::Call(function, arguments));
+                }
+
+                match (self.resolve)(&name) {
+                    Some(Symbol::Variable(i)) => Ok(Expression::Variable(i)),
+                    Some(Symbol::Constant(v)) => Ok(Expression::Number(v)),
+                    None => Err(format!("unknown name `{name}` at {position}")),
+                }
+            }
+            Some(Token::Operator(c)) => Err(format!("unexpected `{c}` at {position}")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+impl Expression {
+    /// Parses `text`, looking names up with `resolve`
+    pub fn parse(text: &str, resolve: &impl Fn(&str) -> Option<Symbol>) -> IoResult<Self> {
+        let error = |message: String| IoError::new(
+            ErrorKind::InvalidData,
+            format!("Expression `{text}`: {message}"),
+        );
+
+        let mut parser = Parser {
+            tokens: tokenize(text).map_err(error)?,
+            next: 0,
+            end: text.len(),
+            resolve,
+        };
+
+        let expression = parser.sum().map_err(error)?;
+        if parser.next < parser.tokens.len() {
+            return Err(error(format!("unexpected input at {}", parser.position())));
+        }
+
+        Ok(expression)
+    }
+
+    pub fn evaluate(&self, variables: &[SimFloat]) -> SimFloat {
+        match self {
+            Expression::Number(v) => *v,
+            Expression::Variable(i) => variables[*i],
+            Expression::Negate(a) => -a.evaluate(variables),
+            Expression::Add(a, b) => a.evaluate(variables) + b.evaluate(variables),
+            Expression::Subtract(a, b) => a.evaluate(variables) - b.evaluate(variables),
+            Expression::Multiply(a, b) => a.evaluate(variables) * b.evaluate(variables),
+            Expression::Divide(a, b) => a.evaluate(variables) / b.evaluate(variables),
+            Expression::Power(a, b) => {
+                let base = a.evaluate(variables);
+                match b.as_ref() {
+                    // Integer powers are common in reaction terms and much cheaper
+                    Expression::Number(e) if e.fract() == 0.0 && e.abs() < 16.0 => base.powi(*e as i32),
+                    b => base.powf(b.evaluate(variables)),
+                }
+            }
+            Expression::Call(function, arguments) => function.apply(
+                arguments[0].evaluate(variables),
+                arguments.get(1).map(|a| a.evaluate(variables)).unwrap_or(0.0),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(name: &str) -> Option<Symbol> {
+        match name {
+            "u" => Some(Symbol::Variable(0)),
+            "v" => Some(Symbol::Variable(1)),
+            "k" => Some(Symbol::Constant(0.5)),
+            _ => None,
+        }
+    }
+
+    fn eval(text: &str, variables: &[SimFloat]) -> SimFloat {
+        Expression::parse(text, &resolve).unwrap().evaluate(variables)
+    }
+
+    #[test]
+    fn operators_follow_usual_precedence() {
+        assert_eq!(eval("1 + 2 * 3", &[]), 7.0);
+        assert_eq!(eval("(1 + 2) * 3", &[]), 9.0);
+        assert_eq!(eval("-2^2", &[]), -4.0);
+        assert_eq!(eval("2^3^2", &[]), 512.0);
+        assert_eq!(eval("8 / 4 / 2", &[]), 1.0);
+        assert_eq!(eval("1.5e2 - 5e-1", &[]), 149.5);
+    }
+
+    #[test]
+    fn names_resolve_to_variables_and_constants() {
+        // Gray-Scott activator term
+        assert_eq!(eval("-u * v^2 + k * (1 - u)", &[0.5, 2.0]), -2.0 + 0.25);
+        assert_eq!(eval("max(u, v) + pow(v, 0.5)^2", &[1.0, 4.0]), 8.0);
+        assert!((eval("exp(ln(u))", &[3.0]) - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn malformed_expressions_are_rejected() {
+        for text in ["u +", "(u", "u v", "w * 2", "foo(u)", "min(u)", "u # v", "1e"] {
+            assert!(Expression::parse(text, &resolve).is_err(), "{text}");
+        }
+    }
+}
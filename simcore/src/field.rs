@@ -0,0 +1,823 @@
+pub mod proto {
+    use std::{
+        collections::HashMap,
+        io::{Error as IoError, ErrorKind, Result as IoResult},
+        ops::{Add, AddAssign, Mul, Sub},
+    };
+
+    use nalgebra as na;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        diffusion::proto::DiffusionSolver, poisson::proto::PoissonSolver, stats::Timeseries,
+        wave::proto::WaveSolver, Error, Format, Property, PropertyMap, SimFloat,
+    };
+
+    /// Value stored in a field cell
+    pub trait FieldValue:
+        Copy
+        + std::fmt::Debug
+        + Add<Output = Self>
+        + Sub<Output = Self>
+        + Mul<SimFloat, Output = Self>
+        + AddAssign
+    {
+        /// Number of scalar components
+        const COMPONENTS: usize;
+
+        fn zero() -> Self;
+        fn from_components(f: impl FnMut(usize) -> SimFloat) -> Self;
+        fn component(&self, index: usize) -> SimFloat;
+        fn from_property(property: &Property) -> Option<Self>;
+        fn to_property(&self) -> Property;
+        fn dot(&self, other: &Self) -> SimFloat;
+
+        fn magnitude(&self) -> SimFloat {
+            self.dot(self).sqrt()
+        }
+    }
+
+    impl FieldValue for SimFloat {
+        const COMPONENTS: usize = 1;
+
+        fn zero() -> Self {
+            0.0
+        }
+
+        fn from_components(mut f: impl FnMut(usize) -> SimFloat) -> Self {
+            f(0)
+        }
+
+        fn component(&self, _index: usize) -> SimFloat {
+            *self
+        }
+
+        fn from_property(property: &Property) -> Option<Self> {
+            property.try_float()
+        }
+
+        fn to_property(&self) -> Property {
+            Property::Float(*self)
+        }
+
+        fn dot(&self, other: &Self) -> SimFloat {
+            self * other
+        }
+
+        fn magnitude(&self) -> SimFloat {
+            self.abs()
+        }
+    }
+
+    impl<const K: usize> FieldValue for na::SVector<SimFloat, K> {
+        const COMPONENTS: usize = K;
+
+        fn zero() -> Self {
+            Self::zeros()
+        }
+
+        fn from_components(mut f: impl FnMut(usize) -> SimFloat) -> Self {
+            Self::from_fn(|i, _| f(i))
+        }
+
+        fn component(&self, index: usize) -> SimFloat {
+            self[index]
+        }
+
+        fn from_property(property: &Property) -> Option<Self> {
+            Self::try_from(property).ok()
+        }
+
+        fn to_property(&self) -> Property {
+            (*self).into()
+        }
+
+        fn dot(&self, other: &Self) -> SimFloat {
+            na::Matrix::dot(self, other)
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+    pub struct FieldSolverConfig {
+        pub timestep: SimFloat,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct GridConfig {
+        /// Cell count along each axis. Length of 1 to 3 sets dimension of the grid
+        pub shape: Vec<usize>,
+        pub spacing: SimFloat,
+        /// Position of the first cell. Defaults to zero
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub origin: Vec<SimFloat>,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    pub enum FieldBoundaryConfig {
+        Periodic,
+        /// Fixed outward derivative. Zero (insulating) if not specified
+        Neumann {
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            gradient: Option<Property>,
+        },
+        /// Fixed value outside of the grid
+        Dirichlet {
+            value: Property,
+        },
+    }
+
+    /// Either one boundary for all sides or a `[low, high]` pair per axis
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(untagged)]
+    pub enum FieldBoundarySpec {
+        Uniform(FieldBoundaryConfig),
+        PerAxis(Vec<[FieldBoundaryConfig; 2]>),
+    }
+
+    /// Box of cells `min..max` (exclusive) set to `value`
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct FieldRegion {
+        pub min: Vec<usize>,
+        pub max: Vec<usize>,
+        pub value: Property,
+    }
+
+    /// Named scalar map over the grid, such as diffusivity
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct FieldMapConfig {
+        /// Value outside of all regions
+        pub value: SimFloat,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub regions: Vec<FieldRegion>,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct FieldConfiguration {
+        simulation_config: HashMap<String, Property>,
+        solver_config: FieldSolverConfig,
+        grid: GridConfig,
+        boundary: FieldBoundarySpec,
+        /// Value of every cell before regions are applied. Defaults to zero
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        initial_value: Option<Property>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        initial_regions: Vec<FieldRegion>,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        maps: HashMap<String, FieldMapConfig>,
+    }
+
+    impl FieldConfiguration {
+        pub fn save(&self, filename: &str) -> IoResult<()> {
+            let stringified = serde_json::to_string_pretty(self)?;
+
+            std::fs::write(filename, stringified)
+        }
+    }
+
+    /// Regular grid of up to 3 dimensions. Unused axes have size 1
+    #[derive(Clone, Copy, Debug)]
+    pub struct Grid {
+        pub shape: [usize; 3],
+        pub dimension: usize,
+        pub spacing: SimFloat,
+        pub origin: [SimFloat; 3],
+    }
+
+    impl Grid {
+        pub fn new(shape: &[usize], spacing: SimFloat, origin: &[SimFloat]) -> IoResult<Self> {
+            if shape.is_empty() || shape.len() > 3 || shape.contains(&0) {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Grid shape should have 1 to 3 non-zero sizes, got {shape:?}"),
+                ));
+            }
+
+            if !(spacing.is_finite() && spacing > 0.0) {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Grid spacing should be a positive number, got {spacing}"),
+                ));
+            }
+
+            if !origin.is_empty() && origin.len() != shape.len() {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    "Grid origin should have the same dimension as shape",
+                ));
+            }
+
+            let mut grid = Self {
+                shape: [1; 3],
+                dimension: shape.len(),
+                spacing,
+                origin: [0.0; 3],
+            };
+            grid.shape[..shape.len()].copy_from_slice(shape);
+            grid.origin[..origin.len()].copy_from_slice(origin);
+
+            Ok(grid)
+        }
+
+        pub fn len(&self) -> usize {
+            self.shape.iter().product()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        pub fn index(&self, cell: [usize; 3]) -> usize {
+            (cell[2] * self.shape[1] + cell[1]) * self.shape[0] + cell[0]
+        }
+
+        pub fn cell(&self, index: usize) -> [usize; 3] {
+            [
+                index % self.shape[0],
+                index / self.shape[0] % self.shape[1],
+                index / (self.shape[0] * self.shape[1]),
+            ]
+        }
+
+        /// World position of the cell
+        pub fn position(&self, cell: [usize; 3]) -> [SimFloat; 3] {
+            std::array::from_fn(|a| self.origin[a] + cell[a] as SimFloat * self.spacing)
+        }
+
+        /// Length, area or volume of one cell depending on dimension
+        pub fn cell_volume(&self) -> SimFloat {
+            self.spacing.powi(self.dimension as i32)
+        }
+
+        pub fn cells(&self) -> impl Iterator<Item = [usize; 3]> + use<> {
+            let grid = *self;
+            (0..self.len()).map(move |i| grid.cell(i))
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum FieldBoundary<T> {
+        Periodic,
+        /// Outward derivative
+        Neumann(T),
+        Dirichlet(T),
+    }
+
+    impl<T: FieldValue> FieldBoundary<T> {
+        pub fn from_config(config: &FieldBoundaryConfig) -> IoResult<Self> {
+            let value = |p: &Property| T::from_property(p).ok_or_else(|| IoError::new(
+                ErrorKind::InvalidData,
+                format!("Boundary value {p:?} does not match field type"),
+            ));
+
+            match config {
+                FieldBoundaryConfig::Periodic => Ok(FieldBoundary::Periodic),
+                FieldBoundaryConfig::Neumann { gradient: None } => Ok(FieldBoundary::Neumann(T::zero())),
+                FieldBoundaryConfig::Neumann { gradient: Some(g) } => Ok(FieldBoundary::Neumann(value(g)?)),
+                FieldBoundaryConfig::Dirichlet { value: v } => Ok(FieldBoundary::Dirichlet(value(v)?)),
+            }
+        }
+    }
+
+    impl<T: FieldValue> FieldBoundary<T> {
+        /// Same kind of boundary with zero value, used to split off constant boundary terms
+        pub fn homogeneous(&self) -> Self {
+            match self {
+                FieldBoundary::Periodic => FieldBoundary::Periodic,
+                FieldBoundary::Neumann(_) => FieldBoundary::Neumann(T::zero()),
+                FieldBoundary::Dirichlet(_) => FieldBoundary::Dirichlet(T::zero()),
+            }
+        }
+    }
+
+    /// `[low, high]` boundary per axis
+    pub type FieldBoundaries<T> = [[FieldBoundary<T>; 2]; 3];
+
+    pub fn boundaries_from_spec<T: FieldValue>(
+        spec: &FieldBoundarySpec,
+        dimension: usize,
+    ) -> IoResult<FieldBoundaries<T>> {
+        match spec {
+            FieldBoundarySpec::Uniform(config) => {
+                let boundary = FieldBoundary::from_config(config)?;
+                Ok([[boundary; 2]; 3])
+            }
+            FieldBoundarySpec::PerAxis(axes) => {
+                if axes.len() != dimension {
+                    return Err(IoError::new(
+                        ErrorKind::InvalidData,
+                        format!("Expected boundaries for {dimension} axes, got {}", axes.len()),
+                    ));
+                }
+
+                let mut boundaries = [[FieldBoundary::Neumann(T::zero()); 2]; 3];
+                for (axis, [low, high]) in axes.iter().enumerate() {
+                    boundaries[axis] = [FieldBoundary::from_config(low)?, FieldBoundary::from_config(high)?];
+                }
+                Ok(boundaries)
+            }
+        }
+    }
+
+    /// Boundaries of a coefficient map over a field with `boundaries`: periodic
+    /// where the field is and zero-gradient elsewhere
+    pub fn map_boundaries<T>(boundaries: &FieldBoundaries<T>) -> FieldBoundaries<SimFloat> {
+        boundaries.each_ref().map(|sides| sides.each_ref().map(|b| match b {
+            FieldBoundary::Periodic => FieldBoundary::Periodic,
+            _ => FieldBoundary::Neumann(0.0),
+        }))
+    }
+
+    /// Builds scalar map `name` from `maps`, falling back to uniform float `name`
+    /// of simulation config. Map is periodic where `boundaries` are and zero-gradient elsewhere
+    pub fn scalar_map<T>(
+        grid: Grid,
+        boundaries: &FieldBoundaries<T>,
+        maps: &HashMap<String, FieldMapConfig>,
+        simulation_properties: &HashMap<String, Property>,
+        name: &str,
+    ) -> IoResult<Field<SimFloat>> {
+        let map_boundaries = map_boundaries(boundaries);
+
+        let Some(config) = maps.get(name) else {
+            let value = simulation_properties.get_float(name)?;
+            return Ok(Field::new(grid, map_boundaries, value));
+        };
+
+        let mut map = Field::new(grid, map_boundaries, config.value);
+        for region in config.regions.iter() {
+            map.fill_region(region)?;
+        }
+        Ok(map)
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct Field<T> {
+        pub grid: Grid,
+        pub boundaries: FieldBoundaries<T>,
+        data: Vec<T>,
+    }
+
+    impl<T: FieldValue> Field<T> {
+        pub fn new(grid: Grid, boundaries: FieldBoundaries<T>, fill: T) -> Self {
+            Self {
+                grid,
+                boundaries,
+                data: vec![fill; grid.len()],
+            }
+        }
+
+        /// Same field with boundary values set to zero
+        pub fn homogeneous(&self) -> Self {
+            Self {
+                grid: self.grid,
+                boundaries: self.boundaries.map(|sides| sides.map(|b| b.homogeneous())),
+                data: self.data.clone(),
+            }
+        }
+
+        /// Field of the same shape and boundaries filled with `fill`
+        pub fn filled_like(&self, fill: T) -> Self {
+            Self::new(self.grid, self.boundaries, fill)
+        }
+
+        pub fn data(&self) -> &[T] {
+            &self.data
+        }
+
+        pub fn data_mut(&mut self) -> &mut [T] {
+            &mut self.data
+        }
+
+        pub fn get(&self, cell: [usize; 3]) -> T {
+            self.data[self.grid.index(cell)]
+        }
+
+        pub fn set(&mut self, cell: [usize; 3], value: T) {
+            let index = self.grid.index(cell);
+            self.data[index] = value;
+        }
+
+        /// Sets every cell in `min..max` to `value`
+        pub fn fill_region(&mut self, region: &FieldRegion) -> IoResult<()> {
+            let value = T::from_property(&region.value).ok_or_else(|| IoError::new(
+                ErrorKind::InvalidData,
+                format!("Region value {:?} does not match field type", region.value),
+            ))?;
+
+            let dimension = self.grid.dimension;
+            if region.min.len() != dimension || region.max.len() != dimension {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Region bounds should have {dimension} components"),
+                ));
+            }
+
+            for cell in self.grid.cells() {
+                let inside = (0..dimension)
+                    .all(|a| cell[a] >= region.min[a] && cell[a] < region.max[a]);
+                if inside {
+                    self.set(cell, value);
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Value of possibly out of grid cell, resolved through boundary conditions
+        pub fn at(&self, cell: [isize; 3]) -> T {
+            let mut resolved = [0usize; 3];
+            let mut offset = T::zero();
+
+            for axis in 0..3 {
+                let size = self.grid.shape[axis] as isize;
+                let c = cell[axis];
+                if (0..size).contains(&c) {
+                    resolved[axis] = c as usize;
+                    continue;
+                }
+
+                let (side, edge, distance) = if c < 0 {
+                    (0, 0, -c)
+                } else {
+                    (1, size - 1, c - size + 1)
+                };
+
+                match self.boundaries[axis][side] {
+                    FieldBoundary::Periodic => resolved[axis] = c.rem_euclid(size) as usize,
+                    FieldBoundary::Dirichlet(value) => return value,
+                    FieldBoundary::Neumann(gradient) => {
+                        resolved[axis] = edge as usize;
+                        offset += gradient * (self.grid.spacing * distance as SimFloat);
+                    }
+                }
+            }
+
+            self.get(resolved) + offset
+        }
+
+        /// Value of neighbour of `cell` shifted by `offset` along `axis`
+        pub fn neighbour(&self, cell: [usize; 3], axis: usize, offset: isize) -> T {
+            let mut shifted = cell.map(|c| c as isize);
+            shifted[axis] += offset;
+            self.at(shifted)
+        }
+
+        /// Central difference gradient, one value per axis. Unused axes are zero
+        pub fn gradient(&self, cell: [usize; 3]) -> [T; 3] {
+            let scale = 0.5 / self.grid.spacing;
+            std::array::from_fn(|axis| if axis < self.grid.dimension {
+                (self.neighbour(cell, axis, 1) - self.neighbour(cell, axis, -1)) * scale
+            } else { T::zero() })
+        }
+
+        /// Multilinear interpolation of `values` at world `position`. Positions
+        /// outside of the grid are resolved through boundary conditions
+        fn interpolate<V: FieldValue>(&self, position: [SimFloat; 3], values: impl Fn([isize; 3]) -> V) -> V {
+            let dimension = self.grid.dimension;
+            let mut base = [0isize; 3];
+            let mut fraction = [0.0; 3];
+            for axis in 0..dimension {
+                let s = (position[axis] - self.grid.origin[axis]) / self.grid.spacing;
+                base[axis] = s.floor() as isize;
+                fraction[axis] = s - s.floor();
+            }
+
+            let mut result = V::zero();
+            for corner in 0..(1 << dimension) {
+                let mut cell = base;
+                let mut weight = 1.0;
+                for axis in 0..dimension {
+                    if corner & (1 << axis) != 0 {
+                        cell[axis] += 1;
+                        weight *= fraction[axis];
+                    } else {
+                        weight *= 1.0 - fraction[axis];
+                    }
+                }
+
+                if weight != 0.0 {
+                    result += values(cell) * weight;
+                }
+            }
+
+            result
+        }
+
+        /// Value at arbitrary world position, interpolated between cells
+        pub fn sample(&self, position: [SimFloat; 3]) -> T {
+            self.interpolate(position, |cell| self.at(cell))
+        }
+
+        /// Gradient at arbitrary world position, interpolated from cell gradients
+        pub fn sample_gradient(&self, position: [SimFloat; 3]) -> [T; 3] {
+            let h = self.grid.spacing;
+            std::array::from_fn(|axis| {
+                if axis >= self.grid.dimension { return T::zero() }
+
+                self.interpolate(position, |cell| {
+                    let mut forward = cell;
+                    let mut backward = cell;
+                    forward[axis] += 1;
+                    backward[axis] -= 1;
+                    (self.at(forward) - self.at(backward)) * (0.5 / h)
+                })
+            })
+        }
+
+        /// Second order finite difference Laplacian
+        pub fn laplacian(&self, cell: [usize; 3]) -> T {
+            let center = self.get(cell);
+            let mut sum = T::zero();
+            for axis in 0..self.grid.dimension {
+                sum += self.neighbour(cell, axis, 1) + self.neighbour(cell, axis, -1) - center * 2.0;
+            }
+
+            sum * (1.0 / (self.grid.spacing * self.grid.spacing))
+        }
+    }
+
+    /// Rate of change of a cell, used by explicit solvers
+    pub type FieldRateFn<T> = fn(
+        field: &Field<T>,
+        cell: [usize; 3],
+        simulation_properties: &HashMap<String, Property>,
+    ) -> T;
+
+    pub trait FieldSolver<T> {
+        fn step(
+            &mut self,
+            field: &mut Field<T>,
+            simulation_properties: &HashMap<String, Property>,
+            delta: SimFloat,
+        );
+
+        /// Adds solver specific statistics, such as field energy
+        fn record_stats(&self, _field: &Field<T>, _stats: &mut HashMap<String, Property>) {}
+    }
+
+    /// Forward Euler integration of a rate function
+    pub struct ExplicitFieldSolver<T> {
+        rate_fn: FieldRateFn<T>,
+    }
+
+    impl<T> ExplicitFieldSolver<T> {
+        pub fn new(rate_fn: FieldRateFn<T>) -> Self {
+            Self { rate_fn }
+        }
+    }
+
+    impl<T: FieldValue> FieldSolver<T> for ExplicitFieldSolver<T> {
+        fn step(
+            &mut self,
+            field: &mut Field<T>,
+            simulation_properties: &HashMap<String, Property>,
+            delta: SimFloat,
+        ) {
+            let rates = field.grid.cells()
+                .map(|cell| (self.rate_fn)(field, cell, simulation_properties))
+                .collect::<Vec<_>>();
+
+            for (value, rate) in field.data_mut().iter_mut().zip(rates) {
+                *value += rate * delta;
+            }
+        }
+    }
+
+    pub struct FieldSimulator<T> {
+        solver_config: FieldSolverConfig,
+        sim_config: HashMap<String, Property>,
+        field: Field<T>,
+        solver: Box<dyn FieldSolver<T>>,
+        simulation_time: SimFloat,
+        stats: Option<Timeseries<HashMap<String, Property>>>,
+    }
+
+    impl<T: FieldValue + 'static> FieldSimulator<T> {
+        pub fn load(filename: &str) -> Result<Self, Error> {
+            let source = std::fs::read_to_string(filename)?;
+            Self::from_source(&source, Format::from_path(filename))
+        }
+
+        /// Loads configuration from text. Errors carry snippets of `source`
+        pub fn from_source(source: &str, format: Format) -> Result<Self, Error> {
+            format.deserialize::<FieldConfiguration>(source)
+                .and_then(|config| Self::from_configuration(config).map_err(Error::from_setup))
+                .map_err(|e| e.located(source, format))
+        }
+
+        fn from_configuration(config: FieldConfiguration) -> IoResult<Self> {
+            config.simulation_config.get_str("name").map_err(|e| e.within("simulation_config"))?;
+
+            let grid = Grid::new(&config.grid.shape, config.grid.spacing, &config.grid.origin)?;
+            let boundaries = boundaries_from_spec(&config.boundary, grid.dimension)?;
+
+            let initial_value = match config.initial_value.as_ref() {
+                Some(v) => T::from_property(v).ok_or_else(|| IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Initial value {v:?} does not match field type"),
+                ))?,
+                None => T::zero(),
+            };
+
+            let mut field = Field::new(grid, boundaries, initial_value);
+            for region in config.initial_regions.iter() {
+                field.fill_region(region)?;
+            }
+
+            let model = config.simulation_config.get("model").and_then(|m| m.try_str());
+            let solver: Box<dyn FieldSolver<T>> = match model {
+                Some("diffusion") => Box::new(DiffusionSolver::from_config(
+                    &field,
+                    &config.maps,
+                    &config.simulation_config,
+                    config.solver_config.timestep,
+                )?),
+                Some("wave") => Box::new(WaveSolver::from_config(
+                    &field,
+                    &config.maps,
+                    &config.simulation_config,
+                    config.solver_config.timestep,
+                )?),
+                Some("poisson") => Box::new(PoissonSolver::from_config(
+                    &field,
+                    &config.maps,
+                    &config.simulation_config,
+                )?),
+                Some(model) => return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown field model `{model}`"),
+                )),
+                // TODO: Definable
+                None => Box::new(ExplicitFieldSolver::new(|_, _, _| T::zero())),
+            };
+
+            Ok(Self {
+                solver_config: config.solver_config,
+                sim_config: config.simulation_config,
+                field,
+                solver,
+                simulation_time: 0.0,
+                stats: None,
+            })
+        }
+
+        pub fn new(field: Field<T>, solver: Box<dyn FieldSolver<T>>, timestep: SimFloat) -> Self {
+            Self {
+                solver_config: FieldSolverConfig { timestep },
+                sim_config: HashMap::new(),
+                field,
+                solver,
+                simulation_time: 0.0,
+                stats: None,
+            }
+        }
+
+        pub fn start_recording_statistics(&mut self) {
+            self.stats = Some(Timeseries::new());
+        }
+
+        pub fn save_statistics(&self, filename: &str) -> IoResult<()> {
+            if let Some(stats) = self.stats.as_ref() {
+                stats.save(filename)?
+            }
+
+            Ok(())
+        }
+
+        pub fn sim_name(&self) -> &str {
+            self.sim_config.get("name").and_then(|n| n.try_str()).unwrap_or_default()
+        }
+
+        pub fn field(&self) -> &Field<T> {
+            &self.field
+        }
+
+        pub fn field_mut(&mut self) -> &mut Field<T> {
+            &mut self.field
+        }
+
+        fn record_stats(&mut self) {
+            if self.stats.is_none() { return; }
+
+            let magnitudes = self.field.data().iter().map(|v| v.magnitude());
+            let (min, max) = magnitudes.fold(
+                (SimFloat::INFINITY, SimFloat::NEG_INFINITY),
+                |(min, max), v| (min.min(v), max.max(v)),
+            );
+            let total = self.field.data().iter()
+                .fold(T::zero(), |a, v| a + *v) * self.field.grid.cell_volume();
+            let mean = total * (1.0 / (self.field.grid.len() as SimFloat * self.field.grid.cell_volume()));
+
+            let mut hashmap = HashMap::new();
+            hashmap.insert("min".to_string(), Property::Float(min));
+            hashmap.insert("max".to_string(), Property::Float(max));
+            hashmap.insert("total".to_string(), total.to_property());
+            hashmap.insert("mean".to_string(), mean.to_property());
+            self.solver.record_stats(&self.field, &mut hashmap);
+
+            self.stats.as_mut().unwrap().record(hashmap, Some(self.simulation_time));
+        }
+
+        pub fn step(&mut self) {
+            self.record_stats();
+
+            self.solver.step(&mut self.field, &self.sim_config, self.solver_config.timestep);
+
+            self.simulation_time += self.solver_config.timestep;
+        }
+
+        pub fn time(&self) -> SimFloat {
+            self.simulation_time
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra as na;
+
+    use super::proto::*;
+    use crate::Property;
+
+    fn line(values: &[f64], boundary: FieldBoundary<f64>) -> Field<f64> {
+        let grid = Grid::new(&[values.len()], 0.5, &[]).unwrap();
+        let mut field = Field::new(grid, [[boundary; 2]; 3], 0.0);
+        field.data_mut().copy_from_slice(values);
+        field
+    }
+
+    #[test]
+    fn grid_rejects_bad_shapes_and_indexes_round_trip() {
+        assert!(Grid::new(&[], 1.0, &[]).is_err());
+        assert!(Grid::new(&[2, 0], 1.0, &[]).is_err());
+        assert!(Grid::new(&[2, 2, 2, 2], 1.0, &[]).is_err());
+        assert!(Grid::new(&[2, 2], 1.0, &[0.0]).is_err());
+        assert!(Grid::new(&[2, 2], 0.0, &[]).is_err());
+        assert!(Grid::new(&[2, 2], -1.0, &[]).is_err());
+        assert!(Grid::new(&[2, 2], f64::NAN, &[]).is_err());
+        assert!(Grid::new(&[2, 2], f64::INFINITY, &[]).is_err());
+
+        let grid = Grid::new(&[3, 4, 2], 0.5, &[1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(grid.len(), 24);
+        assert_eq!(grid.cell_volume(), 0.125);
+        assert_eq!(grid.position([2, 1, 0]), [2.0, 0.5, 0.0]);
+        assert!(grid.cells().enumerate().all(|(i, cell)| grid.index(cell) == i));
+    }
+
+    #[test]
+    fn out_of_grid_cells_resolve_through_boundaries() {
+        let periodic = line(&[1.0, 2.0, 3.0], FieldBoundary::Periodic);
+        assert_eq!(periodic.at([-1, 0, 0]), 3.0);
+        assert_eq!(periodic.at([4, 0, 0]), 2.0);
+
+        let dirichlet = line(&[1.0, 2.0, 3.0], FieldBoundary::Dirichlet(-1.0));
+        assert_eq!(dirichlet.at([-2, 0, 0]), -1.0);
+
+        // Outward gradient of 2 over spacing 0.5 per cell
+        let neumann = line(&[1.0, 2.0, 3.0], FieldBoundary::Neumann(2.0));
+        assert_eq!(neumann.at([3, 0, 0]), 4.0);
+        assert_eq!(neumann.at([-2, 0, 0]), 3.0);
+    }
+
+    #[test]
+    fn finite_differences_are_exact_for_quadratics() {
+        // f = x^2 on x = 0, 0.5, ..., 3.5, boundaries only matter at the edges
+        let values = (0..8).map(|i| (i as f64 * 0.5).powi(2)).collect::<Vec<_>>();
+        let field = line(&values, FieldBoundary::Periodic);
+
+        for i in 1..7 {
+            let x = i as f64 * 0.5;
+            assert!((field.laplacian([i, 0, 0]) - 2.0).abs() < 1e-12);
+            assert!((field.gradient([i, 0, 0])[0] - 2.0 * x).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn sample_interpolates_between_cells() {
+        let grid = Grid::new(&[2, 2], 1.0, &[]).unwrap();
+        let mut field = Field::new(grid, [[FieldBoundary::Neumann(0.0); 2]; 3], 0.0);
+        field.data_mut().copy_from_slice(&[0.0, 1.0, 2.0, 3.0]);
+
+        assert!((field.sample([0.5, 0.5, 0.0]) - 1.5).abs() < 1e-12);
+        assert!((field.sample([0.25, 1.0, 0.0]) - 2.25).abs() < 1e-12);
+        assert!((field.sample_gradient([0.5, 0.5, 0.0])[1] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn regions_and_boundaries_are_validated() {
+        let grid = Grid::new(&[4, 4], 1.0, &[]).unwrap();
+        let mut field = Field::new(grid, [[FieldBoundary::Periodic; 2]; 3], na::Vector2::zeros());
+
+        let region = |value| FieldRegion { min: vec![1, 1], max: vec![3, 2], value };
+        field.fill_region(&region(Property::Vector2([1.0, 2.0]))).unwrap();
+        assert_eq!(field.data().iter().filter(|v| v.x == 1.0).count(), 2);
+        assert!(field.fill_region(&region(Property::Float(1.0))).is_err());
+        assert!(field.fill_region(&FieldRegion { min: vec![0], max: vec![1], value: Property::Vector2([0.0; 2]) }).is_err());
+
+        let spec = FieldBoundarySpec::PerAxis(vec![[FieldBoundaryConfig::Periodic, FieldBoundaryConfig::Periodic]]);
+        assert!(boundaries_from_spec::<f64>(&spec, 2).is_err());
+        let spec = FieldBoundarySpec::Uniform(FieldBoundaryConfig::Dirichlet { value: Property::Float(1.0) });
+        assert!(boundaries_from_spec::<na::Vector2<f64>>(&spec, 2).is_err());
+    }
+}
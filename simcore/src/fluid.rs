@@ -0,0 +1,474 @@
+pub mod proto {
+    use std::{
+        collections::HashMap,
+        io::{Error as IoError, ErrorKind, Result as IoResult},
+    };
+
+    use nalgebra as na;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        diffusion::proto::{DiffusionScheme, DiffusionSolver},
+        field::proto::{Field, FieldBoundary, FieldRegion, FieldSolver, FieldSolverConfig, Grid, GridConfig},
+        stats::Timeseries,
+        Error, Format, Property, PropertyMap, SimFloat,
+    };
+
+    /// Box of cells `min..max` (exclusive) with a special role
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    pub enum FluidCellConfig {
+        Solid {
+            min: [usize; 2],
+            max: [usize; 2],
+        },
+        /// Cells with fixed velocity and dye concentration
+        Inflow {
+            min: [usize; 2],
+            max: [usize; 2],
+            velocity: [SimFloat; 2],
+            #[serde(default)]
+            dye: SimFloat,
+        },
+        /// Cells at zero pressure which fluid can leave through
+        Outflow {
+            min: [usize; 2],
+            max: [usize; 2],
+        },
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum CellType {
+        Fluid,
+        Solid,
+        Inflow {
+            velocity: na::Vector2<SimFloat>,
+            dye: SimFloat,
+        },
+        Outflow,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct FluidConfiguration {
+        simulation_config: HashMap<String, Property>,
+        solver_config: FieldSolverConfig,
+        grid: GridConfig,
+        /// Cells outside of all boxes are fluid. Grid edges act as solid walls
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        cells: Vec<FluidCellConfig>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        initial_dye: Vec<FieldRegion>,
+    }
+
+    impl FluidConfiguration {
+        pub fn save(&self, filename: &str) -> IoResult<()> {
+            let stringified = serde_json::to_string_pretty(self)?;
+
+            std::fs::write(filename, stringified)
+        }
+    }
+
+    const NEIGHBOURS: [(usize, isize); 4] = [(0, 1), (0, -1), (1, 1), (1, -1)];
+
+    /// 2D incompressible flow after Stam's stable fluids: semi-Lagrangian
+    /// advection, implicit viscosity and pressure projection on a collocated grid
+    pub struct FluidSimulator {
+        solver_config: FieldSolverConfig,
+        sim_config: HashMap<String, Property>,
+        grid: Grid,
+        cells: Vec<CellType>,
+        velocity: Field<na::Vector2<SimFloat>>,
+        dye: Field<SimFloat>,
+        /// Pressure times timestep over density, kept as initial guess for next projection
+        pressure: Field<SimFloat>,
+        viscosity: Option<DiffusionSolver>,
+        dye_diffusion: Option<DiffusionSolver>,
+        pressure_tolerance: SimFloat,
+        max_pressure_iterations: usize,
+        pressure_iterations: usize,
+        simulation_time: SimFloat,
+        stats: Option<Timeseries<HashMap<String, Property>>>,
+    }
+
+    impl FluidSimulator {
+        /// Reads `viscosity`, `dye_diffusivity`, `pressure_tolerance` and
+        /// `max_pressure_iterations` from simulation config
+        pub fn load(filename: &str) -> Result<Self, Error> {
+            let source = std::fs::read_to_string(filename)?;
+            Self::from_source(&source, Format::from_path(filename))
+        }
+
+        /// Loads configuration from text. Errors carry snippets of `source`
+        pub fn from_source(source: &str, format: Format) -> Result<Self, Error> {
+            format.deserialize::<FluidConfiguration>(source)
+                .and_then(|config| Self::from_configuration(config).map_err(Error::from_setup))
+                .map_err(|e| e.located(source, format))
+        }
+
+        fn from_configuration(config: FluidConfiguration) -> IoResult<Self> {
+            config.simulation_config.get_str("name").map_err(|e| e.within("simulation_config"))?;
+
+            let grid = Grid::new(&config.grid.shape, config.grid.spacing, &config.grid.origin)?;
+            if grid.dimension != 2 {
+                return Err(IoError::new(ErrorKind::InvalidData, "Fluid simulation requires a 2D grid"));
+            }
+
+            let mut cells = vec![CellType::Fluid; grid.len()];
+            for cell_config in config.cells.iter() {
+                let (min, max, kind) = match cell_config {
+                    FluidCellConfig::Solid { min, max } => (min, max, CellType::Solid),
+                    FluidCellConfig::Inflow { min, max, velocity, dye } => (
+                        min,
+                        max,
+                        CellType::Inflow { velocity: (*velocity).into(), dye: *dye },
+                    ),
+                    FluidCellConfig::Outflow { min, max } => (min, max, CellType::Outflow),
+                };
+
+                for cell in grid.cells() {
+                    if (0..2).all(|a| cell[a] >= min[a] && cell[a] < max[a]) {
+                        cells[grid.index(cell)] = kind;
+                    }
+                }
+            }
+
+            let walls = [[FieldBoundary::Dirichlet(na::Vector2::zeros()); 2]; 3];
+            let velocity = Field::new(grid, walls, na::Vector2::zeros());
+
+            let insulating = [[FieldBoundary::Neumann(0.0); 2]; 3];
+            let mut dye = Field::new(grid, insulating, 0.0);
+            for region in config.initial_dye.iter() {
+                dye.fill_region(region)?;
+            }
+
+            let delta = config.solver_config.timestep;
+            let diffusion = |key: &str| -> IoResult<Option<DiffusionSolver>> {
+                match config.simulation_config.get_float_opt(key)? {
+                    Some(d) if d > 0.0 => Ok(Some(DiffusionSolver::new(
+                        DiffusionScheme::CrankNicolson,
+                        Field::new(grid, insulating, d),
+                        delta,
+                    )?)),
+                    _ => Ok(None),
+                }
+            };
+
+            let mut simulator = Self {
+                solver_config: config.solver_config,
+                viscosity: diffusion("viscosity")?,
+                dye_diffusion: diffusion("dye_diffusivity")?,
+                pressure_tolerance: config.simulation_config.get_float_or("pressure_tolerance", 1e-6)?,
+                max_pressure_iterations: config.simulation_config.get_count_or("max_pressure_iterations", 500)?,
+                sim_config: config.simulation_config,
+                grid,
+                cells,
+                velocity,
+                dye,
+                pressure: Field::new(grid, insulating, 0.0),
+                pressure_iterations: 0,
+                simulation_time: 0.0,
+                stats: None,
+            };
+            simulator.enforce_cells();
+
+            Ok(simulator)
+        }
+
+        pub fn start_recording_statistics(&mut self) {
+            self.stats = Some(Timeseries::new());
+        }
+
+        pub fn save_statistics(&self, filename: &str) -> IoResult<()> {
+            if let Some(stats) = self.stats.as_ref() {
+                stats.save(filename)?
+            }
+
+            Ok(())
+        }
+
+        pub fn sim_name(&self) -> &str {
+            self.sim_config.get("name").and_then(|n| n.try_str()).unwrap_or_default()
+        }
+
+        pub fn grid(&self) -> &Grid {
+            &self.grid
+        }
+
+        pub fn cell_type(&self, cell: [usize; 3]) -> CellType {
+            self.cells[self.grid.index(cell)]
+        }
+
+        pub fn velocity(&self) -> &Field<na::Vector2<SimFloat>> {
+            &self.velocity
+        }
+
+        pub fn dye(&self) -> &Field<SimFloat> {
+            &self.dye
+        }
+
+        /// Type of cell shifted by `offset` along `axis`. Outside of grid is solid
+        fn neighbour_type(&self, cell: [usize; 3], axis: usize, offset: isize) -> CellType {
+            let shifted = cell[axis] as isize + offset;
+            if shifted < 0 || shifted >= self.grid.shape[axis] as isize {
+                return CellType::Solid;
+            }
+
+            let mut neighbour = cell;
+            neighbour[axis] = shifted as usize;
+            self.cell_type(neighbour)
+        }
+
+        fn enforce_cells(&mut self) {
+            for (index, kind) in self.cells.iter().enumerate() {
+                match kind {
+                    CellType::Solid => {
+                        self.velocity.data_mut()[index] = na::Vector2::zeros();
+                        self.dye.data_mut()[index] = 0.0;
+                    }
+                    CellType::Inflow { velocity, dye } => {
+                        self.velocity.data_mut()[index] = *velocity;
+                        self.dye.data_mut()[index] = *dye;
+                    }
+                    CellType::Fluid | CellType::Outflow => {}
+                }
+            }
+        }
+
+        /// Traces every free cell back along the velocity and samples old values there
+        fn advect(&mut self, delta: SimFloat) {
+            let old_velocity = self.velocity.clone();
+            let old_dye = self.dye.clone();
+
+            for (index, cell) in self.grid.cells().enumerate() {
+                if !matches!(self.cells[index], CellType::Fluid | CellType::Outflow) { continue }
+
+                let position = self.grid.position(cell);
+                let u = old_velocity.data()[index];
+                let back = [position[0] - u.x * delta, position[1] - u.y * delta, 0.0];
+
+                self.velocity.data_mut()[index] = old_velocity.sample(back);
+                self.dye.data_mut()[index] = old_dye.sample(back);
+            }
+        }
+
+        fn divergence(&self) -> Vec<SimFloat> {
+            let scale = 0.5 / self.grid.spacing;
+
+            self.grid.cells().enumerate().map(|(index, cell)| {
+                if self.cells[index] != CellType::Fluid { return 0.0 }
+
+                NEIGHBOURS.iter().fold(0.0, |a, &(axis, offset)| {
+                    let u = match self.neighbour_type(cell, axis, offset) {
+                        CellType::Solid => 0.0,
+                        _ => self.velocity.neighbour(cell, axis, offset)[axis],
+                    };
+                    a + offset as SimFloat * u * scale
+                })
+            }).collect()
+        }
+
+        /// Makes velocity divergence free by relaxing laplacian(q) = div(u) and
+        /// subtracting grad(q). Walls and inflows are zero-gradient, outflows are zero
+        fn project(&mut self) {
+            let tolerance = self.pressure_tolerance;
+            let max_iterations = self.max_pressure_iterations;
+            const OMEGA: SimFloat = 1.7;
+
+            let divergence = self.divergence();
+            let h2 = self.grid.spacing * self.grid.spacing;
+            let scale = divergence.iter().fold(0.0, |m: SimFloat, d| m.max(d.abs()));
+            if scale == 0.0 {
+                self.pressure_iterations = 0;
+                return;
+            }
+
+            let mut iterations = 0;
+            while iterations < max_iterations {
+                let mut change: SimFloat = 0.0;
+                for (index, cell) in self.grid.cells().enumerate() {
+                    if self.cells[index] != CellType::Fluid { continue }
+
+                    let mut sum = 0.0;
+                    let mut count = 0.0;
+                    for &(axis, offset) in NEIGHBOURS.iter() {
+                        match self.neighbour_type(cell, axis, offset) {
+                            CellType::Solid | CellType::Inflow { .. } => {}
+                            CellType::Outflow => count += 1.0,
+                            CellType::Fluid => {
+                                sum += self.pressure.neighbour(cell, axis, offset);
+                                count += 1.0;
+                            }
+                        }
+                    }
+                    if count == 0.0 { continue }
+
+                    let old = self.pressure.data()[index];
+                    let new = (sum - h2 * divergence[index]) / count;
+                    let relaxed = old + OMEGA * (new - old);
+                    self.pressure.data_mut()[index] = relaxed;
+                    change = change.max((relaxed - old).abs());
+                }
+
+                iterations += 1;
+                if change <= tolerance * scale * h2 { break }
+            }
+            self.pressure_iterations = iterations;
+
+            let scale = 0.5 / self.grid.spacing;
+            for (index, cell) in self.grid.cells().enumerate() {
+                if self.cells[index] != CellType::Fluid { continue }
+
+                let center = self.pressure.data()[index];
+                let mut gradient = na::Vector2::zeros();
+                for &(axis, offset) in NEIGHBOURS.iter() {
+                    let q = match self.neighbour_type(cell, axis, offset) {
+                        CellType::Solid | CellType::Inflow { .. } => center,
+                        CellType::Outflow => 0.0,
+                        CellType::Fluid => self.pressure.neighbour(cell, axis, offset),
+                    };
+                    gradient[axis] += offset as SimFloat * q * scale;
+                }
+
+                self.velocity.data_mut()[index] -= gradient;
+            }
+        }
+
+        fn record_stats(&mut self) {
+            if self.stats.is_none() { return; }
+
+            let cell_volume = self.grid.cell_volume();
+            let mut kinetic_energy = 0.0;
+            let mut max_speed: SimFloat = 0.0;
+            for (u, kind) in self.velocity.data().iter().zip(self.cells.iter()) {
+                if *kind == CellType::Solid { continue }
+
+                kinetic_energy += 0.5 * u.magnitude_squared() * cell_volume;
+                max_speed = max_speed.max(u.magnitude());
+            }
+
+            let divergence = self.divergence();
+            let divergence_rms = (divergence.iter().fold(0.0, |a, d| a + d * d)
+                / divergence.len() as SimFloat).sqrt();
+
+            let mut hashmap = HashMap::new();
+            hashmap.insert("kinetic_energy".to_string(), Property::Float(kinetic_energy));
+            hashmap.insert("max_speed".to_string(), Property::Float(max_speed));
+            hashmap.insert(
+                "dye_total".to_string(),
+                Property::Float(self.dye.data().iter().sum::<SimFloat>() * cell_volume),
+            );
+            hashmap.insert("divergence".to_string(), Property::Float(divergence_rms));
+            hashmap.insert(
+                "pressure_iterations".to_string(),
+                Property::Float(self.pressure_iterations as SimFloat),
+            );
+
+            self.stats.as_mut().unwrap().record(hashmap, Some(self.simulation_time));
+        }
+
+        pub fn step(&mut self) {
+            self.record_stats();
+            let delta = self.solver_config.timestep;
+
+            self.advect(delta);
+            self.enforce_cells();
+
+            if let Some(viscosity) = self.viscosity.as_mut() {
+                viscosity.step(&mut self.velocity, &self.sim_config, delta);
+            }
+            if let Some(dye_diffusion) = self.dye_diffusion.as_mut() {
+                dye_diffusion.step(&mut self.dye, &self.sim_config, delta);
+            }
+            self.enforce_cells();
+
+            self.project();
+            self.enforce_cells();
+
+            self.simulation_time += delta;
+        }
+
+        pub fn time(&self) -> SimFloat {
+            self.simulation_time
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proto::*;
+    use crate::Format;
+
+    fn load(config: &str) -> Result<FluidSimulator, crate::Error> {
+        FluidSimulator::from_source(config, Format::Json)
+    }
+
+    /// Channel of `width` rows fed from the left column and drained through the right one
+    fn channel(width: usize, extra: &str) -> FluidSimulator {
+        load(&format!(r#"{{
+            "simulation_config": {{"name": "channel", "pressure_tolerance": 1e-8, "max_pressure_iterations": 5000{extra}}},
+            "solver_config": {{"timestep": 0.05}},
+            "grid": {{"shape": [16, {width}], "spacing": 0.1}},
+            "cells": [
+                {{"type": "inflow", "min": [0, 0], "max": [1, {width}], "velocity": [1.0, 0.0], "dye": 1.0}},
+                {{"type": "outflow", "min": [15, 0], "max": [16, {width}]}}
+            ]
+        }}"#)).unwrap()
+    }
+
+    #[test]
+    fn fluid_requires_2d_grid() {
+        let config = r#"{
+            "simulation_config": {"name": "line"},
+            "solver_config": {"timestep": 0.1},
+            "grid": {"shape": [8], "spacing": 1.0}
+        }"#;
+        assert!(load(config).is_err());
+    }
+
+    #[test]
+    fn cell_boxes_set_cell_types() {
+        let simulator = channel(4, "");
+
+        assert!(matches!(simulator.cell_type([0, 2, 0]), CellType::Inflow { dye: 1.0, .. }));
+        assert_eq!(simulator.cell_type([15, 0, 0]), CellType::Outflow);
+        assert_eq!(simulator.cell_type([7, 3, 0]), CellType::Fluid);
+        assert_eq!(simulator.dye().get([0, 1, 0]), 1.0);
+    }
+
+    #[test]
+    fn projection_carries_inflow_through_channel() {
+        let mut simulator = channel(4, r#", "viscosity": 0.01"#);
+        for _ in 0..100 {
+            simulator.step();
+        }
+
+        // Incompressible: volume flux through every interior column matches the inflow
+        for column in 2..14 {
+            let flux = (0..4).map(|row| simulator.velocity().get([column, row, 0]).x).sum::<f64>();
+            assert!((flux - 4.0).abs() < 0.2, "column {column}: {flux}");
+        }
+        assert_eq!(simulator.velocity().get([0, 0, 0]), nalgebra::Vector2::new(1.0, 0.0));
+        assert!(simulator.dye().get([12, 2, 0]) > 0.5);
+    }
+
+    #[test]
+    fn solid_cells_stay_at_rest() {
+        let mut simulator = load(r#"{
+            "simulation_config": {"name": "obstacle"},
+            "solver_config": {"timestep": 0.05},
+            "grid": {"shape": [12, 6], "spacing": 0.1},
+            "cells": [
+                {"type": "inflow", "min": [0, 0], "max": [1, 6], "velocity": [1.0, 0.0]},
+                {"type": "outflow", "min": [11, 0], "max": [12, 6]},
+                {"type": "solid", "min": [5, 2], "max": [7, 4]}
+            ]
+        }"#).unwrap();
+        for _ in 0..20 {
+            simulator.step();
+        }
+
+        assert_eq!(simulator.velocity().get([5, 2, 0]), nalgebra::Vector2::zeros());
+        assert_eq!(simulator.velocity().get([6, 3, 0]), nalgebra::Vector2::zeros());
+        assert!((simulator.time() - 1.0).abs() < 1e-12);
+    }
+}
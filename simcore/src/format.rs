@@ -0,0 +1,142 @@
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::Error;
+#[cfg(any(feature = "toml", feature = "yaml"))]
+use crate::error::line_column;
+
+/// Configuration file format, detected from file extension. Formats other
+/// than JSON require the cargo feature of the same name
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Json,
+    Toml,
+    /// `.yaml` or `.yml`
+    Yaml,
+    /// Read through typed fields, so quantity strings like `"1 h"` only work inside
+    /// property maps such as `simulation_config` and particles. Typed fields like
+    /// `solver_config.timestep` take plain numbers in internal units
+    Ron,
+}
+
+impl Format {
+    /// Format of file at `path`. Unknown or missing extensions are JSON
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        let extension = path.as_ref().extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("toml") => Format::Toml,
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("ron") => Format::Ron,
+            _ => Format::Json,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Toml => "TOML",
+            Format::Yaml => "YAML",
+            Format::Ron => "RON",
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            Format::Json => true,
+            Format::Toml => cfg!(feature = "toml"),
+            Format::Yaml => cfg!(feature = "yaml"),
+            Format::Ron => cfg!(feature = "ron"),
+        }
+    }
+
+    fn disabled_error(&self) -> Error {
+        let feature = match self {
+            Format::Json => "",
+            Format::Toml => "toml",
+            Format::Yaml => "yaml",
+            Format::Ron => "ron",
+        };
+        Error::Io(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("{} support requires `{feature}` feature of simcore", self.name()),
+        ))
+    }
+
+    /// Reads `source`, parse errors have line and column in it
+    pub fn deserialize<T: DeserializeOwned>(&self, source: &str) -> Result<T, Error> {
+        match self {
+            Format::Json => Ok(serde_json::from_str(source)?),
+            #[cfg(feature = "toml")]
+            Format::Toml => toml::from_str(source).map_err(|e| {
+                let (line, column) = e.span().map(|s| line_column(source, s.start)).unwrap_or((0, 0));
+                Error::Parse { message: e.message().to_string(), line, column, snippet: None }
+            }),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::from_str(source).map_err(|e| {
+                let (line, column) = e.location().map(|l| line_column(source, l.index())).unwrap_or((0, 0));
+                let message = e.to_string();
+                let message = message.split_once(" at line ").map(|(m, _)| m).unwrap_or(&message);
+                Error::Parse { message: message.to_string(), line, column, snippet: None }
+            }),
+            #[cfg(feature = "ron")]
+            Format::Ron => ron::from_str(source).map_err(|e| Error::Parse {
+                message: e.code.to_string(),
+                line: e.span.start.line,
+                column: e.span.start.col,
+                snippet: None,
+            }),
+            // Formats of disabled features
+            #[allow(unreachable_patterns)]
+            _ => Err(self.disabled_error()),
+        }
+    }
+
+    /// Pretty-printed `value`
+    pub fn serialize<T: Serialize>(&self, value: &T) -> Result<String, Error> {
+        match self {
+            Format::Json => serde_json::to_string_pretty(value).map_err(invalid_data),
+            #[cfg(feature = "toml")]
+            Format::Toml => toml::to_string(value).map_err(invalid_data),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::to_string(value).map_err(invalid_data),
+            #[cfg(feature = "ron")]
+            Format::Ron => ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()).map_err(invalid_data),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.disabled_error()),
+        }
+    }
+}
+
+fn invalid_data(error: impl std::error::Error + Send + Sync + 'static) -> Error {
+    Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_is_detected_from_extension() {
+        assert_eq!(Format::from_path("config.TOML"), Format::Toml);
+        assert_eq!(Format::from_path("dir.d/config.yml"), Format::Yaml);
+        assert_eq!(Format::from_path("config.ron"), Format::Ron);
+        assert_eq!(Format::from_path("config.txt"), Format::Json);
+        assert_eq!(Format::from_path("config"), Format::Json);
+    }
+
+    #[test]
+    fn disabled_formats_name_their_feature() {
+        for format in [Format::Toml, Format::Yaml, Format::Ron] {
+            if format.is_enabled() { continue }
+
+            let error = format.deserialize::<serde_json::Value>("").unwrap_err();
+            assert!(matches!(&error, Error::Io(e) if e.kind() == std::io::ErrorKind::Unsupported), "{error}");
+            assert!(format.serialize(&1).is_err());
+        }
+    }
+}
@@ -0,0 +1,587 @@
+pub mod proto {
+    use std::{
+        collections::HashMap,
+        io::{Error as IoError, ErrorKind, Result as IoResult},
+    };
+
+    use nalgebra as na;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        field::proto::{Field, FieldBoundary, FieldSolverConfig, Grid, GridConfig},
+        stats::Timeseries,
+        Error, Format, Property, PropertyMap, SimFloat,
+    };
+
+    /// Discrete velocity set of a lattice Boltzmann model
+    #[derive(Clone, Debug)]
+    pub struct Lattice {
+        pub dimension: usize,
+        pub velocities: Vec<[isize; 3]>,
+        pub weights: Vec<SimFloat>,
+        /// Index of velocity pointing the opposite way
+        pub opposite: Vec<usize>,
+    }
+
+    impl Lattice {
+        fn from_velocities(dimension: usize, velocities: Vec<[isize; 3]>, weights: Vec<SimFloat>) -> Self {
+            let opposite = velocities.iter()
+                .map(|c| velocities.iter().position(|o| *o == c.map(|x| -x)).unwrap())
+                .collect();
+
+            Self { dimension, velocities, weights, opposite }
+        }
+
+        pub fn d2q9() -> Self {
+            let mut velocities = vec![[0, 0, 0]];
+            let mut weights = vec![4.0 / 9.0];
+            for x in -1..=1 {
+                for y in -1..=1 {
+                    if x == 0 && y == 0 { continue }
+                    velocities.push([x, y, 0]);
+                    weights.push(if x == 0 || y == 0 { 1.0 / 9.0 } else { 1.0 / 36.0 });
+                }
+            }
+
+            Self::from_velocities(2, velocities, weights)
+        }
+
+        pub fn d3q19() -> Self {
+            let mut velocities = vec![[0, 0, 0]];
+            let mut weights = vec![1.0 / 3.0];
+            for x in -1..=1isize {
+                for y in -1..=1isize {
+                    for z in -1..=1isize {
+                        match x.abs() + y.abs() + z.abs() {
+                            1 => weights.push(1.0 / 18.0),
+                            2 => weights.push(1.0 / 36.0),
+                            _ => continue,
+                        }
+                        velocities.push([x, y, z]);
+                    }
+                }
+            }
+
+            Self::from_velocities(3, velocities, weights)
+        }
+
+        pub fn len(&self) -> usize {
+            self.velocities.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.velocities.is_empty()
+        }
+
+        /// Second order equilibrium distribution with squared sound speed 1/3
+        pub fn equilibrium(&self, i: usize, density: SimFloat, velocity: &na::Vector3<SimFloat>) -> SimFloat {
+            let c = self.velocities[i];
+            let cu = c[0] as SimFloat * velocity.x + c[1] as SimFloat * velocity.y + c[2] as SimFloat * velocity.z;
+
+            self.weights[i] * density
+                * (1.0 + 3.0 * cu + 4.5 * cu * cu - 1.5 * velocity.magnitude_squared())
+        }
+    }
+
+    /// Box of nodes `min..max` (exclusive) or a disk (sphere in 3D) with a special role
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    pub enum LatticeCellConfig {
+        /// No-slip bounce-back wall
+        Wall {
+            min: Vec<usize>,
+            max: Vec<usize>,
+        },
+        /// Bounce-back obstacle, e.g. cylinder for flow past cylinder. Center and radius are in nodes
+        Circle {
+            center: Vec<SimFloat>,
+            radius: SimFloat,
+        },
+        /// Nodes kept at equilibrium with fixed velocity
+        Velocity {
+            min: Vec<usize>,
+            max: Vec<usize>,
+            velocity: Vec<SimFloat>,
+        },
+        /// Nodes kept at equilibrium with fixed density, i.e. pressure density / 3
+        Pressure {
+            min: Vec<usize>,
+            max: Vec<usize>,
+            density: SimFloat,
+        },
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum NodeType {
+        Fluid,
+        Wall,
+        Velocity(na::Vector3<SimFloat>),
+        Pressure(SimFloat),
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct LatticeConfiguration {
+        simulation_config: HashMap<String, Property>,
+        /// Physical time of one lattice update
+        solver_config: FieldSolverConfig,
+        /// Shape of 2 uses D2Q9, shape of 3 uses D3Q19. Spacing only scales output positions
+        grid: GridConfig,
+        /// Axes wrapping around. Non-periodic grid edges are walls
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        periodic: Vec<bool>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        cells: Vec<LatticeCellConfig>,
+        /// Velocity of fluid nodes at start. Defaults to rest
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        initial_velocity: Option<Vec<SimFloat>>,
+    }
+
+    impl LatticeConfiguration {
+        pub fn save(&self, filename: &str) -> IoResult<()> {
+            let stringified = serde_json::to_string_pretty(self)?;
+
+            std::fs::write(filename, stringified)
+        }
+    }
+
+    fn vector3(values: &[SimFloat], dimension: usize, owner: &str) -> IoResult<na::Vector3<SimFloat>> {
+        if values.len() != dimension {
+            return Err(IoError::new(
+                ErrorKind::InvalidData,
+                format!("{owner} should have {dimension} components"),
+            ));
+        }
+
+        Ok(na::Vector3::from_fn(|a, _| values.get(a).copied().unwrap_or(0.0)))
+    }
+
+    /// Lattice Boltzmann solver with BGK collision in lattice units
+    pub struct LatticeBoltzmannSimulator {
+        solver_config: FieldSolverConfig,
+        sim_config: HashMap<String, Property>,
+        lattice: Lattice,
+        grid: Grid,
+        periodic: [bool; 3],
+        nodes: Vec<NodeType>,
+        /// Relaxation time, viscosity = (tau - 1/2) / 3
+        tau: SimFloat,
+        /// Distributions, `lattice.len()` per node
+        distributions: Vec<SimFloat>,
+        streamed: Vec<SimFloat>,
+        density: Field<SimFloat>,
+        velocity: Field<na::Vector3<SimFloat>>,
+        steps: usize,
+        stats: Option<Timeseries<HashMap<String, Property>>>,
+    }
+
+    impl LatticeBoltzmannSimulator {
+        /// Reads relaxation time `tau` or lattice `viscosity` from simulation config
+        pub fn load(filename: &str) -> Result<Self, Error> {
+            let source = std::fs::read_to_string(filename)?;
+            Self::from_source(&source, Format::from_path(filename))
+        }
+
+        /// Loads configuration from text. Errors carry snippets of `source`
+        pub fn from_source(source: &str, format: Format) -> Result<Self, Error> {
+            format.deserialize::<LatticeConfiguration>(source)
+                .and_then(|config| Self::from_configuration(config).map_err(Error::from_setup))
+                .map_err(|e| e.located(source, format))
+        }
+
+        fn from_configuration(config: LatticeConfiguration) -> IoResult<Self> {
+            config.simulation_config.get_str("name").map_err(|e| e.within("simulation_config"))?;
+
+            let grid = Grid::new(&config.grid.shape, config.grid.spacing, &config.grid.origin)?;
+            let lattice = match grid.dimension {
+                2 => Lattice::d2q9(),
+                3 => Lattice::d3q19(),
+                _ => return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    "Lattice Boltzmann requires a 2D or 3D grid",
+                )),
+            };
+            let dimension = grid.dimension;
+
+            let float = |key: &str| config.simulation_config.get_float_opt(key);
+            let tau = match (float("tau")?, float("viscosity")?) {
+                (Some(tau), _) => tau,
+                (None, Some(viscosity)) => 3.0 * viscosity + 0.5,
+                (None, None) => return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    "Lattice Boltzmann requires float `tau` or `viscosity`",
+                )),
+            };
+            if tau <= 0.5 {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Relaxation time {tau} should be above 0.5"),
+                ));
+            }
+
+            let periodic = std::array::from_fn(|a| config.periodic.get(a).copied().unwrap_or(false));
+
+            let mut nodes = vec![NodeType::Fluid; grid.len()];
+            let inside_box = |cell: [usize; 3], min: &[usize], max: &[usize]| -> IoResult<bool> {
+                if min.len() != dimension || max.len() != dimension {
+                    return Err(IoError::new(
+                        ErrorKind::InvalidData,
+                        format!("Cell box bounds should have {dimension} components"),
+                    ));
+                }
+                Ok((0..dimension).all(|a| cell[a] >= min[a] && cell[a] < max[a]))
+            };
+
+            for cell_config in config.cells.iter() {
+                for cell in grid.cells() {
+                    let (inside, kind) = match cell_config {
+                        LatticeCellConfig::Wall { min, max } => (inside_box(cell, min, max)?, NodeType::Wall),
+                        LatticeCellConfig::Circle { center, radius } => {
+                            let center = vector3(center, dimension, "Circle center")?;
+                            let offset = na::Vector3::from_fn(|a, _| cell[a] as SimFloat - center[a]);
+                            (offset.magnitude() <= *radius, NodeType::Wall)
+                        }
+                        LatticeCellConfig::Velocity { min, max, velocity } => (
+                            inside_box(cell, min, max)?,
+                            NodeType::Velocity(vector3(velocity, dimension, "Boundary velocity")?),
+                        ),
+                        LatticeCellConfig::Pressure { min, max, density } => (
+                            inside_box(cell, min, max)?,
+                            NodeType::Pressure(*density),
+                        ),
+                    };
+
+                    if inside {
+                        nodes[grid.index(cell)] = kind;
+                    }
+                }
+            }
+
+            let initial_velocity = match config.initial_velocity.as_ref() {
+                Some(v) => vector3(v, dimension, "Initial velocity")?,
+                None => na::Vector3::zeros(),
+            };
+
+            let boundaries = std::array::from_fn(|a| if periodic[a] {
+                [FieldBoundary::Periodic; 2]
+            } else {
+                [FieldBoundary::Neumann(0.0); 2]
+            });
+            let vector_boundaries = boundaries.map(|sides| sides.map(|b| match b {
+                FieldBoundary::Periodic => FieldBoundary::Periodic,
+                _ => FieldBoundary::Neumann(na::Vector3::zeros()),
+            }));
+
+            let mut simulator = Self {
+                solver_config: config.solver_config,
+                sim_config: config.simulation_config,
+                distributions: vec![0.0; grid.len() * lattice.len()],
+                streamed: vec![0.0; grid.len() * lattice.len()],
+                lattice,
+                grid,
+                periodic,
+                nodes,
+                tau,
+                density: Field::new(grid, boundaries, 1.0),
+                velocity: Field::new(grid, vector_boundaries, na::Vector3::zeros()),
+                steps: 0,
+                stats: None,
+            };
+
+            for (index, node) in simulator.nodes.iter().enumerate() {
+                let (density, velocity) = match node {
+                    NodeType::Fluid => (1.0, initial_velocity),
+                    NodeType::Wall => (1.0, na::Vector3::zeros()),
+                    NodeType::Velocity(u) => (1.0, *u),
+                    NodeType::Pressure(rho) => (*rho, initial_velocity),
+                };
+
+                let q = simulator.lattice.len();
+                for i in 0..q {
+                    simulator.distributions[index * q + i] = simulator.lattice.equilibrium(i, density, &velocity);
+                }
+            }
+            simulator.update_moments();
+
+            Ok(simulator)
+        }
+
+        pub fn start_recording_statistics(&mut self) {
+            self.stats = Some(Timeseries::new());
+        }
+
+        pub fn save_statistics(&self, filename: &str) -> IoResult<()> {
+            if let Some(stats) = self.stats.as_ref() {
+                stats.save(filename)?
+            }
+
+            Ok(())
+        }
+
+        pub fn sim_name(&self) -> &str {
+            self.sim_config.get("name").and_then(|n| n.try_str()).unwrap_or_default()
+        }
+
+        pub fn lattice(&self) -> &Lattice {
+            &self.lattice
+        }
+
+        pub fn grid(&self) -> &Grid {
+            &self.grid
+        }
+
+        pub fn node_type(&self, cell: [usize; 3]) -> NodeType {
+            self.nodes[self.grid.index(cell)]
+        }
+
+        /// Macroscopic density in lattice units
+        pub fn density(&self) -> &Field<SimFloat> {
+            &self.density
+        }
+
+        /// Macroscopic velocity in lattice units. Unused components are zero
+        pub fn velocity(&self) -> &Field<na::Vector3<SimFloat>> {
+            &self.velocity
+        }
+
+        fn update_moments(&mut self) {
+            let q = self.lattice.len();
+            for index in 0..self.grid.len() {
+                let f = &self.distributions[index * q..(index + 1) * q];
+                let density = f.iter().sum::<SimFloat>();
+                let momentum = f.iter().zip(self.lattice.velocities.iter())
+                    .fold(na::Vector3::zeros(), |a, (f, c)| {
+                        a + na::Vector3::new(c[0] as SimFloat, c[1] as SimFloat, c[2] as SimFloat) * *f
+                    });
+
+                self.density.data_mut()[index] = density;
+                self.velocity.data_mut()[index] = if self.nodes[index] == NodeType::Wall || density <= 0.0 {
+                    na::Vector3::zeros()
+                } else {
+                    momentum / density
+                };
+            }
+        }
+
+        /// Index of node shifted by `c`, or None if it leaves a non-periodic grid
+        fn shifted(&self, cell: [usize; 3], c: [isize; 3]) -> Option<usize> {
+            let mut target = [0; 3];
+            for axis in 0..3 {
+                let size = self.grid.shape[axis] as isize;
+                let mut x = cell[axis] as isize + c[axis];
+                if x < 0 || x >= size {
+                    if !self.periodic[axis] { return None }
+                    x = x.rem_euclid(size);
+                }
+                target[axis] = x as usize;
+            }
+
+            Some(self.grid.index(target))
+        }
+
+        /// Mean density and velocity of fluid nodes next to node `index`. Boundary nodes
+        /// take the moment they don't prescribe from here, as their own populations
+        /// coming from outside of the domain are only bounced back
+        fn fluid_neighbour_moments(&self, index: usize) -> Option<(SimFloat, na::Vector3<SimFloat>)> {
+            let cell = self.grid.cell(index);
+            let mut count = 0.0;
+            let mut density = 0.0;
+            let mut velocity = na::Vector3::zeros();
+            for c in self.lattice.velocities.iter().skip(1) {
+                let Some(neighbour) = self.shifted(cell, *c) else { continue };
+                if self.nodes[neighbour] != NodeType::Fluid { continue }
+
+                count += 1.0;
+                density += self.density.data()[neighbour];
+                velocity += self.velocity.data()[neighbour];
+            }
+
+            (count > 0.0).then(|| (density / count, velocity / count))
+        }
+
+        fn record_stats(&mut self) {
+            if self.stats.is_none() { return; }
+
+            let mut mass = 0.0;
+            let mut kinetic_energy = 0.0;
+            let mut max_speed: SimFloat = 0.0;
+            for ((rho, u), node) in self.density.data().iter().zip(self.velocity.data()).zip(self.nodes.iter()) {
+                if *node == NodeType::Wall { continue }
+
+                mass += rho;
+                kinetic_energy += 0.5 * rho * u.magnitude_squared();
+                max_speed = max_speed.max(u.magnitude());
+            }
+
+            let mut hashmap = HashMap::new();
+            hashmap.insert("mass".to_string(), Property::Float(mass));
+            hashmap.insert("kinetic_energy".to_string(), Property::Float(kinetic_energy));
+            hashmap.insert("max_speed".to_string(), Property::Float(max_speed));
+
+            let time = self.time();
+            self.stats.as_mut().unwrap().record(hashmap, Some(time));
+        }
+
+        pub fn step(&mut self) {
+            self.record_stats();
+
+            let q = self.lattice.len();
+
+            // Collision, with boundary nodes reset to equilibrium
+            for index in 0..self.grid.len() {
+                let density = self.density.data()[index];
+                let velocity = self.velocity.data()[index];
+
+                match self.nodes[index] {
+                    NodeType::Wall => {}
+                    NodeType::Fluid => {
+                        let f = &mut self.distributions[index * q..(index + 1) * q];
+                        for (i, f) in f.iter_mut().enumerate() {
+                            *f -= (*f - self.lattice.equilibrium(i, density, &velocity)) / self.tau;
+                        }
+                    }
+                    NodeType::Velocity(u) => {
+                        let (density, _) = self.fluid_neighbour_moments(index).unwrap_or((density, velocity));
+                        let f = &mut self.distributions[index * q..(index + 1) * q];
+                        for (i, f) in f.iter_mut().enumerate() {
+                            *f = self.lattice.equilibrium(i, density, &u);
+                        }
+                    }
+                    NodeType::Pressure(rho) => {
+                        let (_, velocity) = self.fluid_neighbour_moments(index).unwrap_or((density, velocity));
+                        let f = &mut self.distributions[index * q..(index + 1) * q];
+                        for (i, f) in f.iter_mut().enumerate() {
+                            *f = self.lattice.equilibrium(i, rho, &velocity);
+                        }
+                    }
+                }
+            }
+
+            // Streaming with half-way bounce-back on walls and closed grid edges
+            for (index, cell) in self.grid.cells().enumerate() {
+                if self.nodes[index] == NodeType::Wall {
+                    self.streamed[index * q..(index + 1) * q].fill(0.0);
+                    continue;
+                }
+
+                for i in 0..q {
+                    let value = self.distributions[index * q + i];
+                    match self.shifted(cell, self.lattice.velocities[i]) {
+                        Some(target) if self.nodes[target] != NodeType::Wall => {
+                            self.streamed[target * q + i] = value;
+                        }
+                        _ => self.streamed[index * q + self.lattice.opposite[i]] = value,
+                    }
+                }
+            }
+
+            std::mem::swap(&mut self.distributions, &mut self.streamed);
+            self.update_moments();
+            self.steps += 1;
+        }
+
+        pub fn time(&self) -> SimFloat {
+            self.steps as SimFloat * self.solver_config.timestep
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra as na;
+
+    use super::proto::*;
+    use crate::Format;
+
+    fn load(config: &str) -> Result<LatticeBoltzmannSimulator, crate::Error> {
+        LatticeBoltzmannSimulator::from_source(config, Format::Json)
+    }
+
+    fn config(shape: &str, simulation: &str, extra: &str) -> String {
+        format!(r#"{{
+            "simulation_config": {{"name": "lattice"{simulation}}},
+            "solver_config": {{"timestep": 1.0}},
+            "grid": {{"shape": {shape}, "spacing": 1.0}}{extra}
+        }}"#)
+    }
+
+    fn total_mass(simulator: &LatticeBoltzmannSimulator) -> f64 {
+        simulator.grid().cells()
+            .filter(|c| simulator.node_type(*c) != NodeType::Wall)
+            .map(|c| simulator.density().get(c))
+            .sum()
+    }
+
+    #[test]
+    fn equilibrium_has_requested_moments() {
+        for lattice in [Lattice::d2q9(), Lattice::d3q19()] {
+            assert!((lattice.weights.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            assert!((0..lattice.len()).all(|i| lattice.opposite[lattice.opposite[i]] == i));
+
+            let u = na::Vector3::new(0.05, -0.02, 0.0);
+            let f = (0..lattice.len()).map(|i| lattice.equilibrium(i, 1.2, &u)).collect::<Vec<_>>();
+            let momentum = f.iter().zip(lattice.velocities.iter())
+                .fold(na::Vector3::zeros(), |a, (f, c)| a + na::Vector3::new(c[0] as f64, c[1] as f64, c[2] as f64) * *f);
+
+            assert!((f.iter().sum::<f64>() - 1.2).abs() < 1e-12);
+            assert!((momentum - u * 1.2).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn invalid_configurations_are_rejected() {
+        assert!(load(&config("[8]", r#", "tau": 1.0"#, "")).is_err());
+        assert!(load(&config("[8, 8]", r#", "tau": 0.5"#, "")).is_err());
+        assert!(load(&config("[8, 8]", "", "")).is_err());
+        let bad_box = r#", "cells": [{"type": "wall", "min": [0], "max": [1]}]"#;
+        assert!(load(&config("[8, 8]", r#", "tau": 1.0"#, bad_box)).is_err());
+    }
+
+    #[test]
+    fn bounce_back_box_conserves_mass_and_stops_flow() {
+        let extra = r#", "initial_velocity": [0.05, 0.02]"#;
+        let mut simulator = load(&config("[12, 10]", r#", "viscosity": 0.1"#, extra)).unwrap();
+        let mass = total_mass(&simulator);
+
+        for _ in 0..2000 {
+            simulator.step();
+        }
+
+        assert!((total_mass(&simulator) - mass).abs() < 1e-9 * mass);
+        let max_speed = simulator.velocity().data().iter().fold(0.0, |m: f64, u| m.max(u.magnitude()));
+        assert!(max_speed < 1e-3, "{max_speed}");
+    }
+
+    #[test]
+    fn circle_nodes_are_walls_at_rest() {
+        let extra = r#",
+            "periodic": [true, true],
+            "initial_velocity": [0.05, 0.0],
+            "cells": [{"type": "circle", "center": [8.0, 8.0], "radius": 2.0}]"#;
+        let mut simulator = load(&config("[16, 16]", r#", "tau": 0.8"#, extra)).unwrap();
+        simulator.step();
+
+        assert_eq!(simulator.node_type([8, 10, 0]), NodeType::Wall);
+        assert_eq!(simulator.node_type([8, 11, 0]), NodeType::Fluid);
+        assert_eq!(simulator.velocity().get([8, 8, 0]), na::Vector3::zeros());
+        assert!(simulator.velocity().get([0, 0, 0]).x > 0.0);
+    }
+
+    #[test]
+    fn channel_flow_has_no_slip_walls() {
+        // Closed top and bottom edges are bounce-back walls
+        let extra = r#",
+            "cells": [
+                {"type": "velocity", "min": [0, 0], "max": [1, 11], "velocity": [0.05, 0.0]},
+                {"type": "pressure", "min": [39, 0], "max": [40, 11], "density": 1.0}
+            ]"#;
+        let mut simulator = load(&config("[40, 11]", r#", "tau": 0.8"#, extra)).unwrap();
+        for _ in 0..1000 {
+            simulator.step();
+        }
+
+        let u = |y: usize| simulator.velocity().get([20, y, 0]).x;
+        assert!(u(5) > 0.05, "{}", u(5));
+        assert!(u(0) < 0.5 * u(5));
+        assert!((u(1) - u(9)).abs() < 1e-6);
+    }
+}
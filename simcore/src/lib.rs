@@ -1,28 +1,140 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 use serde::{Deserialize, Serialize};
 
+pub mod arithmetic;
+pub mod automaton;
+pub mod barostat;
+pub mod bond;
+pub mod boundary;
+pub mod constraint;
+pub mod convert;
+pub mod diffusion;
+pub mod error;
+pub mod expression;
+pub mod field;
+pub mod fluid;
+pub mod format;
+pub mod lattice;
+pub mod mesh;
+pub mod obstacle;
 pub mod particle;
+pub mod poisson;
+pub mod random;
+pub mod reaction;
+pub mod rigid;
+pub mod schema;
 pub mod stats;
+pub mod thermostat;
+pub mod units;
+pub mod wave;
+
+pub use error::Error;
+pub use format::Format;
 
 pub type SimFloat = f64;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Rectangular matrix of floats, stored row by row
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Vec<SimFloat>>", into = "Vec<Vec<SimFloat>>")]
+pub struct PropertyMatrix {
+    rows: usize,
+    columns: usize,
+    data: Vec<SimFloat>,
+}
+
+impl PropertyMatrix {
+    pub fn from_rows(rows: Vec<Vec<SimFloat>>) -> Result<Self, String> {
+        let columns = rows.first().map(|r| r.len()).unwrap_or(0);
+        if columns == 0 {
+            return Err("Matrix should have at least one row and column".to_string());
+        }
+        if rows.iter().any(|r| r.len() != columns) {
+            return Err("Matrix rows should all have the same length".to_string());
+        }
+
+        Ok(Self {
+            rows: rows.len(),
+            columns,
+            data: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> SimFloat {
+        self.data[row * self.columns + column]
+    }
+
+    pub fn row(&self, row: usize) -> &[SimFloat] {
+        &self.data[row * self.columns..(row + 1) * self.columns]
+    }
+
+    pub fn data(&self) -> &[SimFloat] {
+        &self.data
+    }
+}
+
+impl TryFrom<Vec<Vec<SimFloat>>> for PropertyMatrix {
+    type Error = String;
+
+    fn try_from(rows: Vec<Vec<SimFloat>>) -> Result<Self, String> {
+        Self::from_rows(rows)
+    }
+}
+
+impl From<PropertyMatrix> for Vec<Vec<SimFloat>> {
+    fn from(matrix: PropertyMatrix) -> Self {
+        matrix.data.chunks(matrix.columns).map(|r| r.to_vec()).collect()
+    }
+}
+
+/// Untagged variants are tried in declaration order: `true` is Bool, `1` is Int
+/// and `1.0` is Float. Numeric lists of length 2 to 4 are vectors, equal length
+/// numeric rows are a Matrix and any other list is an Array
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Property {
+    Bool(bool),
+    Int(i64),
     Float(SimFloat),
     Vector2([SimFloat; 2]),
     Vector3([SimFloat; 3]),
     Vector4([SimFloat; 4]),
+    Matrix(PropertyMatrix),
+    Array(Vec<Property>),
     String(String),
     Nested(HashMap<String, Property>),
 }
 
 impl Property {
-    /// Unwrap property as Float. Panics if property wasn't Float
+    /// Name of the variant, used in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Property::Bool(_) => "Bool",
+            Property::Int(_) => "Int",
+            Property::Float(_) => "Float",
+            Property::Vector2(_) => "Vector2",
+            Property::Vector3(_) => "Vector3",
+            Property::Vector4(_) => "Vector4",
+            Property::Matrix(_) => "Matrix",
+            Property::Array(_) => "Array",
+            Property::String(_) => "String",
+            Property::Nested(_) => "Nested",
+        }
+    }
+
+    /// Unwrap property as Float. Panics if property wasn't Float or Int
     pub fn float(&self) -> SimFloat {
         match self {
             Property::Float(v) => *v,
+            Property::Int(v) => *v as SimFloat,
             _ => panic!("Unwrap Float on incompatible value: {:?}", self)
         }
     }
@@ -67,10 +179,11 @@ impl Property {
         }
     }
 
-    /// Unwrap property as Float. Returns None if property wasn't Float
+    /// Unwrap property as Float. Returns None if property wasn't Float or Int
     pub fn try_float(&self) -> Option<SimFloat> {
         match self {
             Property::Float(v) => Some(*v),
+            Property::Int(v) => Some(*v as SimFloat),
             _ => None
         }
     }
@@ -114,18 +227,381 @@ impl Property {
             _ => None,
         }
     }
+
+    /// Unwrap property as Int. Returns None if property wasn't Int
+    pub fn try_int(&self) -> Option<i64> {
+        match self {
+            Property::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Unwrap property as Bool. Returns None if property wasn't Bool
+    pub fn try_bool(&self) -> Option<bool> {
+        match self {
+            Property::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Unwrap property as Array. Returns None if property wasn't Array
+    pub fn try_array(&self) -> Option<&[Property]> {
+        match self {
+            Property::Array(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Unwrap property as Matrix. Returns None if property wasn't Matrix
+    pub fn try_matrix(&self) -> Option<&PropertyMatrix> {
+        match self {
+            Property::Matrix(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    /// Components of a number or vector, e.g. a position of any dimension
+    pub fn try_components(&self) -> Option<Vec<SimFloat>> {
+        match self {
+            Property::Int(_) | Property::Float(_) => self.try_float().map(|v| vec![v]),
+            Property::Vector2(v) => Some(v.to_vec()),
+            Property::Vector3(v) => Some(v.to_vec()),
+            Property::Vector4(v) => Some(v.to_vec()),
+            Property::Array(a) => a.iter().map(|v| v.try_float()).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// Failure to read a property of expected type from a property map
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyError {
+    Missing {
+        path: String,
+    },
+    WrongType {
+        path: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// Numeric property with wrong number of rows and columns
+    WrongShape {
+        path: String,
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    /// Number outside of the bounds declared in a schema
+    OutOfRange {
+        path: String,
+        value: SimFloat,
+        min: Option<SimFloat>,
+        max: Option<SimFloat>,
+        exclusive_min: bool,
+    },
+    /// Arithmetic between properties that can't be combined element-wise
+    Incompatible {
+        operation: &'static str,
+        left: &'static str,
+        right: &'static str,
+    },
+}
+
+impl PropertyError {
+    /// Key path of the offending property, e.g. `initial_objects[2].mass`.
+    /// Empty for errors not tied to a map
+    pub fn path(&self) -> &str {
+        match self {
+            PropertyError::Missing { path }
+                | PropertyError::WrongType { path, .. }
+                | PropertyError::WrongShape { path, .. }
+                | PropertyError::OutOfRange { path, .. } => path,
+            PropertyError::Incompatible { .. } => "",
+        }
+    }
+
+    /// Prefixes key path with the location of the map it was read from
+    pub fn within(mut self, parent: &str) -> Self {
+        if let PropertyError::Missing { path }
+            | PropertyError::WrongType { path, .. }
+            | PropertyError::WrongShape { path, .. }
+            | PropertyError::OutOfRange { path, .. } = &mut self
+        {
+            *path = if path.is_empty() { parent.to_string() } else { format!("{parent}.{path}") };
+        }
+        self
+    }
+}
+
+impl fmt::Display for PropertyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let subject = match self.path() {
+            "" => "Property".to_string(),
+            path => format!("Property `{path}`"),
+        };
+
+        match self {
+            PropertyError::Missing { path } => write!(f, "Missing property `{path}`"),
+            PropertyError::WrongType { expected, found, .. } => {
+                write!(f, "{subject} should be {expected}, found {found}")
+            }
+            PropertyError::WrongShape { expected, found, .. } => write!(
+                f,
+                "{subject} should be {}x{}, found {}x{}",
+                expected.0, expected.1, found.0, found.1,
+            ),
+            PropertyError::OutOfRange { value, min, max, exclusive_min, .. } => write!(
+                f,
+                "{subject} is {value}, outside of {}{}, {}]",
+                if *exclusive_min { "(" } else { "[" },
+                min.unwrap_or(SimFloat::NEG_INFINITY),
+                max.unwrap_or(SimFloat::INFINITY),
+            ),
+            PropertyError::Incompatible { operation, left, right } => {
+                write!(f, "Cannot {operation} {left} and {right}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PropertyError {}
+
+impl From<PropertyError> for std::io::Error {
+    fn from(error: PropertyError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// Typed access to property maps. Paths may reach into nested maps with dots,
+/// e.g. `thermostat.temperature`
+pub trait PropertyMap {
+    fn get_property(&self, path: &str) -> Result<&Property, PropertyError>;
+
+    fn get_typed<'a, T>(
+        &'a self,
+        path: &str,
+        expected: &'static str,
+        convert: impl FnOnce(&'a Property) -> Option<T>,
+    ) -> Result<T, PropertyError> {
+        let property = self.get_property(path)?;
+        convert(property).ok_or_else(|| PropertyError::WrongType {
+            path: path.to_string(),
+            expected,
+            found: property.type_name(),
+        })
+    }
+
+    fn get_float(&self, path: &str) -> Result<SimFloat, PropertyError> {
+        self.get_typed(path, "Float", Property::try_float)
+    }
+
+    fn get_int(&self, path: &str) -> Result<i64, PropertyError> {
+        self.get_typed(path, "Int", Property::try_int)
+    }
+
+    fn get_bool(&self, path: &str) -> Result<bool, PropertyError> {
+        self.get_typed(path, "Bool", Property::try_bool)
+    }
+
+    fn get_vec2(&self, path: &str) -> Result<[SimFloat; 2], PropertyError> {
+        self.get_typed(path, "Vector2", Property::try_vec2)
+    }
+
+    fn get_vec3(&self, path: &str) -> Result<[SimFloat; 3], PropertyError> {
+        self.get_typed(path, "Vector3", Property::try_vec3)
+    }
+
+    fn get_vec4(&self, path: &str) -> Result<[SimFloat; 4], PropertyError> {
+        self.get_typed(path, "Vector4", Property::try_vec4)
+    }
+
+    fn get_str(&self, path: &str) -> Result<&str, PropertyError> {
+        self.get_typed(path, "String", Property::try_str)
+    }
+
+    fn get_nested(&self, path: &str) -> Result<&HashMap<String, Property>, PropertyError> {
+        self.get_typed(path, "Nested", Property::try_nested)
+    }
+
+    fn get_array(&self, path: &str) -> Result<&[Property], PropertyError> {
+        self.get_typed(path, "Array", Property::try_array)
+    }
+
+    fn get_matrix(&self, path: &str) -> Result<&PropertyMatrix, PropertyError> {
+        self.get_typed(path, "Matrix", Property::try_matrix)
+    }
+
+    /// Converts property with its `TryFrom` implementation, e.g. to a nalgebra vector
+    fn get_as<'a, T>(&'a self, path: &str) -> Result<T, PropertyError>
+        where T: TryFrom<&'a Property, Error = PropertyError>
+    {
+        T::try_from(self.get_property(path)?).map_err(|e| e.within(path))
+    }
+
+    /// Like `get_float`, but a missing property gives `default`
+    fn get_float_or(&self, path: &str, default: SimFloat) -> Result<SimFloat, PropertyError> {
+        match self.get_float(path) {
+            Err(PropertyError::Missing { .. }) => Ok(default),
+            result => result,
+        }
+    }
+
+    /// Non-negative integer, e.g. an iteration count
+    fn get_count(&self, path: &str) -> Result<usize, PropertyError> {
+        let count = self.get_int(path)?;
+        usize::try_from(count).map_err(|_| PropertyError::OutOfRange {
+            path: path.to_string(),
+            value: count as SimFloat,
+            min: Some(0.0),
+            max: None,
+            exclusive_min: false,
+        })
+    }
+
+    /// Like `get_count`, but a missing property gives `default`
+    fn get_count_or(&self, path: &str, default: usize) -> Result<usize, PropertyError> {
+        match self.get_count(path) {
+            Err(PropertyError::Missing { .. }) => Ok(default),
+            result => result,
+        }
+    }
+
+    /// Like `get_float`, but a missing property gives None
+    fn get_float_opt(&self, path: &str) -> Result<Option<SimFloat>, PropertyError> {
+        match self.get_float(path) {
+            Err(PropertyError::Missing { .. }) => Ok(None),
+            result => result.map(Some),
+        }
+    }
+
+    /// Like `get_str`, but a missing property gives None
+    fn get_str_opt(&self, path: &str) -> Result<Option<&str>, PropertyError> {
+        match self.get_str(path) {
+            Err(PropertyError::Missing { .. }) => Ok(None),
+            result => result.map(Some),
+        }
+    }
+}
+
+impl PropertyMap for HashMap<String, Property> {
+    fn get_property(&self, path: &str) -> Result<&Property, PropertyError> {
+        let mut map = self;
+        let mut segments = path.split('.').peekable();
+        let mut traversed = 0;
+
+        while let Some(key) = segments.next() {
+            traversed += key.len();
+            let property = map.get(key).ok_or_else(|| PropertyError::Missing {
+                path: path[..traversed].to_string(),
+            })?;
+
+            if segments.peek().is_none() {
+                return Ok(property);
+            }
+
+            map = property.try_nested().ok_or_else(|| PropertyError::WrongType {
+                path: path[..traversed].to_string(),
+                expected: "Nested",
+                found: property.type_name(),
+            })?;
+            traversed += 1;
+        }
+
+        Err(PropertyError::Missing { path: path.to_string() })
+    }
+}
+
+/// Engine a configuration is meant for, read from `kind` of its simulation
+/// config. Configurations without one are particle simulations
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SimulationKind {
+    #[default]
+    Particles,
+    Fluid,
+    Lattice,
+}
+
+impl SimulationKind {
+    /// Kind of configuration file `filename` in format detected from its extension
+    pub fn from_file(filename: &str) -> Result<Self, Error> {
+        let source = std::fs::read_to_string(filename)?;
+        Self::detect(&source, Format::from_path(filename))
+    }
+
+    /// Kind of configuration text `source`. Everything but `simulation_config.kind` is ignored
+    pub fn detect(source: &str, format: Format) -> Result<Self, Error> {
+        #[derive(Deserialize)]
+        struct Header {
+            #[serde(default)]
+            simulation_config: HashMap<String, Property>,
+        }
+
+        let detect = || {
+            let header = format.deserialize::<Header>(source)?;
+            let kind = header.simulation_config.get_str_opt("kind")
+                .map_err(|e| e.within("simulation_config"))?;
+
+            match kind {
+                None | Some("particles") => Ok(SimulationKind::Particles),
+                Some("fluid") => Ok(SimulationKind::Fluid),
+                Some("lattice") => Ok(SimulationKind::Lattice),
+                Some(kind) => Err(Error::semantic(
+                    "simulation_config.kind",
+                    format!("Unknown simulation kind `{kind}`, expected `particles`, `fluid` or `lattice`"),
+                )),
+            }
+        };
+
+        detect().map_err(|e| e.located(source, format))
+    }
 }
 
 pub mod proto {
-    use std::{collections::HashMap, io::Result as IoResult};
+    use std::{collections::HashMap, io::{Error as IoError, ErrorKind, Result as IoResult}};
 
     use nalgebra as na;
-    use crate::{particle::proto::{InteractionFn, ParticleProto}, stats::Timeseries, Property, SimFloat};
+    use crate::{
+        barostat::proto::{pressure, Barostat},
+        bond::proto::{Bond, BondConfig, BondForces},
+        boundary::proto::{Boundary, BoundaryConfig, BoundaryKind},
+        constraint::proto::{ConstraintSolver, ConstraintsConfig, DistanceConstraint},
+        mesh::proto::{ParticleMesh, ParticleMeshConfig},
+        obstacle::proto::Obstacle2,
+        particle::proto::{InteractionFn, ParticleProto},
+        poisson::proto::ExternalPotential,
+        random::{Rng, RngStreams},
+        rigid::proto::{RigidBody2, RotationalObject},
+        schema::{Schema, SchemaViolations},
+        stats::Timeseries,
+        thermostat::proto::{temperature, Thermostat},
+        units::{Dimension, UnitSystem, UnitSystemConfig},
+        Error, Format, Property, PropertyError, PropertyMap, SimFloat,
+    };
     use serde::{Deserialize, Serialize};
 
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    pub enum Integrator {
+        /// Newtonian dynamics with velocity as state variable
+        #[default]
+        Inertial,
+        /// Brownian dynamics: dx = mobility * F dt + sqrt(2 mobility kT dt) * noise.
+        /// Velocity is not a state variable and only reflects last displacement
+        Overdamped {
+            temperature: SimFloat,
+        },
+    }
+
+    impl Integrator {
+        fn is_inertial(&self) -> bool {
+            *self == Integrator::Inertial
+        }
+    }
+
     #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
     pub struct EulerMethodSolverConfig {
         pub timestep: SimFloat,
+        #[serde(default, skip_serializing_if = "Integrator::is_inertial")]
+        pub integrator: Integrator,
     }
 
     pub type ParticleDefinition = HashMap<String, Property>;
@@ -135,11 +611,33 @@ pub mod proto {
         simulation_config: HashMap<String, Property>,
         solver_config: EulerMethodSolverConfig,
         initial_objects: Vec<ParticleDefinition>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        boundary: Option<BoundaryConfig>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        obstacles: Vec<Obstacle2>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        constraints: Option<ConstraintsConfig>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        bonds: Vec<BondConfig>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        rigid_bodies: Vec<ParticleDefinition>,
+        /// Extra or overriding declarations of particle and rigid body properties
+        #[serde(default, skip_serializing_if = "Schema::is_empty")]
+        schema: Schema,
+        /// Internal unit system quantities like `"1 h"` are converted to. Defaults to SI
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        units: Option<UnitSystemConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        particle_mesh: Option<ParticleMeshConfig>,
+        /// Seed of all randomness in simulation. Defaults to 0
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seed: Option<u64>,
     }
 
     impl Configuration {
+        /// Saves in format detected from extension of `filename`
         pub fn save(&self, filename: &str) -> IoResult<()> {
-            let stringified = serde_json::to_string_pretty(self)?;
+            let stringified = Format::from_path(filename).serialize(self)?;
 
             std::fs::write(filename, stringified)
         }
@@ -149,55 +647,319 @@ pub mod proto {
         fn default() -> Self {
             Self {
                 simulation_config: HashMap::new(),
-                solver_config: EulerMethodSolverConfig { timestep: 0.02 },
-                initial_objects: vec![]
+                solver_config: EulerMethodSolverConfig { timestep: 0.02, integrator: Integrator::Inertial },
+                initial_objects: vec![],
+                boundary: None,
+                obstacles: vec![],
+                constraints: None,
+                bonds: vec![],
+                rigid_bodies: vec![],
+                schema: Schema::new(),
+                units: None,
+                particle_mesh: None,
+                seed: None,
             }
         }
     }
 
+    /// Random streams of particle simulator, one per subsystem
+    const INTEGRATOR_STREAM: usize = 0;
+    const THERMOSTAT_STREAM: usize = 1;
+    const RNG_STREAM_COUNT: usize = 2;
+
     pub struct ParticleSimulator {
         solver: EulerMethodSolver,
         sim_config: HashMap<String, Property>,
         objects: Vec<ParticleProto<2>>,
+        bodies: Vec<RigidBody2>,
         simulation_time: SimFloat,
         interaction_fn: InteractionFn<2>,
+        boundary: Option<Boundary<2>>,
+        obstacles: Vec<Obstacle2>,
+        constraints: Option<ConstraintSolver>,
+        bonds: Vec<Bond>,
+        thermostat: Option<Thermostat>,
+        barostat: Option<Barostat>,
+        potentials: Vec<ExternalPotential>,
+        mesh: Option<ParticleMesh>,
+        rng: RngStreams,
         stats: Option<Timeseries<HashMap<String, Property>>>,
     }
 
+    /// Index of particle with given `name` property
+    fn find_particle(objects: &[ParticleProto<2>], name: &str) -> IoResult<usize> {
+        objects.iter()
+            .position(|p| p.additional_properties.get("name")
+                .and_then(|n| n.try_str()) == Some(name))
+            .ok_or_else(|| IoError::new(
+                ErrorKind::InvalidData,
+                format!("No particle named `{name}` in initial_objects"),
+            ))
+    }
+
+    /// Replaces quantity strings in a configuration with numbers in its internal
+    /// units, checking them against dimensions declared in schemas
+    fn convert_units(config: &mut serde_json::Value) -> Result<(), Error> {
+        // Malformed configurations are reported by deserialization
+        let Some(root) = config.as_object_mut() else { return Ok(()) };
+
+        let units = match root.get("units").map(|u| serde_json::from_value::<UnitSystemConfig>(u.clone())) {
+            Some(Ok(units)) => units,
+            Some(Err(_)) => return Ok(()),
+            None => UnitSystemConfig::default(),
+        };
+        let system = UnitSystem::from_config(&units)?;
+
+        let schema = match root.get("schema").map(|s| serde_json::from_value::<Schema>(s.clone())) {
+            Some(Ok(schema)) => schema,
+            Some(Err(_)) => return Ok(()),
+            None => Schema::new(),
+        };
+        let simulation = Schema::particle_simulation().dimensions()?;
+        let particle = Schema::particle().merged(&schema).dimensions()?;
+        let body = Schema::rigid_body().merged(&schema).dimensions()?;
+
+        for (key, value) in root.iter_mut() {
+            match key.as_str() {
+                "units" | "schema" => {}
+                "simulation_config" => system.convert_json(value, key, &|k| simulation.get(k).copied())?,
+                "solver_config" => system.convert_json(value, key, &|k| (k == "timestep").then_some(Dimension::TIME))?,
+                "initial_objects" | "rigid_bodies" => {
+                    let dimensions = if key == "initial_objects" { &particle } else { &body };
+                    let Some(items) = value.as_array_mut() else { continue };
+                    for (i, item) in items.iter_mut().enumerate() {
+                        system.convert_json(item, &format!("{key}[{i}]"), &|k| dimensions.get(k).copied())?;
+                    }
+                }
+                _ => system.convert_json(value, key, &|_| None)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses `source` into a configuration with quantities in internal units
+    fn parse_configuration(source: &str, format: Format) -> Result<Configuration, Error> {
+        let mut value = match format {
+            // RON writes enum variants as bare identifiers, only typed fields can read them.
+            // Quantity strings in typed fields therefore fail here, see `Format::Ron`
+            Format::Ron => serde_json::to_value(format.deserialize::<Configuration>(source)?)?,
+            _ => format.deserialize::<serde_json::Value>(source)?,
+        };
+        convert_units(&mut value)?;
+
+        serde_json::from_value(value).map_err(|e| {
+            // Values carry no positions, so the source is deserialized again to locate
+            // the error. Its first error may be a different one, in a quantity string
+            match format.deserialize::<Configuration>(source) {
+                Err(located) if located.to_string().contains(&e.to_string()) => located,
+                _ => e.into(),
+            }
+        })
+    }
+
+    /// Checks for what the simulator can't run without, reported before schema violations
+    fn check_semantics(config: &Configuration) -> Result<(), Error> {
+        if !config.simulation_config.contains_key("name") {
+            return Err(Error::semantic("simulation_config", "Simulation has no `name`"));
+        }
+        if !config.simulation_config.contains_key("g_const") {
+            return Err(Error::semantic("simulation_config", "Gravitational interaction requires `g_const`"));
+        }
+
+        for (owner, particles) in [("initial_objects", &config.initial_objects), ("rigid_bodies", &config.rigid_bodies)] {
+            for (i, p) in particles.iter().enumerate() {
+                if p.contains_key("velocity") { continue }
+
+                let particle = match p.get("name").and_then(|n| n.try_str()) {
+                    Some(name) => format!("Particle `{name}`"),
+                    None => format!("Particle {i}"),
+                };
+                return Err(Error::semantic(&format!("{owner}[{i}]"), format!("{particle} has no `velocity`")));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds a particle from a definition validated against particle schema
+    fn particle_from_definition(mut p: ParticleDefinition) -> Result<ParticleProto<2>, PropertyError> {
+        let position = p.get_as("position")?;
+        let velocity = p.get_as("velocity")?;
+        p.remove("position");
+        p.remove("velocity");
+
+        Ok(ParticleProto {
+            position,
+            velocity,
+            additional_properties: p,
+        })
+    }
+
     impl ParticleSimulator {
-        pub fn load(filename: &str) -> IoResult<Self> {
-            let file = std::fs::read_to_string(filename)?;
-            let config: Configuration = serde_json::from_str(&file)?;
+        /// Loads configuration in format detected from extension of `filename`
+        pub fn load(filename: &str) -> Result<Self, Error> {
+            let source = std::fs::read_to_string(filename)?;
+            Self::from_source(&source, Format::from_path(filename))
+        }
+
+        /// Loads configuration from text. Errors carry snippets of `source`
+        pub fn from_source(source: &str, format: Format) -> Result<Self, Error> {
+            parse_configuration(source, format)
+                .and_then(Self::from_configuration)
+                .map_err(|e| e.located(source, format))
+        }
+
+        fn from_configuration(mut config: Configuration) -> Result<Self, Error> {
+            check_semantics(&config)?;
+
+            let mut violations = Schema::particle_simulation().validate(&config.simulation_config)
+                .into_iter()
+                .map(|e| e.within("simulation_config"))
+                .collect::<Vec<_>>();
+            Schema::particle().merged(&config.schema)
+                .validate_all("initial_objects", config.initial_objects.iter_mut(), &mut violations);
+            Schema::rigid_body().merged(&config.schema)
+                .validate_all("rigid_bodies", config.rigid_bodies.iter_mut(), &mut violations);
+            if !violations.is_empty() {
+                return Err(SchemaViolations(violations).into());
+            }
+
+            let objects = config.initial_objects.into_iter()
+                .enumerate()
+                .map(|(i, p)| particle_from_definition(p)
+                    .map_err(|e| e.within(&format!("initial_objects[{i}]"))))
+                .collect::<Result<Vec<_>, _>>()?;
+            let boundary = config.boundary
+                .map(Boundary::try_from)
+                .transpose()
+                .map_err(|e| Error::semantic("boundary", e))?;
+
+            let bodies = config.rigid_bodies.into_iter()
+                .enumerate()
+                .map(|(i, mut p)| {
+                    let orientation = p.get_float("orientation")?;
+                    let angular_velocity = p.get_float("angular_velocity")?;
+                    let inertia = match p.get_float("inertia") {
+                        // Solid disc
+                        Err(PropertyError::Missing { .. }) => {
+                            let mass = p.get_float("mass")?;
+                            let radius = p.get_float("radius")?;
+                            0.5 * mass * radius * radius
+                        }
+                        inertia => inertia?,
+                    };
+                    for key in ["orientation", "angular_velocity", "inertia"] {
+                        p.remove(key);
+                    }
+
+                    let mut body = RigidBody2::new(particle_from_definition(p)?, inertia);
+                    body.orientation = orientation;
+                    body.angular_velocity = angular_velocity;
+                    Ok(body)
+                }.map_err(|e: PropertyError| e.within(&format!("rigid_bodies[{i}]"))))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let constraints = match config.constraints {
+                Some(constraints) => {
+                    let distance = constraints.distance.iter()
+                        .enumerate()
+                        .map(|(i, c)| {
+                            let first = find_particle(&objects, &c.between[0])?;
+                            let second = find_particle(&objects, &c.between[1])?;
+
+                            let h = objects[second].position - objects[first].position;
+                            let h = match boundary.as_ref() {
+                                Some(boundary) => boundary.minimum_image(h),
+                                None => h,
+                            };
+
+                            Ok(DistanceConstraint {
+                                first,
+                                second,
+                                length: c.length.unwrap_or(h.magnitude()),
+                            })
+                        }.map_err(|e: IoError| Error::semantic(&format!("constraints.distance[{i}]"), e)))
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    Some(ConstraintSolver::new(
+                        constraints.tolerance,
+                        constraints.max_iterations,
+                        distance,
+                    ))
+                }
+                None => None,
+            };
+
+            let bonds = config.bonds.iter()
+                .enumerate()
+                .map(|(i, b)| b.resolve(&objects, boundary.as_ref(), |name| find_particle(&objects, name))
+                    .map_err(|e| Error::semantic(&format!("bonds[{i}]"), e)))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let thermostat = match config.simulation_config.get("thermostat") {
+                Some(t) => Some(Thermostat::from_property(t)
+                    .map_err(|e| Error::semantic("simulation_config.thermostat", e))?),
+                None => None,
+            };
+
+            let barostat = match config.simulation_config.get("barostat") {
+                Some(b) => {
+                    if boundary.map(|b| b.kind) != Some(BoundaryKind::Periodic) {
+                        return Err(Error::semantic(
+                            "simulation_config.barostat",
+                            "Barostat requires a periodic boundary",
+                        ));
+                    }
+                    Some(Barostat::from_property(b)
+                        .map_err(|e| Error::semantic("simulation_config.barostat", e))?)
+                }
+                None => None,
+            };
+
+            let mesh = config.particle_mesh.as_ref()
+                .map(ParticleMesh::from_config)
+                .transpose()
+                .map_err(|e| Error::semantic("particle_mesh", e))?;
+
+            // Mesh coupled to mass already carries gravity, pair forces would add it again
+            let g_const = config.simulation_config.get_float("g_const")
+                .map_err(|e| Error::from(e.within("simulation_config")))?;
+            if mesh.as_ref().is_some_and(|m| m.coupling_property() == "mass") && g_const != 0.0 {
+                return Err(Error::semantic(
+                    "particle_mesh.coupling",
+                    "Mesh coupled to `mass` requires `g_const` of 0, gravity would be counted twice",
+                ));
+            }
 
             Ok(Self {
                 solver: EulerMethodSolver::new(config.solver_config),
                 sim_config: config.simulation_config,
-                objects: config.initial_objects.into_iter()
-                    .map(|mut p| ParticleProto {
-                        position: p.remove("position").unwrap().vec2().into(),
-                        velocity: p.remove("velocity").unwrap().vec2().into(),
-                        additional_properties: p,
-                    }).collect::<Vec<_>>(),
+                objects,
+                bodies,
                 simulation_time: 0.0,
                 
                 // TODO: Definable
-                interaction_fn: |p1, p2, options| {
+                interaction_fn: |p1, p2, h, options| {
                     // simulate gravity interaction for prototype
-                    let h = p2.position - p1.position;
                     let dst = h.magnitude();
                     let dir = h / dst;
 
-                    let m1 = if let Some(m) = p1.additional_properties.get("mass") {
-                        m.float()
-                    } else { 1.0 };
-
-                    let m2 = if let Some(m) = p2.additional_properties.get("mass") {
-                        m.float()
-                    } else { 1.0 };
+                    let m1 = p1.mass();
+                    let m2 = p2.mass();
 
-                    let g_const = options["g_const"].float();
+                    let g_const = options.get("g_const").and_then(|g| g.try_float()).unwrap_or(0.0);
                     return g_const * m1 * m2 * dir / (dst * dst);
                 },
+                boundary,
+                obstacles: config.obstacles,
+                constraints,
+                bonds,
+                thermostat,
+                barostat,
+                potentials: vec![],
+                mesh,
+                rng: RngStreams::new(config.seed.unwrap_or(0), RNG_STREAM_COUNT),
                 stats: None,
             })
         }
@@ -205,12 +967,22 @@ pub mod proto {
         pub fn new() -> Self {
             Self {
                 solver: EulerMethodSolver::new(
-                    EulerMethodSolverConfig { timestep: 0.02 },
+                    EulerMethodSolverConfig { timestep: 0.02, integrator: Integrator::Inertial },
                 ),
                 sim_config: HashMap::new(),
                 objects: vec![],
+                bodies: vec![],
                 simulation_time: 0.0,
-                interaction_fn: |_, _, _| { na::SVector::zeros() },
+                interaction_fn: |_, _, _, _| { na::SVector::zeros() },
+                boundary: None,
+                obstacles: vec![],
+                constraints: None,
+                bonds: vec![],
+                thermostat: None,
+                barostat: None,
+                potentials: vec![],
+                mesh: None,
+                rng: RngStreams::new(0, RNG_STREAM_COUNT),
                 stats: None,
             }
         }
@@ -227,23 +999,79 @@ pub mod proto {
         }
 
         pub fn save_statistics(&self, filename: &str) -> IoResult<()> {
+            #[derive(Serialize)]
+            struct Metadata<'a> {
+                rng: &'a RngStreams,
+            }
+
             if let Some(stats) = self.stats.as_ref() {
-                stats.save(filename)?
+                stats.save_with_metadata(filename, Metadata { rng: &self.rng })?
             }
 
             Ok(())
         }
 
         pub fn sim_name(&self) -> &str {
-            self.sim_config["name"].str()
+            self.sim_config.get("name").and_then(|n| n.try_str()).unwrap_or_default()
+        }
+
+        /// Boltzmann constant in simulation units. Defaults to 1.0
+        fn k_boltzmann(&self) -> SimFloat {
+            self.sim_config.get("k_boltzmann")
+                .and_then(|k| k.try_float())
+                .unwrap_or(1.0)
         }
 
         pub fn particles(&self) -> &[ParticleProto<2>] {
             &self.objects
         }
 
+        pub fn rigid_bodies(&self) -> &[RigidBody2] {
+            &self.bodies
+        }
+
+        pub fn boundary(&self) -> Option<&Boundary<2>> {
+            self.boundary.as_ref()
+        }
+
+        pub fn obstacles(&self) -> &[Obstacle2] {
+            &self.obstacles
+        }
+
+        /// Adds a grid potential acting on every particle and rigid body
+        pub fn add_external_potential(&mut self, potential: ExternalPotential) {
+            self.potentials.push(potential);
+        }
+
+        pub fn external_potentials_mut(&mut self) -> &mut [ExternalPotential] {
+            &mut self.potentials
+        }
+
+        /// Couples particles to a grid potential solved from their own density
+        pub fn set_particle_mesh(&mut self, mesh: Option<ParticleMesh>) {
+            self.mesh = mesh;
+        }
+
+        pub fn particle_mesh(&self) -> Option<&ParticleMesh> {
+            self.mesh.as_ref()
+        }
+
+        /// Vector pointing from `from` to `to`, respecting periodic boundaries
+        fn displacement(
+            &self,
+            from: &na::Point<SimFloat, 2>,
+            to: &na::Point<SimFloat, 2>,
+        ) -> na::SVector<SimFloat, 2> {
+            let h = to - from;
+            match self.boundary.as_ref() {
+                Some(boundary) => boundary.minimum_image(h),
+                None => h,
+            }
+        }
+
         // TODO: Definable
-        fn record_stats(&mut self) {
+        /// `virial` is the pair sum of r_ij . F_ij for current positions
+        fn record_stats(&mut self, virial: SimFloat) {
             if self.stats.is_none() { return; }
             let particles = self.particles();
 
@@ -251,21 +1079,15 @@ pub mod proto {
             // potential = -Gm1m2 / r
             let mut kinetic_energy = 0.0;
             let mut potential_energy = 0.0;
+            let g_const = self.sim_config.get("g_const").and_then(|g| g.try_float()).unwrap_or(0.0);
             for (i, x) in particles.iter().enumerate() {
                 for (j, y) in particles.iter().enumerate() {
                     if i == j { continue }
 
-                    let g_const = self.sim_config["g_const"].float();
-
-                    let m1 = if let Some(m) = x.additional_properties.get("mass") {
-                        m.float()
-                    } else { 1.0 };
+                    let m1 = x.mass();
+                    let m2 = y.mass();
 
-                    let m2 = if let Some(m) = y.additional_properties.get("mass") {
-                        m.float()
-                    } else { 1.0 };
-
-                    let r = (x.position - y.position).magnitude();
+                    let r = self.displacement(&y.position, &x.position).magnitude();
                     potential_energy -= g_const * m1 * m2 / r;
 
                     // NOTE: Relative kinetic energy. Can it really be aggregated?
@@ -277,17 +1099,78 @@ pub mod proto {
             let mut hashmap = HashMap::new();
             hashmap.insert("kinetic_energy".to_string(), Property::Float(kinetic_energy));
             hashmap.insert("potential_energy".to_string(), Property::Float(potential_energy));
+            hashmap.insert(
+                "temperature".to_string(),
+                Property::Float(temperature(particles, self.k_boltzmann())),
+            );
+
+            if let Some(boundary) = self.boundary.as_ref() {
+                let volume = boundary.volume();
+                hashmap.insert("volume".to_string(), Property::Float(volume));
+                hashmap.insert(
+                    "pressure".to_string(),
+                    Property::Float(pressure(
+                        particles.iter().chain(self.bodies.iter().map(|b| &b.particle)),
+                        virial,
+                        volume,
+                    )),
+                );
+            }
+
+            if !self.bonds.is_empty() {
+                let bond_energy = self.bonds.iter()
+                    .fold(0.0, |a, b| a + b.energy(particles, self.boundary.as_ref()));
+                hashmap.insert("bond_energy".to_string(), Property::Float(bond_energy));
+            }
 
             for (i, obj) in particles.iter().enumerate() {
-                let name = if let Some(name) = obj.additional_properties.get("name") {
-                    name.str().to_string()
-                } else { i.to_string() };
+                let name = match obj.additional_properties.get("name").and_then(|n| n.try_str()) {
+                    Some(name) => name.to_string(),
+                    None => i.to_string(),
+                };
 
                 let mut obj_props = HashMap::new();
-                obj_props.insert("position".to_string(), Property::Vector2(obj.position.into()));
-                obj_props.insert("velocity".to_string(), Property::Vector2(obj.position.into()));
+                obj_props.insert("position".to_string(), obj.position.into());
+                obj_props.insert("velocity".to_string(), obj.velocity.into());
+
+                hashmap.insert(name, Property::Nested(obj_props));
+            }
+
+            if !self.potentials.is_empty() {
+                let external_energy = self.potentials.iter()
+                    .flat_map(|u| particles.iter().map(move |p| u.energy(p)))
+                    .sum::<SimFloat>();
+                hashmap.insert("external_energy".to_string(), Property::Float(external_energy));
+            }
+
+            if let Some(mesh) = self.mesh.as_ref() {
+                let all = particles.iter().chain(self.bodies.iter().map(|b| &b.particle));
+                hashmap.insert("mesh_energy".to_string(), Property::Float(mesh.energy(all)));
+                if let Some(report) = mesh.last_report() {
+                    hashmap.insert("mesh_iterations".to_string(), Property::Float(report.iterations as SimFloat));
+                    hashmap.insert("mesh_residual".to_string(), Property::Float(report.residual));
+                }
+            }
+
+            if !self.bodies.is_empty() {
+                let rotational_energy = self.bodies.iter()
+                    .fold(0.0, |a, b| a + b.rotational_energy());
+                hashmap.insert("rotational_energy".to_string(), Property::Float(rotational_energy));
+            }
+
+            for (i, body) in self.bodies.iter().enumerate() {
+                let name = match body.particle.additional_properties.get("name").and_then(|n| n.try_str()) {
+                    Some(name) => name.to_string(),
+                    None => format!("body_{i}"),
+                };
+
+                let mut body_props = HashMap::new();
+                body_props.insert("position".to_string(), body.particle.position.into());
+                body_props.insert("velocity".to_string(), body.particle.velocity.into());
+                body_props.insert("orientation".to_string(), Property::Float(body.orientation));
+                body_props.insert("angular_velocity".to_string(), Property::Float(body.angular_velocity));
 
-                hashmap.insert(format!("{}", name), Property::Nested(obj_props));
+                hashmap.insert(name, Property::Nested(body_props));
             }
 
             hashmap.insert("estimated_error".to_string(), Property::Float(self.compute_error()));
@@ -295,22 +1178,138 @@ pub mod proto {
             self.stats.as_mut().unwrap().record(hashmap, Some(self.simulation_time));
         }
 
+        fn apply_boundary(&mut self) {
+            let Some(boundary) = self.boundary.as_ref() else { return };
+
+            self.bodies.retain_mut(|b| boundary.apply(&mut b.particle));
+
+            let mut new_indices = Vec::with_capacity(self.objects.len());
+            let mut kept = 0;
+            for particle in self.objects.iter_mut() {
+                if boundary.apply(particle) {
+                    new_indices.push(Some(kept));
+                    kept += 1;
+                } else {
+                    new_indices.push(None);
+                }
+            }
+
+            if kept == self.objects.len() { return }
+
+            let mut indices = new_indices.iter();
+            self.objects.retain(|_| indices.next().unwrap().is_some());
+
+            if let Some(constraints) = self.constraints.as_mut() {
+                constraints.remap(&new_indices);
+            }
+            self.bonds.retain_mut(|b| b.remap(&new_indices));
+        }
+
         // Try and experiment with dynamic delta?
-        pub fn step(&mut self) {
-            self.record_stats();
+        pub fn step(&mut self) -> Result<(), Error> {
+            if let Some(mesh) = self.mesh.as_mut() {
+                mesh.update(self.objects.iter().chain(self.bodies.iter().map(|b| &b.particle)))?;
+            }
 
-            let mut forces = vec![na::SVector::<SimFloat, 2>::zeros(); self.objects.len()];
-            for (i, x) in self.objects.iter().enumerate() {
-                for (j, y) in self.objects.iter().enumerate() {
+            // Rigid bodies interact with everything through their centers of mass
+            let all = self.objects.iter()
+                .chain(self.bodies.iter().map(|b| &b.particle))
+                .collect::<Vec<_>>();
+            let particle_count = self.objects.len();
+
+            let mut forces = vec![na::SVector::<SimFloat, 2>::zeros(); all.len()];
+            let mut virial = 0.0;
+            for (i, x) in all.iter().enumerate() {
+                for (j, y) in all.iter().enumerate() {
                     if i == j { continue }
-                    let force = (self.interaction_fn)(x, y, &self.sim_config);
+                    let h = self.displacement(&x.position, &y.position);
+                    let force = (self.interaction_fn)(x, y, h, &self.sim_config);
                     forces[i] += force;
+
+                    // Every pair is visited twice and h = r_j - r_i
+                    virial -= 0.5 * h.dot(&force);
+                }
+            }
+
+            self.record_stats(virial);
+
+            for bond in self.bonds.iter() {
+                bond.add_forces(&self.objects, &mut forces[..particle_count], self.boundary.as_ref());
+            }
+
+            for potential in self.potentials.iter() {
+                let particles = self.objects.iter().chain(self.bodies.iter().map(|b| &b.particle));
+                for (force, particle) in forces.iter_mut().zip(particles) {
+                    *force += potential.force(particle);
+                }
+            }
+
+            if let Some(mesh) = self.mesh.as_ref() {
+                let particles = self.objects.iter().chain(self.bodies.iter().map(|b| &b.particle));
+                for (force, particle) in forces.iter_mut().zip(particles) {
+                    *force += mesh.force(particle);
+                }
+            }
+
+            let old_positions = self.objects.iter().map(|p| p.position).collect::<Vec<_>>();
+            let k_boltzmann = self.k_boltzmann();
+            match self.solver.integrator() {
+                Integrator::Inertial => {
+                    self.solver.step_simulation(&mut self.objects, &forces[..particle_count]);
+                    self.solver.step_simulation(&mut self.bodies, &forces[particle_count..]);
+                }
+                Integrator::Overdamped { temperature } => {
+                    let kt = k_boltzmann * temperature;
+                    let rng = self.rng.stream(INTEGRATOR_STREAM);
+                    self.solver.step_overdamped(&mut self.objects, &forces[..particle_count], kt, rng);
+                    self.solver.step_overdamped(&mut self.bodies, &forces[particle_count..], kt, rng);
+                }
+            }
+            self.solver.step_rotation(&mut self.bodies);
+
+            if let Some(thermostat) = self.thermostat.as_mut() {
+                let rng = self.rng.stream(THERMOSTAT_STREAM);
+                thermostat.apply(&mut self.objects, self.solver.delta(), k_boltzmann, rng);
+            }
+
+            if let (Some(barostat), Some(boundary)) = (self.barostat.as_mut(), self.boundary.as_mut()) {
+                let particles = self.objects.iter().chain(self.bodies.iter().map(|b| &b.particle));
+                let current = pressure(particles, virial, boundary.volume());
+                let bodies = self.bodies.iter_mut().map(|b| &mut b.particle);
+                barostat.apply(
+                    boundary,
+                    self.objects.iter_mut().chain(bodies),
+                    current,
+                    self.solver.delta(),
+                );
+            }
+
+            if let Some(constraints) = self.constraints.as_ref() {
+                let boundary = self.boundary.as_ref();
+                constraints.shake(&mut self.objects, &old_positions, boundary, self.solver.delta());
+                constraints.rattle(&mut self.objects, boundary);
+            }
+
+            self.apply_boundary();
+
+            for particle in self.objects.iter_mut() {
+                let radius = particle.radius();
+
+                for obstacle in self.obstacles.iter() {
+                    obstacle.collide(particle, radius);
                 }
             }
 
-            self.solver.step_simulation(&mut self.objects, &forces);
+            for body in self.bodies.iter_mut() {
+                let radius = body.particle.radius();
+
+                for obstacle in self.obstacles.iter() {
+                    obstacle.collide(&mut body.particle, radius);
+                }
+            }
 
             self.simulation_time += self.solver.delta();
+            Ok(())
         }
 
         pub fn time(&self) -> SimFloat {
@@ -335,6 +1334,10 @@ pub mod proto {
             self.config.timestep
         }
 
+        pub fn integrator(&self) -> Integrator {
+            self.config.integrator
+        }
+
         pub fn step_simulation<const N: usize, TObj>(
             &self,
             objects: &mut [TObj],
@@ -344,9 +1347,354 @@ pub mod proto {
                 obj.step(force.clone(), self.config.timestep);
             }
         }
+
+        pub fn step_overdamped<const N: usize, TObj>(
+            &self,
+            objects: &mut [TObj],
+            forces: &[na::SVector<SimFloat, N>],
+            kt: SimFloat,
+            rng: &mut Rng,
+        ) where TObj: BrownianObject<N> {
+            for (obj, force) in std::iter::zip(objects.iter_mut(), forces.iter()) {
+                obj.step_overdamped(*force, rng.gaussian_vector(), kt, self.config.timestep);
+            }
+        }
+
+        pub fn step_rotation<TObj>(&self, objects: &mut [TObj])
+            where TObj: RotationalObject
+        {
+            for obj in objects.iter_mut() {
+                obj.step_rotation(self.config.timestep);
+            }
+        }
     }
 
     pub trait EulerMethodObject<const N: usize> {
         fn step(&mut self, force: na::SVector<SimFloat, N>, delta: SimFloat);
     }
+
+    pub trait BrownianObject<const N: usize> {
+        /// `noise` is a vector of independent standard normal values
+        fn step_overdamped(
+            &mut self,
+            force: na::SVector<SimFloat, N>,
+            noise: na::SVector<SimFloat, N>,
+            kt: SimFloat,
+            delta: SimFloat,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        proto::{Configuration, ParticleSimulator},
+        Error, Format, Property, PropertyError, PropertyMap, SimulationKind,
+    };
+
+    /// Two particles named `a` and `b` plus `extra` top-level configuration entries
+    fn load(extra: &str) -> Result<ParticleSimulator, Error> {
+        load_with("", extra)
+    }
+
+    /// Like [`load`] with `simulation` entries added to `simulation_config`
+    fn load_with(simulation: &str, extra: &str) -> Result<ParticleSimulator, Error> {
+        let source = format!(r#"{{
+            "simulation_config": {{"name": "test", "g_const": 0.0{simulation}}},
+            "solver_config": {{"timestep": 0.01}},
+            "initial_objects": [
+                {{"name": "a", "position": [0.0, 0.0], "velocity": [0.0, 0.0]}},
+                {{"name": "b", "position": [1.0, 0.0], "velocity": [0.0, 0.0]}}
+            ]{extra}
+        }}"#);
+        ParticleSimulator::from_source(&source, Format::Json)
+    }
+
+    #[test]
+    fn property_variants_deserialize_in_declaration_order() {
+        let parse = |json: &str| serde_json::from_str::<Property>(json).unwrap();
+
+        assert_eq!(parse("true"), Property::Bool(true));
+        assert_eq!(parse("3"), Property::Int(3));
+        assert_eq!(parse("3.0"), Property::Float(3.0));
+        assert_eq!(parse("[1, 2.5]"), Property::Vector2([1.0, 2.5]));
+        assert_eq!(parse("[1, 2, 3, 4]"), Property::Vector4([1.0, 2.0, 3.0, 4.0]));
+        assert_eq!(parse("[1, 2, 3, 4, 5]").type_name(), "Array");
+        assert_eq!(parse("[[1, 2], [3, 4]]").type_name(), "Matrix");
+        assert_eq!(parse("[[1, 2], [3]]").type_name(), "Array");
+        assert_eq!(parse(r#"[1, "a"]"#).type_name(), "Array");
+        assert_eq!(parse(r#""a""#), Property::String("a".to_string()));
+        assert_eq!(parse(r#"{"a": 1}"#).type_name(), "Nested");
+    }
+
+    #[test]
+    fn properties_survive_json_round_trip() {
+        let properties = [
+            Property::Bool(false),
+            Property::Int(-2),
+            Property::Float(0.5),
+            Property::Vector3([1.0, 2.0, 3.0]),
+            Property::Matrix(crate::PropertyMatrix::from_rows(vec![vec![1.0, 0.0], vec![0.0, 1.0]]).unwrap()),
+            Property::Array(vec![Property::Int(1), Property::String("b".to_string())]),
+            Property::Nested(HashMap::from([("c".to_string(), Property::Bool(true))])),
+        ];
+
+        for property in properties {
+            let json = serde_json::to_string(&property).unwrap();
+            assert_eq!(serde_json::from_str::<Property>(&json).unwrap(), property, "{json}");
+        }
+    }
+
+    #[test]
+    fn counts_are_non_negative_integers() {
+        let map = HashMap::from([
+            ("iterations".to_string(), Property::Int(20)),
+            ("negative".to_string(), Property::Int(-1)),
+            ("fraction".to_string(), Property::Float(2.5)),
+        ]);
+
+        assert_eq!(map.get_count("iterations"), Ok(20));
+        assert_eq!(map.get_count_or("missing", 7), Ok(7));
+        assert!(matches!(map.get_count("negative"), Err(PropertyError::OutOfRange { .. })));
+        assert!(matches!(map.get_count_or("fraction", 7), Err(PropertyError::WrongType { expected: "Int", .. })));
+    }
+
+    #[test]
+    fn optional_accessors_reject_wrong_types() {
+        let map = HashMap::from([
+            ("tau".to_string(), Property::Int(2)),
+            ("scheme".to_string(), Property::Float(1.0)),
+            ("name".to_string(), Property::String("test".to_string())),
+        ]);
+
+        assert_eq!(map.get_float_or("tau", 1.0), Ok(2.0));
+        assert_eq!(map.get_float_or("omega", 1.5), Ok(1.5));
+        assert_eq!(map.get_float_opt("omega"), Ok(None));
+        assert_eq!(map.get_str_opt("method"), Ok(None));
+        assert_eq!(
+            map.get_str_opt("scheme"),
+            Err(PropertyError::WrongType { path: "scheme".to_string(), expected: "String", found: "Float" }),
+        );
+        assert!(matches!(map.get_float_or("name", 0.0), Err(PropertyError::WrongType { found: "String", .. })));
+        assert!(matches!(map.get_float_opt("name"), Err(PropertyError::WrongType { found: "String", .. })));
+    }
+
+    #[test]
+    fn config_values_of_wrong_type_are_rejected() {
+        let error = load_with(
+            r#", "thermostat": {"type": "berendsen", "target_temperature": 1.0, "tau": "fast"}"#,
+            "",
+        ).err().unwrap();
+        assert!(matches!(&error, Error::Semantic { path, .. } if path == "simulation_config.thermostat"), "{error}");
+        assert!(error.to_string().contains("Property `tau` should be Float, found String"), "{error}");
+    }
+
+    #[test]
+    fn particle_violations_are_reported_together() {
+        let source = r#"{
+            "simulation_config": {"name": "test", "g_const": 0.0},
+            "solver_config": {"timestep": 0.01},
+            "initial_objects": [
+                {"position": [0.0, 0.0], "velocity": [0.0, 0.0], "mass": -1.0},
+                {"position": [1.0, 0.0], "velocity": [0.0, 0.0], "radius": "big"}
+            ],
+            "schema": {"charge": {"type": "float", "required": true}}
+        }"#;
+        let error = ParticleSimulator::from_source(source, Format::Json).err().unwrap();
+        let Error::Schema(violations) = &error else { panic!("{error}") };
+
+        let paths = violations.iter().map(|(v, _)| v.path()).collect::<Vec<_>>();
+        assert_eq!(paths, [
+            "initial_objects[0].charge",
+            "initial_objects[0].mass",
+            "initial_objects[1].charge",
+            "initial_objects[1].radius",
+        ]);
+    }
+
+    #[test]
+    fn particles_get_schema_defaults() {
+        let simulator = load("").unwrap();
+        assert_eq!(simulator.particles()[0].mass(), 1.0);
+        assert_eq!(simulator.particles()[0].additional_properties.get("radius"), Some(&Property::Float(1.0)));
+    }
+
+    #[test]
+    fn quantities_are_loaded_in_internal_units() {
+        let source = r#"{
+            "simulation_config": {"name": "test", "g_const": 0.0},
+            "solver_config": {"timestep": "2 h"},
+            "units": {"time": "h", "mass": "M_earth"},
+            "initial_objects": [
+                {"position": [0.0, 0.0], "velocity": "[1, 0] m/h", "mass": "5.9722e24 kg"}
+            ]
+        }"#;
+        let simulator = ParticleSimulator::from_source(source, Format::Json).unwrap();
+        let particle = &simulator.particles()[0];
+        assert!((particle.mass() - 1.0).abs() < 1e-12);
+        assert_eq!(particle.velocity, nalgebra::Vector2::new(1.0, 0.0));
+
+        let error = ParticleSimulator::from_source(&source.replace("5.9722e24 kg", "3 m"), Format::Json).err().unwrap();
+        assert!(error.to_string().contains("initial_objects[0].mass"), "{error}");
+    }
+
+    #[test]
+    fn semantic_errors_point_into_source() {
+        let source = |simulation: &str, particle: &str| format!(r#"{{
+            "simulation_config": {{{simulation}}},
+            "solver_config": {{"timestep": 0.01}},
+            "initial_objects": [{{{particle}}}]
+        }}"#);
+        let error = |simulation: &str, particle: &str| {
+            ParticleSimulator::from_source(&source(simulation, particle), Format::Json).err().unwrap()
+        };
+        let particle = r#""position": [0.0, 0.0], "velocity": [0.0, 0.0]"#;
+
+        let missing_name = error(r#""g_const": 0.0"#, particle);
+        assert!(matches!(&missing_name, Error::Semantic { path, snippet: Some(s), .. }
+            if path == "simulation_config" && s.line == 2), "{missing_name}");
+        assert!(error(r#""name": "a""#, particle).to_string().contains("`g_const`"));
+
+        let no_velocity = error(r#""name": "a", "g_const": 0.0"#, r#""name": "b", "position": [0.0, 0.0]"#);
+        assert!(matches!(&no_velocity, Error::Semantic { path, message, snippet: Some(s), .. }
+            if path == "initial_objects[0]" && message.contains("Particle `b`") && s.line == 4), "{no_velocity}");
+    }
+
+    /// Uses every Property variant and enum-valued typed fields
+    const FULL_CONFIGURATION: &str = r#"{
+        "simulation_config": {
+            "name": "full",
+            "g_const": 0.5,
+            "flag": true,
+            "count": 3,
+            "direction": [1.0, 0.0],
+            "color": [1.0, 0.5, 0.0, 1.0],
+            "tensor": [[1.0, 0.0], [0.0, 2.0]],
+            "mixed": [1, "two", 3.5],
+            "nested": {"inner": {"depth": 2}}
+        },
+        "solver_config": {"timestep": 0.01, "integrator": {"type": "overdamped", "temperature": 1.5}},
+        "initial_objects": [{"name": "a", "position": [0.0, 0.0], "velocity": [1.0, 0.0], "mass": 2.0}],
+        "boundary": {"kind": "periodic", "min": [-2.0, -2.0], "max": [2.0, 2.0]},
+        "particle_mesh": {
+            "grid": {"shape": [8, 8], "spacing": 0.5, "origin": [-2.0, -2.0]},
+            "boundary": {"type": "periodic"},
+            "interpolation": "tsc",
+            "source_factor": -1.0
+        },
+        "seed": 7
+    }"#;
+
+    #[test]
+    fn configuration_round_trips_through_enabled_formats() {
+        let config: Configuration = serde_json::from_str(FULL_CONFIGURATION).unwrap();
+        let expected = serde_json::to_value(&config).unwrap();
+
+        for format in [Format::Json, Format::Toml, Format::Yaml, Format::Ron] {
+            if !format.is_enabled() { continue }
+
+            let text = format.serialize(&config).unwrap();
+            let read = format.deserialize::<Configuration>(&text)
+                .unwrap_or_else(|e| panic!("{}: {e}\n{text}", format.name()));
+            assert_eq!(serde_json::to_value(&read).unwrap(), expected, "{}:\n{text}", format.name());
+        }
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn ron_reads_quantities_only_in_property_maps() {
+        let source = |timestep: &str| format!(r#"(
+            simulation_config: {{"name": "test", "g_const": 0.0}},
+            solver_config: (timestep: {timestep}),
+            initial_objects: [{{"position": [0.0, 0.0], "velocity": "[2, 0] m/min"}}],
+        )"#);
+
+        let simulator = ParticleSimulator::from_source(&source("0.01"), Format::Ron).unwrap();
+        assert_eq!(simulator.particles()[0].velocity, nalgebra::Vector2::new(2.0 / 60.0, 0.0));
+
+        let error = ParticleSimulator::from_source(&source(r#""1 h""#), Format::Ron).err().unwrap();
+        assert!(matches!(&error, Error::Parse { line: 3, .. }), "{error}");
+    }
+
+    #[test]
+    fn bonds_with_empty_ring_are_rejected() {
+        let error = load(r#", "bonds": [{"type": "area", "ring": [], "stiffness": 1.0}]"#).err().unwrap();
+        assert!(matches!(&error, Error::Semantic { path, .. } if path == "bonds[0]"), "{error}");
+    }
+
+    #[test]
+    fn barostat_pressure_includes_rigid_bodies() {
+        // Particles are at rest, so only the moving body pushes the box outwards
+        let mut simulator = load_with(
+            r#", "barostat": {"type": "berendsen", "target_pressure": 0.0, "tau": 1.0}"#,
+            r#",
+            "rigid_bodies": [{"name": "c", "position": [0.5, 0.5], "velocity": [1.0, 0.0], "mass": 1.0, "inertia": 1.0}],
+            "boundary": {"kind": "periodic", "min": [-2.0, -2.0], "max": [2.0, 2.0]}"#,
+        ).unwrap();
+
+        simulator.step().unwrap();
+
+        assert!(simulator.boundary().unwrap().volume() > 16.0);
+    }
+
+    #[test]
+    fn mass_coupled_mesh_rejects_direct_gravity() {
+        let mesh = r#""particle_mesh": {
+            "grid": {"shape": [8, 8], "spacing": 0.5, "origin": [-2.0, -2.0]},
+            "boundary": {"type": "periodic"},
+            "coupling": "mass",
+            "source_factor": 12.566,
+            "solver": {"method": "fft"}
+        }"#;
+        let source = format!(r#"{{
+            "simulation_config": {{"name": "test", "g_const": 1.0}},
+            "solver_config": {{"timestep": 0.01}},
+            "initial_objects": [{{"name": "a", "position": [0.0, 0.0], "velocity": [0.0, 0.0]}}],
+            {mesh}
+        }}"#);
+        let error = ParticleSimulator::from_source(&source, Format::Json).err().unwrap();
+        assert!(matches!(&error, Error::Semantic { path, .. } if path == "particle_mesh.coupling"), "{error}");
+
+        let mut simulator = load(&format!(", {mesh}")).unwrap();
+        simulator.step().unwrap();
+        assert!(simulator.particle_mesh().unwrap().last_report().is_some());
+    }
+
+    fn overdamped(seed: u64) -> ParticleSimulator {
+        let source = format!(r#"{{
+            "simulation_config": {{"name": "test", "g_const": 0.0}},
+            "solver_config": {{"timestep": 0.01, "integrator": {{"type": "overdamped", "temperature": 1.0}}}},
+            "initial_objects": [{{"name": "a", "position": [0.0, 0.0], "velocity": [0.0, 0.0], "mobility": 1.0}}],
+            "seed": {seed}
+        }}"#);
+        ParticleSimulator::from_source(&source, Format::Json).unwrap()
+    }
+
+    #[test]
+    fn overdamped_runs_are_reproducible_from_seed() {
+        let run = |seed| {
+            let mut simulator = overdamped(seed);
+            (0..10).for_each(|_| simulator.step().unwrap());
+            simulator.particles()[0].position
+        };
+
+        assert_eq!(run(3), run(3));
+        assert_ne!(run(3), run(4));
+        assert_ne!(run(3), nalgebra::Point2::origin());
+    }
+
+    #[test]
+    fn simulation_kind_is_read_from_simulation_config() {
+        let kind = |source: &str| SimulationKind::detect(source, Format::Json);
+
+        assert_eq!(kind(r#"{"simulation_config": {"name": "a"}, "grid": {}}"#).unwrap(), SimulationKind::Particles);
+        assert_eq!(kind(r#"{"simulation_config": {"kind": "fluid"}}"#).unwrap(), SimulationKind::Fluid);
+        assert_eq!(kind(r#"{"simulation_config": {"kind": "lattice"}}"#).unwrap(), SimulationKind::Lattice);
+
+        let error = kind(r#"{"simulation_config": {"kind": "plasma"}}"#).unwrap_err();
+        assert!(matches!(&error, Error::Semantic { path, snippet: Some(_), .. } if path == "simulation_config.kind"), "{error}");
+        assert!(matches!(kind(r#"{"simulation_config": {"kind": 1}}"#), Err(Error::Schema(_))));
+    }
 }
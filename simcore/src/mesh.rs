@@ -0,0 +1,356 @@
+pub mod proto {
+    use std::{
+        collections::HashMap,
+        io::{Error as IoError, ErrorKind, Result as IoResult},
+    };
+
+    use nalgebra as na;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        field::proto::{boundaries_from_spec, Field, FieldBoundary, FieldBoundarySpec, Grid, GridConfig},
+        particle::proto::ParticleProto,
+        poisson::proto::{fft_boundary_error, is_periodic, PoissonMethod, PoissonReport, PoissonSolver},
+        Property, PropertyMap, SimFloat,
+    };
+
+    /// Shape of a particle when spreading it over grid nodes
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum Interpolation {
+        /// Whole particle on the nearest node
+        #[serde(rename = "ngp")]
+        NearestGridPoint,
+        /// Linear weights over the 2^d surrounding nodes
+        #[default]
+        #[serde(rename = "cic")]
+        CloudInCell,
+        /// Quadratic weights over the 3^d nearest nodes
+        #[serde(rename = "tsc")]
+        TriangularShapedCloud,
+    }
+
+    impl Interpolation {
+        /// Nodes along one axis and their weights for grid coordinate `s`
+        fn axis_weights(&self, s: SimFloat) -> ([isize; 3], [SimFloat; 3]) {
+            match self {
+                Interpolation::NearestGridPoint => ([s.round() as isize, 0, 0], [1.0, 0.0, 0.0]),
+                Interpolation::CloudInCell => {
+                    let node = s.floor();
+                    let f = s - node;
+                    let node = node as isize;
+                    ([node, node + 1, 0], [1.0 - f, f, 0.0])
+                }
+                Interpolation::TriangularShapedCloud => {
+                    let node = s.round();
+                    let d = s - node;
+                    let node = node as isize;
+                    (
+                        [node - 1, node, node + 1],
+                        [0.5 * (0.5 - d).powi(2), 0.75 - d * d, 0.5 * (0.5 + d).powi(2)],
+                    )
+                }
+            }
+        }
+
+        /// Grid indices and weights of nodes a particle at `position` is spread over.
+        /// Nodes beyond a non-periodic edge are left out
+        pub fn stencil(&self, grid: &Grid, periodic: [bool; 3], position: [SimFloat; 3]) -> Vec<(usize, SimFloat)> {
+            let axes: [_; 3] = std::array::from_fn(|a| if a < grid.dimension {
+                self.axis_weights((position[a] - grid.origin[a]) / grid.spacing)
+            } else {
+                ([0, 0, 0], [1.0, 0.0, 0.0])
+            });
+
+            let mut stencil = Vec::with_capacity(27);
+            for k in 0..3 {
+                for j in 0..3 {
+                    'node: for i in 0..3 {
+                        let weight = axes[0].1[i] * axes[1].1[j] * axes[2].1[k];
+                        if weight == 0.0 { continue }
+
+                        let mut cell = [0; 3];
+                        for (axis, n) in [i, j, k].into_iter().enumerate() {
+                            let size = grid.shape[axis] as isize;
+                            let mut x = axes[axis].0[n];
+                            if x < 0 || x >= size {
+                                if !periodic[axis] { continue 'node }
+                                x = x.rem_euclid(size);
+                            }
+                            cell[axis] = x as usize;
+                        }
+
+                        stencil.push((grid.index(cell), weight));
+                    }
+                }
+            }
+
+            stencil
+        }
+    }
+
+    fn default_coupling() -> String {
+        "charge".to_string()
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct ParticleMeshConfig {
+        pub grid: GridConfig,
+        pub boundary: FieldBoundarySpec,
+        #[serde(default)]
+        pub interpolation: Interpolation,
+        /// Particle property deposited to the grid and coupling to the potential
+        #[serde(default = "default_coupling")]
+        pub coupling: String,
+        /// Potential solves laplacian(potential) = source_factor * density.
+        /// -1/epsilon_0 for electrostatics, 4 pi G for gravity
+        pub source_factor: SimFloat,
+        /// `method`, `omega`, `tolerance` and `max_iterations` of the Poisson solver
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        pub solver: HashMap<String, Property>,
+    }
+
+    /// Particle-in-cell coupling: particles deposit `coupling` density on the grid,
+    /// the potential is solved from it and forces are gathered back with the same weights
+    pub struct ParticleMesh {
+        interpolation: Interpolation,
+        coupling: String,
+        source_factor: SimFloat,
+        periodic: [bool; 3],
+        density: Field<SimFloat>,
+        potential: Field<SimFloat>,
+        solver: PoissonSolver<SimFloat>,
+    }
+
+    impl ParticleMesh {
+        pub fn new(
+            interpolation: Interpolation,
+            coupling: &str,
+            source_factor: SimFloat,
+            potential: Field<SimFloat>,
+            solver: PoissonSolver<SimFloat>,
+        ) -> Self {
+            Self {
+                interpolation,
+                coupling: coupling.to_string(),
+                source_factor,
+                periodic: std::array::from_fn(|a| matches!(potential.boundaries[a][0], FieldBoundary::Periodic)),
+                density: potential.filled_like(0.0),
+                potential,
+                solver,
+            }
+        }
+
+        pub fn from_config(config: &ParticleMeshConfig) -> IoResult<Self> {
+            let grid = Grid::new(&config.grid.shape, config.grid.spacing, &config.grid.origin)?;
+            if grid.dimension != 2 {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    "Particle mesh of 2D particle simulator requires a 2D grid",
+                ));
+            }
+
+            let boundaries = boundaries_from_spec(&config.boundary, grid.dimension)?;
+            let potential = Field::new(grid, boundaries, 0.0);
+
+            let method = PoissonMethod::from_config(&config.solver)?;
+            if method == PoissonMethod::Fft && !is_periodic(&potential) {
+                return Err(fft_boundary_error());
+            }
+
+            let solver = PoissonSolver::new(
+                method,
+                config.solver.get_float_or("tolerance", 1e-8)?,
+                config.solver.get_count_or("max_iterations", 10000)?,
+            );
+
+            Ok(Self::new(config.interpolation, &config.coupling, config.source_factor, potential, solver))
+        }
+
+        /// Particle property the mesh couples to
+        pub fn coupling_property(&self) -> &str {
+            &self.coupling
+        }
+
+        pub fn interpolation(&self) -> Interpolation {
+            self.interpolation
+        }
+
+        pub fn density(&self) -> &Field<SimFloat> {
+            &self.density
+        }
+
+        pub fn potential(&self) -> &Field<SimFloat> {
+            &self.potential
+        }
+
+        pub fn last_report(&self) -> Option<PoissonReport> {
+            self.solver.last_report()
+        }
+
+        /// Particles without the coupling property are not affected
+        fn coupling<const N: usize>(&self, particle: &ParticleProto<N>) -> SimFloat {
+            particle.additional_properties.get(&self.coupling).and_then(|q| q.try_float()).unwrap_or(0.0)
+        }
+
+        fn stencil<const N: usize>(&self, particle: &ParticleProto<N>) -> Vec<(usize, SimFloat)> {
+            let position = std::array::from_fn(|a| if a < N { particle.position[a] } else { 0.0 });
+            self.interpolation.stencil(&self.density.grid, self.periodic, position)
+        }
+
+        /// Replaces density with the particles' contributions
+        pub fn deposit<'a, const N: usize>(&mut self, particles: impl Iterator<Item = &'a ParticleProto<N>>) {
+            let volume = self.density.grid.cell_volume();
+            self.density.data_mut().fill(0.0);
+
+            for particle in particles {
+                let q = self.coupling(particle);
+                if q == 0.0 { continue }
+
+                for (index, weight) in self.stencil(particle) {
+                    self.density.data_mut()[index] += q * weight / volume;
+                }
+            }
+        }
+
+        /// Solves potential from current density, starting from the last solution
+        pub fn solve(&mut self) -> IoResult<PoissonReport> {
+            let mut source = self.density.clone();
+            for value in source.data_mut() {
+                *value *= self.source_factor;
+            }
+
+            self.solver.solve(&mut self.potential, &source)
+        }
+
+        /// Deposits particles and solves for potential
+        pub fn update<'a, const N: usize>(
+            &mut self,
+            particles: impl Iterator<Item = &'a ParticleProto<N>>,
+        ) -> IoResult<PoissonReport> {
+            self.deposit(particles);
+            self.solve()
+        }
+
+        /// Force -q grad(potential), gradient taken on nodes and interpolated with deposit weights
+        pub fn force<const N: usize>(&self, particle: &ParticleProto<N>) -> na::SVector<SimFloat, N> {
+            let q = self.coupling(particle);
+            if q == 0.0 { return na::SVector::zeros() }
+
+            let grid = self.potential.grid;
+            let mut force = na::SVector::zeros();
+            for (index, weight) in self.stencil(particle) {
+                let gradient = self.potential.gradient(grid.cell(index));
+                for axis in 0..N {
+                    force[axis] -= q * weight * gradient[axis];
+                }
+            }
+
+            force
+        }
+
+        /// Field energy sum(q potential) / 2 of particles
+        pub fn energy<'a, const N: usize>(&self, particles: impl Iterator<Item = &'a ParticleProto<N>>) -> SimFloat {
+            particles
+                .map(|p| {
+                    let potential = self.stencil(p).iter()
+                        .map(|(index, weight)| weight * self.potential.data()[*index])
+                        .sum::<SimFloat>();
+                    0.5 * self.coupling(p) * potential
+                })
+                .sum()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::proto::*;
+    use crate::{
+        field::proto::{Field, FieldBoundary, Grid},
+        particle::proto::ParticleProto,
+        poisson::proto::{PoissonMethod, PoissonSolver},
+        Property,
+    };
+
+    fn charged(x: f64, y: f64, charge: f64) -> ParticleProto<2> {
+        let mut particle = ParticleProto::new();
+        particle.position = nalgebra::Point2::new(x, y);
+        particle.additional_properties.insert("charge".to_string(), Property::Float(charge));
+        particle
+    }
+
+    fn mesh(interpolation: Interpolation, boundary: FieldBoundary<f64>, method: PoissonMethod) -> ParticleMesh {
+        let potential = Field::new(Grid::new(&[16, 16], 0.5, &[]).unwrap(), [[boundary; 2]; 3], 0.0);
+        ParticleMesh::new(interpolation, "charge", -1.0, potential, PoissonSolver::new(method, 1e-8, 10000))
+    }
+
+    #[test]
+    fn deposit_conserves_charge() {
+        let interpolations = [
+            Interpolation::NearestGridPoint,
+            Interpolation::CloudInCell,
+            Interpolation::TriangularShapedCloud,
+        ];
+        // Second particle straddles the periodic edge
+        let particles = [charged(3.3, 4.1, 2.0), charged(7.9, 0.1, -0.5)];
+
+        for interpolation in interpolations {
+            let mut mesh = mesh(interpolation, FieldBoundary::Periodic, PoissonMethod::Fft);
+            mesh.deposit(particles.iter());
+
+            let volume = mesh.density().grid.cell_volume();
+            let total = mesh.density().data().iter().sum::<f64>() * volume;
+            assert!((total - 1.5).abs() < 1e-12, "{interpolation:?}: {total}");
+        }
+    }
+
+    #[test]
+    fn opposite_charges_attract() {
+        let mut mesh = mesh(Interpolation::CloudInCell, FieldBoundary::Dirichlet(0.0), PoissonMethod::Multigrid);
+        // Mirrored about the grid center at 3.75
+        let particles = [charged(2.35, 3.75, 1.0), charged(5.15, 3.75, -1.0)];
+        let report = mesh.update(particles.iter()).unwrap();
+        assert!(report.converged);
+
+        let left = mesh.force(&particles[0]);
+        let right = mesh.force(&particles[1]);
+        assert!(left.x > 0.0 && right.x < 0.0, "{left} {right}");
+        assert!((left.x + right.x).abs() < 1e-6 * left.x);
+    }
+
+    #[test]
+    fn uncharged_particles_feel_no_force() {
+        let mut mesh = mesh(Interpolation::CloudInCell, FieldBoundary::Periodic, PoissonMethod::Fft);
+        let particles = [charged(2.6, 4.0, 1.0), charged(5.4, 4.0, -1.0)];
+        mesh.update(particles.iter()).unwrap();
+
+        let mut neutral = ParticleProto::<2>::new();
+        neutral.position = nalgebra::Point2::new(4.0, 4.0);
+        assert_eq!(mesh.force(&neutral), nalgebra::Vector2::zeros());
+    }
+
+    #[test]
+    fn failed_solve_is_returned() {
+        let mut mesh = mesh(Interpolation::CloudInCell, FieldBoundary::Dirichlet(0.0), PoissonMethod::Fft);
+        assert!(mesh.update([charged(2.0, 2.0, 1.0)].iter()).is_err());
+    }
+
+    #[test]
+    fn fft_requires_periodic_grid() {
+        let config: ParticleMeshConfig = serde_json::from_str(r#"{
+            "grid": {"shape": [8, 8], "spacing": 1.0},
+            "boundary": {"type": "dirichlet", "value": 0.0},
+            "source_factor": -1.0,
+            "solver": {"method": "fft"}
+        }"#).unwrap();
+        assert!(ParticleMesh::from_config(&config).is_err());
+
+        let config = ParticleMeshConfig {
+            solver: HashMap::from([("method".to_string(), Property::String("multigrid".to_string()))]),
+            ..config
+        };
+        assert!(ParticleMesh::from_config(&config).is_ok());
+    }
+}
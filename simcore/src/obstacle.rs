@@ -0,0 +1,442 @@
+pub mod proto {
+    use nalgebra as na;
+    use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::{particle::proto::ParticleProto, SimFloat};
+
+    fn default_restitution() -> SimFloat {
+        1.0
+    }
+
+    /// Restitution above 1 or below 0 would add energy on every bounce
+    fn restitution<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SimFloat, D::Error> {
+        let restitution = SimFloat::deserialize(deserializer)?;
+        if !(0.0..=1.0).contains(&restitution) {
+            return Err(D::Error::custom(format!("Restitution should be in [0, 1], got {restitution}")));
+        }
+        Ok(restitution)
+    }
+
+    fn valid_radius(radius: SimFloat) -> bool {
+        radius.is_finite() && radius > 0.0
+    }
+
+    /// Static object particles bounce off of
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct Obstacle<S> {
+        #[serde(flatten)]
+        pub shape: S,
+        /// Fraction of normal velocity kept after bounce. 1.0 is perfectly elastic
+        #[serde(default = "default_restitution", deserialize_with = "restitution")]
+        pub restitution: SimFloat,
+    }
+
+    pub type Obstacle2 = Obstacle<Shape2>;
+    pub type Obstacle3 = Obstacle<Shape3>;
+
+    /// Derived (de)serialization is wrapped to reject degenerate shapes
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(remote = "Self", tag = "type", rename_all = "snake_case")]
+    pub enum Shape2 {
+        Segment {
+            start: [SimFloat; 2],
+            end: [SimFloat; 2],
+        },
+        /// Closed polygon. Vertices may be in any winding order
+        Polygon {
+            vertices: Vec<[SimFloat; 2]>,
+        },
+        Circle {
+            center: [SimFloat; 2],
+            radius: SimFloat,
+        },
+    }
+
+    /// Derived (de)serialization is wrapped to reject degenerate shapes
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(remote = "Self", tag = "type", rename_all = "snake_case")]
+    pub enum Shape3 {
+        /// Infinite plane. Particles are kept on the side `normal` points to
+        Plane {
+            point: [SimFloat; 3],
+            normal: [SimFloat; 3],
+        },
+        Sphere {
+            center: [SimFloat; 3],
+            radius: SimFloat,
+        },
+        /// Two-sided surface made of triangles indexing into `vertices`
+        TriangleMesh {
+            vertices: Vec<[SimFloat; 3]>,
+            triangles: Vec<[usize; 3]>,
+        },
+    }
+
+    impl Shape2 {
+        /// Error if contact normals of shape would be undefined
+        pub fn validate(&self) -> Result<(), String> {
+            match self {
+                Shape2::Segment { start, end } if start == end => {
+                    Err("Segment should have distinct start and end".to_string())
+                }
+                Shape2::Circle { radius, .. } if !valid_radius(*radius) => {
+                    Err(format!("Circle radius should be positive, got {radius}"))
+                }
+                Shape2::Polygon { vertices } => {
+                    if vertices.len() < 3 {
+                        return Err("Polygon should have at least 3 vertices".to_string());
+                    }
+                    match (0..vertices.len()).find(|i| vertices[*i] == vertices[(i + 1) % vertices.len()]) {
+                        Some(i) => Err(format!("Polygon vertex {i} repeats the next one")),
+                        None => Ok(()),
+                    }
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl Shape3 {
+        /// Error if contact normals of shape would be undefined
+        pub fn validate(&self) -> Result<(), String> {
+            match self {
+                Shape3::Plane { normal, .. } if *normal == [0.0; 3] => {
+                    Err("Plane normal should not be zero".to_string())
+                }
+                Shape3::Sphere { radius, .. } if !valid_radius(*radius) => {
+                    Err(format!("Sphere radius should be positive, got {radius}"))
+                }
+                Shape3::TriangleMesh { vertices, triangles } => {
+                    for (i, triangle) in triangles.iter().enumerate() {
+                        if let Some(index) = triangle.iter().find(|v| **v >= vertices.len()) {
+                            return Err(format!(
+                                "Triangle {i} uses vertex {index}, mesh has {} vertices",
+                                vertices.len(),
+                            ));
+                        }
+
+                        let [a, b, c] = triangle.map(|v| na::Point3::from(vertices[v]));
+                        if (b - a).cross(&(c - a)) == na::Vector3::zeros() {
+                            return Err(format!("Triangle {i} has zero area"));
+                        }
+                    }
+                    Ok(())
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    macro_rules! validated_serde {
+        ($shape:ty) => {
+            impl Serialize for $shape {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    <$shape>::serialize(self, serializer)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $shape {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let shape = <$shape>::deserialize(deserializer)?;
+                    shape.validate().map_err(D::Error::custom)?;
+                    Ok(shape)
+                }
+            }
+        };
+    }
+
+    validated_serde!(Shape2);
+    validated_serde!(Shape3);
+
+    pub trait Collider<const N: usize> {
+        /// Signed distance from point to surface (negative inside) and
+        /// unit normal pointing away from the surface towards the point.
+        fn contact(&self, point: &na::Point<SimFloat, N>) -> (SimFloat, na::SVector<SimFloat, N>);
+    }
+
+    impl<S> Obstacle<S> {
+        /// Pushes particle of given radius out of obstacle and reflects its velocity
+        pub fn collide<const N: usize>(&self, particle: &mut ParticleProto<N>, radius: SimFloat)
+            where S: Collider<N>
+        {
+            let (distance, normal) = self.shape.contact(&particle.position);
+            if distance >= radius { return }
+
+            particle.position += normal * (radius - distance);
+
+            let normal_velocity = particle.velocity.dot(&normal);
+            if normal_velocity < 0.0 {
+                particle.velocity -= (1.0 + self.restitution) * normal_velocity * normal;
+            }
+        }
+    }
+
+    fn ball_contact<const N: usize>(
+        point: &na::Point<SimFloat, N>,
+        center: &na::Point<SimFloat, N>,
+        radius: SimFloat,
+    ) -> (SimFloat, na::SVector<SimFloat, N>) {
+        let h = point - center;
+        let dst = h.magnitude();
+        if dst == 0.0 {
+            let mut normal = na::SVector::zeros();
+            normal[0] = 1.0;
+            return (-radius, normal);
+        }
+
+        (dst - radius, h / dst)
+    }
+
+    fn closest_on_segment<const N: usize>(
+        point: &na::Point<SimFloat, N>,
+        start: &na::Point<SimFloat, N>,
+        end: &na::Point<SimFloat, N>,
+    ) -> na::Point<SimFloat, N> {
+        let edge = end - start;
+        let length_squared = edge.magnitude_squared();
+        if length_squared == 0.0 {
+            return *start;
+        }
+
+        let t = ((point - start).dot(&edge) / length_squared).clamp(0.0, 1.0);
+        start + edge * t
+    }
+
+    fn segment_contact(
+        point: &na::Point2<SimFloat>,
+        start: &na::Point2<SimFloat>,
+        end: &na::Point2<SimFloat>,
+    ) -> (SimFloat, na::Vector2<SimFloat>) {
+        let closest = closest_on_segment(point, start, end);
+        let h = point - closest;
+        let dst = h.magnitude();
+        if dst == 0.0 {
+            // Point lies exactly on the segment, use its perpendicular
+            let edge = end - start;
+            return (0.0, na::Vector2::new(-edge.y, edge.x).normalize());
+        }
+
+        (dst, h / dst)
+    }
+
+    fn polygon_contains(point: &na::Point2<SimFloat>, vertices: &[na::Point2<SimFloat>]) -> bool {
+        let mut inside = false;
+        for i in 0..vertices.len() {
+            let a = vertices[i];
+            let b = vertices[(i + 1) % vertices.len()];
+
+            if (a.y > point.y) != (b.y > point.y)
+                && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+            {
+                inside = !inside;
+            }
+        }
+
+        inside
+    }
+
+    impl Collider<2> for Shape2 {
+        fn contact(&self, point: &na::Point2<SimFloat>) -> (SimFloat, na::Vector2<SimFloat>) {
+            match self {
+                Shape2::Segment { start, end } => {
+                    segment_contact(point, &(*start).into(), &(*end).into())
+                }
+                Shape2::Circle { center, radius } => {
+                    ball_contact(point, &(*center).into(), *radius)
+                }
+                Shape2::Polygon { vertices } => {
+                    let vertices = vertices.iter()
+                        .map(|v| na::Point2::from(*v))
+                        .collect::<Vec<_>>();
+
+                    let (dst, normal) = (0..vertices.len())
+                        .map(|i| segment_contact(
+                            point,
+                            &vertices[i],
+                            &vertices[(i + 1) % vertices.len()],
+                        ))
+                        .min_by(|a, b| a.0.total_cmp(&b.0))
+                        .unwrap_or((SimFloat::INFINITY, na::Vector2::x()));
+
+                    if polygon_contains(point, &vertices) {
+                        (-dst, -normal)
+                    } else {
+                        (dst, normal)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Closest point on triangle, see "Real-Time Collision Detection" by C. Ericson
+    fn closest_on_triangle(
+        p: &na::Point3<SimFloat>,
+        a: &na::Point3<SimFloat>,
+        b: &na::Point3<SimFloat>,
+        c: &na::Point3<SimFloat>,
+    ) -> na::Point3<SimFloat> {
+        let ab = b - a;
+        let ac = c - a;
+        let ap = p - a;
+
+        let d1 = ab.dot(&ap);
+        let d2 = ac.dot(&ap);
+        if d1 <= 0.0 && d2 <= 0.0 { return *a }
+
+        let bp = p - b;
+        let d3 = ab.dot(&bp);
+        let d4 = ac.dot(&bp);
+        if d3 >= 0.0 && d4 <= d3 { return *b }
+
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+            return a + ab * (d1 / (d1 - d3));
+        }
+
+        let cp = p - c;
+        let d5 = ab.dot(&cp);
+        let d6 = ac.dot(&cp);
+        if d6 >= 0.0 && d5 <= d6 { return *c }
+
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+            return a + ac * (d2 / (d2 - d6));
+        }
+
+        let va = d3 * d6 - d5 * d4;
+        if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+            return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+        }
+
+        let denom = 1.0 / (va + vb + vc);
+        a + ab * (vb * denom) + ac * (vc * denom)
+    }
+
+    impl Collider<3> for Shape3 {
+        fn contact(&self, point: &na::Point3<SimFloat>) -> (SimFloat, na::Vector3<SimFloat>) {
+            match self {
+                Shape3::Plane { point: origin, normal } => {
+                    let normal = na::Vector3::from(*normal).normalize();
+                    ((point - na::Point3::from(*origin)).dot(&normal), normal)
+                }
+                Shape3::Sphere { center, radius } => {
+                    ball_contact(point, &(*center).into(), *radius)
+                }
+                Shape3::TriangleMesh { vertices, triangles } => {
+                    let mut result = (SimFloat::INFINITY, na::Vector3::x());
+
+                    for [a, b, c] in triangles.iter() {
+                        let a = na::Point3::from(vertices[*a]);
+                        let b = na::Point3::from(vertices[*b]);
+                        let c = na::Point3::from(vertices[*c]);
+
+                        let h = point - closest_on_triangle(point, &a, &b, &c);
+                        let dst = h.magnitude();
+                        if dst >= result.0 { continue }
+
+                        let normal = if dst == 0.0 {
+                            (b - a).cross(&(c - a)).normalize()
+                        } else { h / dst };
+
+                        result = (dst, normal);
+                    }
+
+                    result
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra as na;
+
+    use super::proto::*;
+    use crate::particle::proto::ParticleProto;
+
+    #[test]
+    fn shapes_round_trip_through_json() {
+        let json = r#"[
+            {"type": "segment", "start": [0.0, 0.0], "end": [1.0, 0.0]},
+            {"type": "polygon", "vertices": [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]], "restitution": 0.5},
+            {"type": "circle", "center": [0.0, 0.0], "radius": 2.0}
+        ]"#;
+        let obstacles: Vec<Obstacle2> = serde_json::from_str(json).unwrap();
+        assert_eq!(obstacles[1].restitution, 0.5);
+        assert_eq!(obstacles[2].restitution, 1.0);
+
+        let again: Vec<Obstacle2> = serde_json::from_str(&serde_json::to_string(&obstacles).unwrap()).unwrap();
+        assert_eq!(again.len(), 3);
+    }
+
+    #[test]
+    fn degenerate_shapes_are_rejected() {
+        for json in [
+            r#"{"type": "segment", "start": [1.0, 1.0], "end": [1.0, 1.0]}"#,
+            r#"{"type": "polygon", "vertices": [[0.0, 0.0], [1.0, 0.0]]}"#,
+            r#"{"type": "polygon", "vertices": [[0.0, 0.0], [1.0, 0.0], [1.0, 0.0], [0.0, 1.0]]}"#,
+            r#"{"type": "circle", "center": [0.0, 0.0], "radius": 0.0}"#,
+            r#"{"type": "circle", "center": [0.0, 0.0], "radius": -1.0}"#,
+            r#"{"type": "circle", "center": [0.0, 0.0], "radius": 1.0, "restitution": -0.5}"#,
+            r#"{"type": "segment", "start": [0.0, 0.0], "end": [1.0, 0.0], "restitution": 1.5}"#,
+        ] {
+            assert!(serde_json::from_str::<Obstacle2>(json).is_err(), "{json}");
+        }
+        let circle = r#"{"type": "circle", "center": [0.0, 0.0], "radius": 1.0, "restitution": 0.5}"#;
+        assert_eq!(serde_json::from_str::<Obstacle2>(circle).unwrap().restitution, 0.5);
+
+        for json in [
+            r#"{"type": "plane", "point": [0.0, 0.0, 0.0], "normal": [0.0, 0.0, 0.0]}"#,
+            r#"{"type": "triangle_mesh", "vertices": [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]], "triangles": [[0, 1, 3]]}"#,
+            r#"{"type": "triangle_mesh", "vertices": [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0]], "triangles": [[0, 1, 2]]}"#,
+            r#"{"type": "sphere", "center": [0.0, 0.0, 0.0], "radius": -2.0}"#,
+            r#"{"type": "sphere", "center": [0.0, 0.0, 0.0], "radius": 1.0, "restitution": 2.0}"#,
+        ] {
+            assert!(serde_json::from_str::<Obstacle3>(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn polygon_contact_is_negative_inside() {
+        let square = Shape2::Polygon { vertices: vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]] };
+
+        let (distance, normal) = square.contact(&na::Point2::new(1.0, 0.5));
+        assert!((distance + 0.5).abs() < 1e-12);
+        assert!((normal - na::Vector2::new(0.0, -1.0)).norm() < 1e-12);
+
+        let (distance, _) = square.contact(&na::Point2::new(3.0, 1.0));
+        assert!((distance - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn triangle_mesh_contact_on_surface_has_unit_normal() {
+        let mesh = Shape3::TriangleMesh {
+            vertices: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            triangles: vec![[0, 1, 2]],
+        };
+
+        let (distance, normal) = mesh.contact(&na::Point3::new(0.2, 0.2, 0.0));
+        assert!(distance.abs() < 1e-12);
+        assert!((normal.norm() - 1.0).abs() < 1e-12);
+
+        let (distance, _) = mesh.contact(&na::Point3::new(0.2, 0.2, 0.5));
+        assert!((distance - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn collision_applies_restitution() {
+        let wall = Obstacle {
+            shape: Shape2::Segment { start: [-10.0, 0.0], end: [10.0, 0.0] },
+            restitution: 0.5,
+        };
+
+        let mut particle = ParticleProto::<2>::new();
+        particle.position = na::Point2::new(0.0, 0.5);
+        particle.velocity = na::Vector2::new(1.0, -2.0);
+        wall.collide(&mut particle, 1.0);
+
+        assert!((particle.position.y - 1.0).abs() < 1e-12);
+        assert!((particle.velocity - na::Vector2::new(1.0, 1.0)).norm() < 1e-12);
+    }
+}
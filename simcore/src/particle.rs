@@ -3,11 +3,14 @@ pub mod proto {
     // NOTE: nalgebra is not the fastest library but it is accurate
     use nalgebra as na;
 
-    use crate::{proto::EulerMethodObject, SimFloat, Property};
+    use crate::{proto::{BrownianObject, EulerMethodObject}, schema::particle_schema, SimFloat, Property};
 
+    /// Force acting on `p1` from `p2`. `displacement` points from `p1` to `p2`
+    /// and already accounts for periodic boundaries
     pub type InteractionFn<const N: usize> = fn(
         p1: &ParticleProto<N>,
         p2: &ParticleProto<N>,
+        displacement: na::SVector<SimFloat, N>,
         simulation_properties: &HashMap<String, Property>
     ) -> na::SVector<SimFloat, N>;
 
@@ -25,18 +28,90 @@ pub mod proto {
                 additional_properties: HashMap::new(),
             }
         }
+
+        /// Float property, or its default from the particle schema.
+        /// Zero if neither is a number
+        pub fn float_property(&self, name: &str) -> SimFloat {
+            self.additional_properties.get(name)
+                .or_else(|| particle_schema().default_value(name))
+                .and_then(|v| v.try_float())
+                .unwrap_or(0.0)
+        }
+
+        pub fn mass(&self) -> SimFloat {
+            self.float_property("mass")
+        }
+
+        pub fn radius(&self) -> SimFloat {
+            self.float_property("radius")
+        }
     }
 
     impl<const N: usize> EulerMethodObject<N> for ParticleProto<N> {
         // TODO: Definable
         fn step(&mut self, force: na::SVector<SimFloat, N>, delta: SimFloat) {
-            let mass = if let Some(m) = self.additional_properties.get("mass") {
-                m.float()
-            } else { 1.0 };
+            let mass = self.mass();
             self.velocity += force / mass * delta;
             self.position += self.velocity * delta;
         }
     }
+
+    impl<const N: usize> BrownianObject<N> for ParticleProto<N> {
+        fn step_overdamped(
+            &mut self,
+            force: na::SVector<SimFloat, N>,
+            noise: na::SVector<SimFloat, N>,
+            kt: SimFloat,
+            delta: SimFloat,
+        ) {
+            let mobility = self.float_property("mobility");
+
+            let displacement = mobility * force * delta
+                + (2.0 * mobility * kt * delta).sqrt() * noise;
+            self.position += displacement;
+            self.velocity = displacement / delta;
+        }
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use nalgebra as na;
+
+    use super::proto::*;
+    use crate::{proto::BrownianObject, random::Rng, Property};
 
+    fn particle(mobility: f64) -> ParticleProto<2> {
+        let mut particle = ParticleProto::new();
+        particle.additional_properties.insert("mobility".to_string(), Property::Float(mobility));
+        particle
+    }
+
+    #[test]
+    fn overdamped_drift_is_mobility_times_force() {
+        let mut particle = particle(0.5);
+
+        particle.step_overdamped(na::Vector2::new(2.0, -4.0), na::Vector2::zeros(), 1.0, 0.1);
+
+        assert!((particle.position - na::Point2::new(0.1, -0.2)).norm() < 1e-12);
+        assert!((particle.velocity - na::Vector2::new(1.0, -2.0)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn overdamped_diffusion_follows_einstein_relation() {
+        let (mobility, kt, delta, steps) = (0.5, 2.0, 0.01, 100);
+        let mut rng = Rng::new(11);
+        let mut particles = (0..500).map(|_| particle(mobility)).collect::<Vec<_>>();
+
+        for _ in 0..steps {
+            for p in particles.iter_mut() {
+                p.step_overdamped(na::Vector2::zeros(), rng.gaussian_vector(), kt, delta);
+            }
+        }
+
+        // <r^2> = 2 d D t with D = mobility * kT
+        let msd = particles.iter().map(|p| p.position.coords.magnitude_squared()).sum::<f64>() / 500.0;
+        let expected = 2.0 * 2.0 * mobility * kt * delta * steps as f64;
+        assert!((msd - expected).abs() / expected < 0.1, "{msd} vs {expected}");
+    }
+}
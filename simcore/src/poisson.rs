@@ -0,0 +1,579 @@
+pub mod proto {
+    use std::{
+        collections::HashMap,
+        io::{Error as IoError, ErrorKind, Result as IoResult},
+    };
+
+    use nalgebra as na;
+
+    use crate::{
+        field::proto::{scalar_map, Field, FieldBoundary, FieldMapConfig, FieldSolver, FieldValue, Grid},
+        particle::proto::ParticleProto,
+        Property, PropertyMap, SimFloat,
+    };
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum PoissonMethod {
+        Jacobi,
+        GaussSeidel,
+        /// Successive over-relaxation with factor `omega` in (0, 2)
+        Sor { omega: SimFloat },
+        /// Geometric multigrid V-cycles with Gauss-Seidel smoothing
+        Multigrid,
+        /// Direct spectral solve. Requires periodic boundaries on every side
+        Fft,
+    }
+
+    impl PoissonMethod {
+        /// Reads `method` (multigrid by default) and `omega` of SOR
+        pub fn from_config(config: &HashMap<String, Property>) -> IoResult<Self> {
+            Ok(match config.get_str_opt("method")? {
+                Some("jacobi") => PoissonMethod::Jacobi,
+                Some("gauss_seidel") => PoissonMethod::GaussSeidel,
+                Some("sor") => {
+                    let omega = config.get_float_or("omega", 1.5)?;
+                    // Over-relaxation diverges outside of this range
+                    if !(omega > 0.0 && omega < 2.0) {
+                        return Err(IoError::new(
+                            ErrorKind::InvalidData,
+                            format!("SOR `omega` should be in (0, 2), got {omega}"),
+                        ));
+                    }
+                    PoissonMethod::Sor { omega }
+                }
+                None | Some("multigrid") => PoissonMethod::Multigrid,
+                Some("fft") => PoissonMethod::Fft,
+                Some(method) => return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown Poisson method `{method}`"),
+                )),
+            })
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PoissonReport {
+        pub iterations: usize,
+        /// Root mean square of `source - laplacian(potential)` relative to the source
+        pub residual: SimFloat,
+        pub converged: bool,
+    }
+
+    fn rms<T: FieldValue>(values: &[T]) -> SimFloat {
+        (values.iter().fold(0.0, |a, v| a + v.dot(v)) / values.len() as SimFloat).sqrt()
+    }
+
+    /// Writes `source - laplacian(potential)` to `out` and returns its root mean square
+    pub fn residual<T: FieldValue>(potential: &Field<T>, source: &Field<T>, out: &mut [T]) -> SimFloat {
+        for (index, cell) in potential.grid.cells().enumerate() {
+            out[index] = source.data()[index] - potential.laplacian(cell);
+        }
+
+        rms(out)
+    }
+
+    /// Without a Dirichlet side potential is only defined up to a constant and
+    /// only the fluctuating part of the source has a solution
+    fn is_singular<T>(field: &Field<T>) -> bool {
+        field.boundaries[..field.grid.dimension].iter()
+            .flatten()
+            .all(|b| !matches!(b, FieldBoundary::Dirichlet(_)))
+    }
+
+    pub(crate) fn is_periodic<T>(field: &Field<T>) -> bool {
+        field.boundaries[..field.grid.dimension].iter()
+            .flatten()
+            .all(|b| matches!(b, FieldBoundary::Periodic))
+    }
+
+    /// One Gauss-Seidel sweep with relaxation `omega`
+    fn relax<T: FieldValue>(potential: &mut Field<T>, source: &Field<T>, omega: SimFloat) {
+        let grid = potential.grid;
+        let h2 = grid.spacing * grid.spacing;
+        let diagonal = 2.0 * grid.dimension as SimFloat;
+
+        for (index, cell) in grid.cells().enumerate() {
+            let mut sum = T::zero();
+            for axis in 0..grid.dimension {
+                sum += potential.neighbour(cell, axis, 1) + potential.neighbour(cell, axis, -1);
+            }
+
+            let old = potential.data()[index];
+            let new = (sum - source.data()[index] * h2) * (1.0 / diagonal);
+            potential.data_mut()[index] = old * (1.0 - omega) + new * omega;
+        }
+    }
+
+    fn jacobi<T: FieldValue>(potential: &mut Field<T>, source: &Field<T>) {
+        let grid = potential.grid;
+        let h2 = grid.spacing * grid.spacing;
+        let diagonal = 2.0 * grid.dimension as SimFloat;
+
+        let next = grid.cells().enumerate().map(|(index, cell)| {
+            let mut sum = T::zero();
+            for axis in 0..grid.dimension {
+                sum += potential.neighbour(cell, axis, 1) + potential.neighbour(cell, axis, -1);
+            }
+            (sum - source.data()[index] * h2) * (1.0 / diagonal)
+        }).collect::<Vec<_>>();
+
+        potential.data_mut().copy_from_slice(&next);
+    }
+
+    /// Grid with every used axis halved, rounding up
+    fn coarsen(grid: &Grid) -> Option<Grid> {
+        if (0..grid.dimension).any(|a| grid.shape[a] < 4) { return None }
+
+        let mut coarse = *grid;
+        for axis in 0..grid.dimension {
+            coarse.shape[axis] = grid.shape[axis].div_ceil(2);
+        }
+        coarse.spacing *= 2.0;
+        Some(coarse)
+    }
+
+    fn v_cycle<T: FieldValue>(potential: &mut Field<T>, source: &Field<T>) {
+        const SMOOTHING: usize = 2;
+        const COARSEST_SWEEPS: usize = 50;
+
+        let grid = potential.grid;
+        let Some(coarse_grid) = coarsen(&grid) else {
+            for _ in 0..COARSEST_SWEEPS {
+                relax(potential, source, 1.0);
+            }
+            return;
+        };
+
+        for _ in 0..SMOOTHING {
+            relax(potential, source, 1.0);
+        }
+
+        let mut r = vec![T::zero(); grid.len()];
+        residual(potential, source, &mut r);
+
+        // Restriction averages children, prolongation injects the coarse value back
+        let homogeneous = potential.boundaries.map(|sides| sides.map(|b| b.homogeneous()));
+        let mut coarse_source = Field::new(coarse_grid, homogeneous, T::zero());
+        let mut counts = vec![0.0; coarse_grid.len()];
+        for (index, cell) in grid.cells().enumerate() {
+            let coarse_index = coarse_grid.index(cell.map(|c| c / 2));
+            coarse_source.data_mut()[coarse_index] += r[index];
+            counts[coarse_index] += 1.0;
+        }
+        for (value, count) in coarse_source.data_mut().iter_mut().zip(counts) {
+            *value = *value * (1.0 / count);
+        }
+
+        let mut correction = coarse_source.filled_like(T::zero());
+        v_cycle(&mut correction, &coarse_source);
+
+        for (index, cell) in grid.cells().enumerate() {
+            potential.data_mut()[index] += correction.get(cell.map(|c| c / 2));
+        }
+
+        for _ in 0..SMOOTHING {
+            relax(potential, source, 1.0);
+        }
+    }
+
+    /// In-place discrete Fourier transform. Radix-2 for powers of two, direct otherwise
+    fn fft(data: &mut [na::Complex<SimFloat>], inverse: bool) {
+        let n = data.len();
+        let sign = if inverse { 1.0 } else { -1.0 };
+        if n <= 1 { return }
+
+        if !n.is_power_of_two() {
+            let input = data.to_vec();
+            for (k, out) in data.iter_mut().enumerate() {
+                *out = input.iter().enumerate().fold(na::Complex::new(0.0, 0.0), |a, (j, x)| {
+                    let angle = sign * std::f64::consts::TAU * ((j * k) % n) as SimFloat / n as SimFloat;
+                    a + x * na::Complex::from_polar(1.0, angle)
+                });
+            }
+            return;
+        }
+
+        let bits = n.trailing_zeros();
+        for i in 0..n {
+            let j = i.reverse_bits() >> (usize::BITS - bits);
+            if i < j { data.swap(i, j) }
+        }
+
+        let mut length = 2;
+        while length <= n {
+            let root = na::Complex::from_polar(1.0, sign * std::f64::consts::TAU / length as SimFloat);
+            for start in (0..n).step_by(length) {
+                let mut w = na::Complex::new(1.0, 0.0);
+                for k in 0..length / 2 {
+                    let even = data[start + k];
+                    let odd = data[start + k + length / 2] * w;
+                    data[start + k] = even + odd;
+                    data[start + k + length / 2] = even - odd;
+                    w *= root;
+                }
+            }
+            length *= 2;
+        }
+    }
+
+    /// Transforms every line of `data` along `axis`
+    fn fft_axis(grid: &Grid, data: &mut [na::Complex<SimFloat>], axis: usize, inverse: bool) {
+        let n = grid.shape[axis];
+        let mut line = vec![na::Complex::new(0.0, 0.0); n];
+        for cell in grid.cells().filter(|c| c[axis] == 0) {
+            let indices = (0..n).map(|i| {
+                let mut c = cell;
+                c[axis] = i;
+                grid.index(c)
+            }).collect::<Vec<_>>();
+
+            for (l, i) in line.iter_mut().zip(indices.iter()) {
+                *l = data[*i];
+            }
+            fft(&mut line, inverse);
+            for (l, i) in line.iter().zip(indices.iter()) {
+                data[*i] = *l;
+            }
+        }
+    }
+
+    fn solve_fft<T: FieldValue>(potential: &mut Field<T>, source: &Field<T>) {
+        let grid = potential.grid;
+        let h2 = grid.spacing * grid.spacing;
+
+        // Eigenvalues of the discrete Laplacian for each wave vector
+        let eigenvalues = grid.cells().map(|cell| {
+            (0..grid.dimension).fold(0.0, |a, axis| {
+                let k = cell[axis] as SimFloat / grid.shape[axis] as SimFloat;
+                a + (2.0 * (std::f64::consts::TAU * k).cos() - 2.0) / h2
+            })
+        }).collect::<Vec<_>>();
+
+        let components = (0..T::COMPONENTS).map(|c| {
+            let mut data = source.data().iter()
+                .map(|v| na::Complex::new(v.component(c), 0.0))
+                .collect::<Vec<_>>();
+
+            for axis in 0..grid.dimension {
+                fft_axis(&grid, &mut data, axis, false);
+            }
+            for (value, eigenvalue) in data.iter_mut().zip(eigenvalues.iter()) {
+                // Zero mode is the free constant of a periodic potential
+                *value = if *eigenvalue == 0.0 { na::Complex::new(0.0, 0.0) } else { *value / *eigenvalue };
+            }
+            for axis in 0..grid.dimension {
+                fft_axis(&grid, &mut data, axis, true);
+            }
+
+            data.into_iter().map(|v| v.re / grid.len() as SimFloat).collect::<Vec<_>>()
+        }).collect::<Vec<_>>();
+
+        for (index, value) in potential.data_mut().iter_mut().enumerate() {
+            *value = T::from_components(|c| components[c][index]);
+        }
+    }
+
+    pub(crate) fn fft_boundary_error() -> IoError {
+        IoError::new(
+            ErrorKind::InvalidData,
+            "FFT Poisson solver requires periodic boundaries on every side",
+        )
+    }
+
+    /// Solver of laplacian(potential) = source. Use source = -charge density / epsilon
+    /// for electrostatics and 4 pi G mass density for gravity
+    pub struct PoissonSolver<T> {
+        method: PoissonMethod,
+        /// Relative residual at which iterative methods stop
+        tolerance: SimFloat,
+        max_iterations: usize,
+        /// Source used when stepping as a field solver
+        source: Option<Field<T>>,
+        last_report: Option<PoissonReport>,
+    }
+
+    impl<T: FieldValue> PoissonSolver<T> {
+        pub fn new(method: PoissonMethod, tolerance: SimFloat, max_iterations: usize) -> Self {
+            Self {
+                method,
+                tolerance,
+                max_iterations,
+                source: None,
+                last_report: None,
+            }
+        }
+
+        /// Reads `method` (`jacobi`, `gauss_seidel`, `sor`, `multigrid` or `fft`),
+        /// `omega`, `tolerance` and `max_iterations` from simulation config and
+        /// source from `source` map or float
+        pub fn from_config(
+            field: &Field<T>,
+            maps: &HashMap<String, FieldMapConfig>,
+            simulation_properties: &HashMap<String, Property>,
+        ) -> IoResult<Self> {
+            let method = PoissonMethod::from_config(simulation_properties)?;
+
+            if method == PoissonMethod::Fft && !is_periodic(field) {
+                return Err(fft_boundary_error());
+            }
+
+            if T::COMPONENTS != 1 {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    "Poisson model reads its source from a scalar map and needs a scalar field",
+                ));
+            }
+
+            let map = scalar_map(field.grid, &field.boundaries, maps, simulation_properties, "source")?;
+            let mut source = field.filled_like(T::zero());
+            for (value, s) in source.data_mut().iter_mut().zip(map.data()) {
+                *value = T::from_components(|_| *s);
+            }
+
+            let mut solver = Self::new(
+                method,
+                simulation_properties.get_float_or("tolerance", 1e-8)?,
+                simulation_properties.get_count_or("max_iterations", 10000)?,
+            );
+            solver.source = Some(source);
+
+            Ok(solver)
+        }
+
+        pub fn last_report(&self) -> Option<PoissonReport> {
+            self.last_report
+        }
+
+        /// Solves for `potential` using its current value as initial guess
+        pub fn solve(&mut self, potential: &mut Field<T>, source: &Field<T>) -> IoResult<PoissonReport> {
+            let grid = potential.grid;
+            let mut source = source.clone();
+            if is_singular(potential) {
+                let mean = source.data().iter().fold(T::zero(), |a, v| a + *v) * (1.0 / grid.len() as SimFloat);
+                for value in source.data_mut() {
+                    *value = *value - mean;
+                }
+            }
+
+            let source_rms = rms(source.data());
+            let scale = if source_rms > 0.0 { source_rms } else { 1.0 };
+            let mut r = vec![T::zero(); grid.len()];
+
+            let report = if self.method == PoissonMethod::Fft {
+                if !is_periodic(potential) {
+                    return Err(fft_boundary_error());
+                }
+
+                solve_fft(potential, &source);
+                let relative = residual(potential, &source, &mut r) / scale;
+                PoissonReport { iterations: 1, residual: relative, converged: relative <= self.tolerance }
+            } else {
+                let mut relative = residual(potential, &source, &mut r) / scale;
+                let mut iterations = 0;
+                while relative > self.tolerance && iterations < self.max_iterations {
+                    match self.method {
+                        PoissonMethod::Jacobi => jacobi(potential, &source),
+                        PoissonMethod::GaussSeidel => relax(potential, &source, 1.0),
+                        PoissonMethod::Sor { omega } => relax(potential, &source, omega),
+                        PoissonMethod::Multigrid => v_cycle(potential, &source),
+                        PoissonMethod::Fft => unreachable!(),
+                    }
+
+                    relative = residual(potential, &source, &mut r) / scale;
+                    iterations += 1;
+                }
+
+                PoissonReport { iterations, residual: relative, converged: relative <= self.tolerance }
+            };
+
+            self.last_report = Some(report);
+            Ok(report)
+        }
+    }
+
+    impl<T: FieldValue> FieldSolver<T> for PoissonSolver<T> {
+        fn step(
+            &mut self,
+            field: &mut Field<T>,
+            _simulation_properties: &HashMap<String, Property>,
+            _delta: SimFloat,
+        ) {
+            if let Some(source) = self.source.take() {
+                // Boundaries were validated when solver was created
+                self.solve(field, &source).expect("Poisson solve failed");
+                self.source = Some(source);
+            }
+        }
+
+        fn record_stats(&self, _field: &Field<T>, stats: &mut HashMap<String, Property>) {
+            if let Some(report) = self.last_report {
+                stats.insert("solver_iterations".to_string(), Property::Float(report.iterations as SimFloat));
+                stats.insert("solver_residual".to_string(), Property::Float(report.residual));
+            }
+        }
+    }
+
+    /// Static scalar potential acting on particles with property `coupling`,
+    /// such as `charge`. Particles without the property don't feel the potential
+    pub struct ExternalPotential {
+        pub potential: Field<SimFloat>,
+        pub coupling: String,
+    }
+
+    impl ExternalPotential {
+        pub fn new(potential: Field<SimFloat>, coupling: &str) -> Self {
+            Self {
+                potential,
+                coupling: coupling.to_string(),
+            }
+        }
+
+        fn coupling<const N: usize>(&self, particle: &ParticleProto<N>) -> SimFloat {
+            particle.additional_properties.get(&self.coupling).and_then(|q| q.try_float()).unwrap_or(0.0)
+        }
+
+        fn position<const N: usize>(particle: &ParticleProto<N>) -> [SimFloat; 3] {
+            std::array::from_fn(|a| if a < N { particle.position[a] } else { 0.0 })
+        }
+
+        pub fn energy<const N: usize>(&self, particle: &ParticleProto<N>) -> SimFloat {
+            self.coupling(particle) * self.potential.sample(Self::position(particle))
+        }
+
+        /// Force -q grad(potential) at particle position
+        pub fn force<const N: usize>(&self, particle: &ParticleProto<N>) -> na::SVector<SimFloat, N> {
+            let q = self.coupling(particle);
+            if q == 0.0 { return na::SVector::zeros() }
+
+            let gradient = self.potential.sample_gradient(Self::position(particle));
+            na::SVector::from_fn(|a, _| -q * gradient[a])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::proto::*;
+    use crate::{
+        field::proto::{Field, FieldBoundary, Grid},
+        particle::proto::ParticleProto,
+        Property,
+    };
+
+    fn square(n: usize, boundary: FieldBoundary<f64>) -> Field<f64> {
+        Field::new(Grid::new(&[n, n], 0.5, &[]).unwrap(), [[boundary; 2]; 3], 0.0)
+    }
+
+    /// Point source and sink, so the source has zero mean
+    fn dipole(n: usize) -> Field<f64> {
+        let mut source = square(n, FieldBoundary::Periodic);
+        source.set([n / 4, n / 2, 0], 1.0);
+        source.set([3 * n / 4, n / 2, 0], -1.0);
+        source
+    }
+
+    fn max_residual(potential: &Field<f64>, source: &Field<f64>) -> f64 {
+        let mut r = vec![0.0; potential.grid.len()];
+        residual(potential, source, &mut r);
+        r.iter().fold(0.0, |m: f64, v| m.max(v.abs()))
+    }
+
+    #[test]
+    fn iterative_methods_reach_tolerance() {
+        let methods = [
+            PoissonMethod::Jacobi,
+            PoissonMethod::GaussSeidel,
+            PoissonMethod::Sor { omega: 1.7 },
+            PoissonMethod::Multigrid,
+        ];
+
+        for method in methods {
+            let mut potential = square(16, FieldBoundary::Dirichlet(0.0));
+            let source = dipole(16);
+            let report = PoissonSolver::new(method, 1e-6, 20000).solve(&mut potential, &source).unwrap();
+
+            assert!(report.converged, "{method:?}: {report:?}");
+            assert!(report.residual <= 1e-6);
+            assert!(max_residual(&potential, &source) < 1e-5, "{method:?}");
+        }
+    }
+
+    #[test]
+    fn multigrid_needs_fewer_iterations_than_gauss_seidel() {
+        let iterations = |method| {
+            let mut potential = square(32, FieldBoundary::Dirichlet(0.0));
+            PoissonSolver::new(method, 1e-6, 20000).solve(&mut potential, &dipole(32)).unwrap().iterations
+        };
+
+        assert!(iterations(PoissonMethod::Multigrid) * 10 < iterations(PoissonMethod::GaussSeidel));
+    }
+
+    #[test]
+    fn fft_solves_periodic_problem_directly() {
+        let mut potential = square(12, FieldBoundary::Periodic);
+        let source = dipole(12);
+        let report = PoissonSolver::new(PoissonMethod::Fft, 1e-10, 1).solve(&mut potential, &source).unwrap();
+
+        assert_eq!(report.iterations, 1);
+        assert!(report.converged);
+        assert!(max_residual(&potential, &source) < 1e-10);
+    }
+
+    #[test]
+    fn fft_rejects_non_periodic_boundaries() {
+        let mut potential = square(8, FieldBoundary::Neumann(0.0));
+        let result = PoissonSolver::new(PoissonMethod::Fft, 1e-8, 1).solve(&mut potential, &dipole(8));
+        assert!(result.is_err());
+
+        let config = HashMap::from([
+            ("method".to_string(), Property::String("fft".to_string())),
+            ("source".to_string(), Property::Float(0.0)),
+        ]);
+        assert!(PoissonSolver::from_config(&potential, &HashMap::new(), &config).is_err());
+    }
+
+    #[test]
+    fn sor_requires_omega_between_zero_and_two() {
+        let sor = |omega: f64| PoissonMethod::from_config(&HashMap::from([
+            ("method".to_string(), Property::String("sor".to_string())),
+            ("omega".to_string(), Property::Float(omega)),
+        ]));
+
+        assert_eq!(sor(1.8).unwrap(), PoissonMethod::Sor { omega: 1.8 });
+        for omega in [0.0, -0.5, 2.0, 2.5, f64::NAN] {
+            assert!(sor(omega).is_err(), "{omega}");
+        }
+    }
+
+    #[test]
+    fn singular_problem_solves_fluctuating_part_of_source() {
+        let mut potential = square(8, FieldBoundary::Neumann(0.0));
+        let mut source = dipole(8);
+        for value in source.data_mut() {
+            *value += 3.0;
+        }
+
+        let report = PoissonSolver::new(PoissonMethod::Multigrid, 1e-8, 1000).solve(&mut potential, &source).unwrap();
+
+        assert!(report.converged, "{report:?}");
+    }
+
+    #[test]
+    fn external_potential_pushes_coupled_particles_downhill() {
+        let mut field = Field::new(Grid::new(&[8, 8], 1.0, &[]).unwrap(), [[FieldBoundary::Neumann(0.0); 2]; 3], 0.0);
+        for cell in field.grid.cells().collect::<Vec<_>>() {
+            field.set(cell, 2.0 * cell[0] as f64);
+        }
+        let potential = ExternalPotential::new(field, "charge");
+
+        let mut particle = ParticleProto::<2>::new();
+        particle.position = [3.5, 4.0].into();
+        assert_eq!(potential.force(&particle), nalgebra::Vector2::zeros());
+
+        particle.additional_properties.insert("charge".to_string(), Property::Float(0.5));
+        assert!((potential.force(&particle) - nalgebra::Vector2::new(-1.0, 0.0)).norm() < 1e-12);
+        assert!((potential.energy(&particle) - 3.5).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,172 @@
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::SimFloat;
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// Small reproducible pseudo-random generator (xoshiro256**)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut sm = seed;
+        Self {
+            state: [
+                splitmix64(&mut sm),
+                splitmix64(&mut sm),
+                splitmix64(&mut sm),
+                splitmix64(&mut sm),
+            ],
+        }
+    }
+
+    /// Returns generator continuing current sequence and moves self to the next stream
+    pub fn split(&mut self) -> Self {
+        let child = self.clone();
+        self.jump();
+        child
+    }
+
+    /// Advances state by 2^128 values
+    pub fn jump(&mut self) {
+        const JUMP: [u64; 4] = [
+            0x180EC6D33CFD0ABA,
+            0xD5A61266F0C9392C,
+            0xA9582618E03FC9AA,
+            0x39ABDC4529B1661C,
+        ];
+
+        let mut state = [0u64; 4];
+        for word in JUMP {
+            for bit in 0..64 {
+                if word & (1 << bit) != 0 {
+                    for (s, x) in state.iter_mut().zip(self.state.iter()) {
+                        *s ^= x;
+                    }
+                }
+                self.next_u64();
+            }
+        }
+
+        self.state = state;
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+
+        result
+    }
+
+    /// Uniformly distributed value in [0, 1)
+    pub fn uniform(&mut self) -> SimFloat {
+        (self.next_u64() >> 11) as SimFloat / (1u64 << 53) as SimFloat
+    }
+
+    /// Normally distributed value with zero mean and unit variance
+    pub fn gaussian(&mut self) -> SimFloat {
+        // Box-Muller. 1 - uniform() avoids ln(0)
+        let u1 = 1.0 - self.uniform();
+        let u2 = self.uniform();
+        (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+    }
+
+    pub fn gaussian_vector<const N: usize>(&mut self) -> na::SVector<SimFloat, N> {
+        na::SVector::from_fn(|_, _| self.gaussian())
+    }
+}
+
+/// Independent generators derived from a single seed, one per subsystem
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RngStreams {
+    seed: u64,
+    streams: Vec<Rng>,
+}
+
+impl RngStreams {
+    pub fn new(seed: u64, count: usize) -> Self {
+        let mut base = Rng::new(seed);
+        Self {
+            seed,
+            streams: (0..count).map(|_| base.split()).collect(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, index: usize) -> &mut Rng {
+        &mut self.streams[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+        let mut c = Rng::new(43);
+
+        let first = (0..8).map(|_| a.next_u64()).collect::<Vec<_>>();
+        assert_eq!(first, (0..8).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(first, (0..8).map(|_| c.next_u64()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn split_continues_sequence_and_jumps_parent() {
+        let mut parent = Rng::new(1);
+        let mut reference = parent.clone();
+        let mut child = parent.split();
+
+        assert_eq!(child.next_u64(), reference.next_u64());
+        assert_ne!(parent.next_u64(), Rng::new(1).next_u64());
+    }
+
+    #[test]
+    fn uniform_and_gaussian_have_expected_moments() {
+        let mut rng = Rng::new(5);
+        let count = 100_000;
+
+        let uniform = (0..count).map(|_| rng.uniform()).collect::<Vec<_>>();
+        assert!(uniform.iter().all(|u| (0.0..1.0).contains(u)));
+        assert!((uniform.iter().sum::<SimFloat>() / count as SimFloat - 0.5).abs() < 0.01);
+
+        let gaussian = (0..count).map(|_| rng.gaussian()).collect::<Vec<_>>();
+        let mean = gaussian.iter().sum::<SimFloat>() / count as SimFloat;
+        let variance = gaussian.iter().map(|g| (g - mean) * (g - mean)).sum::<SimFloat>() / count as SimFloat;
+        assert!(mean.abs() < 0.02);
+        assert!((variance - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn streams_are_independent_and_survive_serialization() {
+        let mut streams = RngStreams::new(9, 2);
+        let first = streams.stream(0).next_u64();
+        assert_ne!(first, streams.stream(1).next_u64());
+
+        let mut restored: RngStreams = serde_json::from_str(&serde_json::to_string(&streams).unwrap()).unwrap();
+        assert_eq!(restored.seed(), 9);
+        assert_eq!(restored.stream(0).next_u64(), streams.stream(0).next_u64());
+    }
+}
@@ -0,0 +1,453 @@
+pub mod proto {
+    use std::{
+        collections::HashMap,
+        io::{Error as IoError, ErrorKind, Result as IoResult},
+    };
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        diffusion::proto::{DiffusionScheme, DiffusionSolver},
+        expression::{Expression, Symbol},
+        field::proto::{
+            boundaries_from_spec, map_boundaries, Field, FieldBoundarySpec, FieldRegion, FieldSolver, FieldSolverConfig,
+            Grid, GridConfig,
+        },
+        stats::Timeseries,
+        Error, Format, Property, PropertyMap, SimFloat,
+    };
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct SpeciesConfig {
+        pub name: String,
+        /// Required unless given by preset
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub diffusivity: Option<SimFloat>,
+        /// Concentration before regions are applied. Defaults to zero
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub initial_value: Option<SimFloat>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub initial_regions: Vec<FieldRegion>,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct ReactionDiffusionConfiguration {
+        /// Holds `reactions`, a map from species name to its reaction term, and
+        /// the parameters these terms refer to
+        simulation_config: HashMap<String, Property>,
+        solver_config: FieldSolverConfig,
+        grid: GridConfig,
+        boundary: FieldBoundarySpec,
+        /// Entries with a preset species name override that species
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        species: Vec<SpeciesConfig>,
+    }
+
+    impl ReactionDiffusionConfiguration {
+        pub fn save(&self, filename: &str) -> IoResult<()> {
+            let stringified = serde_json::to_string_pretty(self)?;
+
+            std::fs::write(filename, stringified)
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Preset {
+        /// u + 2v -> 3v with feed of u and removal of v
+        GrayScott,
+        /// Excitable medium with fast activator u and slow inhibitor v
+        FitzHughNagumo,
+    }
+
+    impl Preset {
+        pub fn from_name(name: &str) -> Option<Self> {
+            match name {
+                "gray_scott" => Some(Preset::GrayScott),
+                "fitzhugh_nagumo" => Some(Preset::FitzHughNagumo),
+                _ => None,
+            }
+        }
+
+        pub fn parameters(&self) -> &'static [(&'static str, SimFloat)] {
+            match self {
+                Preset::GrayScott => &[("feed", 0.055), ("kill", 0.062)],
+                Preset::FitzHughNagumo => &[("a", 0.7), ("b", 0.8), ("epsilon", 0.08), ("current", 0.0)],
+            }
+        }
+
+        pub fn reactions(&self) -> &'static [(&'static str, &'static str)] {
+            match self {
+                Preset::GrayScott => &[
+                    ("u", "-u*v^2 + feed*(1 - u)"),
+                    ("v", "u*v^2 - (feed + kill)*v"),
+                ],
+                Preset::FitzHughNagumo => &[
+                    ("u", "u - u^3/3 - v + current"),
+                    ("v", "epsilon*(u + a - b*v)"),
+                ],
+            }
+        }
+
+        /// Species at rest, with a disturbance in the middle of the grid to start patterns
+        pub fn species(&self, grid: &Grid) -> Vec<SpeciesConfig> {
+            let (min, max): (Vec<_>, Vec<_>) = (0..grid.dimension)
+                .map(|a| (grid.shape[a] * 9 / 20, (grid.shape[a] * 11 / 20).max(grid.shape[a] * 9 / 20 + 1)))
+                .unzip();
+            let seed = |value| vec![FieldRegion { min: min.clone(), max: max.clone(), value: Property::Float(value) }];
+
+            let species = |name: &str, diffusivity, initial_value, initial_regions| SpeciesConfig {
+                name: name.to_string(),
+                diffusivity: Some(diffusivity),
+                initial_value: Some(initial_value),
+                initial_regions,
+            };
+
+            match self {
+                Preset::GrayScott => vec![
+                    species("u", 0.16, 1.0, seed(0.5)),
+                    species("v", 0.08, 0.0, seed(0.25)),
+                ],
+                Preset::FitzHughNagumo => vec![
+                    species("u", 1.0, -1.2, seed(2.0)),
+                    species("v", 0.0, -0.62, vec![]),
+                ],
+            }
+        }
+    }
+
+    pub struct Species {
+        pub name: String,
+        pub field: Field<SimFloat>,
+        reaction: Option<Expression>,
+        diffusion: DiffusionSolver,
+    }
+
+    /// Species concentrations coupled by reaction terms, each diffusing on its own
+    pub struct ReactionDiffusionSimulator {
+        solver_config: FieldSolverConfig,
+        sim_config: HashMap<String, Property>,
+        species: Vec<Species>,
+        simulation_time: SimFloat,
+        stats: Option<Timeseries<HashMap<String, Property>>>,
+    }
+
+    impl ReactionDiffusionSimulator {
+        /// Reaction terms may use species names, float parameters of simulation config,
+        /// time `t` and cell position `x`, `y`, `z`. `preset` and `scheme` are read too
+        pub fn load(filename: &str) -> Result<Self, Error> {
+            let source = std::fs::read_to_string(filename)?;
+            Self::from_source(&source, Format::from_path(filename))
+        }
+
+        /// Loads configuration from text. Errors carry snippets of `source`
+        pub fn from_source(source: &str, format: Format) -> Result<Self, Error> {
+            format.deserialize::<ReactionDiffusionConfiguration>(source)
+                .and_then(|config| Self::from_configuration(config).map_err(Error::from_setup))
+                .map_err(|e| e.located(source, format))
+        }
+
+        fn from_configuration(config: ReactionDiffusionConfiguration) -> IoResult<Self> {
+            config.simulation_config.get_str("name").map_err(|e| e.within("simulation_config"))?;
+
+            let grid = Grid::new(&config.grid.shape, config.grid.spacing, &config.grid.origin)?;
+            let boundaries = boundaries_from_spec::<SimFloat>(&config.boundary, grid.dimension)?;
+            let mut sim_config = config.simulation_config;
+
+            let preset = match sim_config.get("preset").and_then(|p| p.try_str()) {
+                Some(name) => Some(Preset::from_name(name).ok_or_else(|| IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown reaction-diffusion preset `{name}`"),
+                ))?),
+                None => None,
+            };
+
+            let mut species_configs = preset.map(|p| p.species(&grid)).unwrap_or_default();
+            for species in config.species {
+                match species_configs.iter_mut().find(|s| s.name == species.name) {
+                    Some(existing) => {
+                        existing.diffusivity = species.diffusivity.or(existing.diffusivity);
+                        existing.initial_value = species.initial_value.or(existing.initial_value);
+                        if !species.initial_regions.is_empty() {
+                            existing.initial_regions = species.initial_regions;
+                        }
+                    }
+                    None => species_configs.push(species),
+                }
+            }
+
+            let mut reactions = sim_config.get("reactions")
+                .map(|r| r.try_nested().cloned().ok_or_else(|| IoError::new(
+                    ErrorKind::InvalidData,
+                    "`reactions` should map species names to expressions",
+                )))
+                .transpose()?
+                .unwrap_or_default();
+
+            if let Some(preset) = preset {
+                for (name, value) in preset.parameters() {
+                    sim_config.entry(name.to_string()).or_insert(Property::Float(*value));
+                }
+                for (name, reaction) in preset.reactions() {
+                    reactions.entry(name.to_string()).or_insert(Property::String(reaction.to_string()));
+                }
+            }
+
+            let names = species_configs.iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+            let species_count = names.len();
+            let resolve = |name: &str| -> Option<Symbol> {
+                if let Some(i) = names.iter().position(|n| n == name) {
+                    return Some(Symbol::Variable(i));
+                }
+                match name {
+                    "t" => Some(Symbol::Variable(species_count)),
+                    "x" => Some(Symbol::Variable(species_count + 1)),
+                    "y" => Some(Symbol::Variable(species_count + 2)),
+                    "z" => Some(Symbol::Variable(species_count + 3)),
+                    _ => sim_config.get(name).and_then(|v| v.try_float()).map(Symbol::Constant),
+                }
+            };
+
+            if let Some(unknown) = reactions.keys().find(|k| !names.contains(k)) {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Reaction for unknown species `{unknown}`"),
+                ));
+            }
+
+            let scheme = match sim_config.get("scheme").and_then(|s| s.try_str()) {
+                None | Some("explicit") => DiffusionScheme::Explicit,
+                Some("crank_nicolson") => DiffusionScheme::CrankNicolson,
+                Some(scheme) => return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown diffusion scheme `{scheme}`"),
+                )),
+            };
+
+            let mut species = Vec::with_capacity(species_count);
+            for species_config in species_configs.iter() {
+                let name = &species_config.name;
+                let diffusivity = species_config.diffusivity.ok_or_else(|| IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Species `{name}` requires `diffusivity`"),
+                ))?;
+
+                let mut field = Field::new(grid, boundaries, species_config.initial_value.unwrap_or(0.0));
+                for region in species_config.initial_regions.iter() {
+                    field.fill_region(region)?;
+                }
+
+                let reaction = match reactions.get(name) {
+                    Some(text) => {
+                        let text = text.try_str().ok_or_else(|| IoError::new(
+                            ErrorKind::InvalidData,
+                            format!("Reaction of `{name}` should be a string"),
+                        ))?;
+                        Some(Expression::parse(text, &resolve)?)
+                    }
+                    None => None,
+                };
+
+                species.push(Species {
+                    name: name.clone(),
+                    diffusion: DiffusionSolver::new(
+                        scheme,
+                        // Diffusivity has no value of its own beyond the edges
+                        Field::new(grid, map_boundaries(&field.boundaries), diffusivity),
+                        config.solver_config.timestep,
+                    )?,
+                    field,
+                    reaction,
+                });
+            }
+
+            Ok(Self {
+                solver_config: config.solver_config,
+                sim_config,
+                species,
+                simulation_time: 0.0,
+                stats: None,
+            })
+        }
+
+        pub fn start_recording_statistics(&mut self) {
+            self.stats = Some(Timeseries::new());
+        }
+
+        pub fn save_statistics(&self, filename: &str) -> IoResult<()> {
+            if let Some(stats) = self.stats.as_ref() {
+                stats.save(filename)?
+            }
+
+            Ok(())
+        }
+
+        pub fn sim_name(&self) -> &str {
+            self.sim_config.get("name").and_then(|n| n.try_str()).unwrap_or_default()
+        }
+
+        pub fn species(&self) -> &[Species] {
+            &self.species
+        }
+
+        pub fn field(&self, name: &str) -> Option<&Field<SimFloat>> {
+            self.species.iter().find(|s| s.name == name).map(|s| &s.field)
+        }
+
+        fn record_stats(&mut self) {
+            if self.stats.is_none() { return; }
+
+            let mut hashmap = HashMap::new();
+            for species in self.species.iter() {
+                let data = species.field.data();
+                let (min, max) = data.iter().fold(
+                    (SimFloat::INFINITY, SimFloat::NEG_INFINITY),
+                    |(min, max), v| (min.min(*v), max.max(*v)),
+                );
+
+                let mut species_props = HashMap::new();
+                species_props.insert("min".to_string(), Property::Float(min));
+                species_props.insert("max".to_string(), Property::Float(max));
+                species_props.insert(
+                    "mean".to_string(),
+                    Property::Float(data.iter().sum::<SimFloat>() / data.len() as SimFloat),
+                );
+
+                hashmap.insert(species.name.clone(), Property::Nested(species_props));
+            }
+
+            self.stats.as_mut().unwrap().record(hashmap, Some(self.simulation_time));
+        }
+
+        pub fn step(&mut self) {
+            self.record_stats();
+            let delta = self.solver_config.timestep;
+
+            // Reaction rates from state before diffusion, so both parts see the same values
+            let count = self.species.len();
+            let Some(grid) = self.species.first().map(|s| s.field.grid) else { return };
+            let mut variables = vec![0.0; count + 4];
+            variables[count] = self.simulation_time;
+
+            let mut rates = vec![vec![0.0; grid.len()]; count];
+            for (index, cell) in grid.cells().enumerate() {
+                for (v, species) in variables.iter_mut().zip(self.species.iter()) {
+                    *v = species.field.data()[index];
+                }
+                variables[count + 1..].copy_from_slice(&grid.position(cell));
+
+                for (rate, species) in rates.iter_mut().zip(self.species.iter()) {
+                    if let Some(reaction) = species.reaction.as_ref() {
+                        rate[index] = reaction.evaluate(&variables);
+                    }
+                }
+            }
+
+            for (species, rate) in self.species.iter_mut().zip(rates) {
+                species.diffusion.step(&mut species.field, &self.sim_config, delta);
+                for (value, rate) in species.field.data_mut().iter_mut().zip(rate) {
+                    *value += rate * delta;
+                }
+            }
+
+            self.simulation_time += delta;
+        }
+
+        pub fn time(&self) -> SimFloat {
+            self.simulation_time
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proto::*;
+    use crate::{Error, Format};
+
+    fn load(simulation: &str, species: &str) -> Result<ReactionDiffusionSimulator, Error> {
+        load_bounded(r#"{"type": "periodic"}"#, simulation, species)
+    }
+
+    /// Like [`load`] with `boundary` for every species
+    fn load_bounded(boundary: &str, simulation: &str, species: &str) -> Result<ReactionDiffusionSimulator, Error> {
+        let config = format!(r#"{{
+            "simulation_config": {{"name": "reaction"{simulation}}},
+            "solver_config": {{"timestep": 0.1}},
+            "grid": {{"shape": [4, 4], "spacing": 1.0}},
+            "boundary": {boundary},
+            "species": [{species}]
+        }}"#);
+        ReactionDiffusionSimulator::from_source(&config, Format::Json)
+    }
+
+    #[test]
+    fn reaction_terms_use_parameters_and_positions() {
+        let mut simulator = load(
+            r#", "rate": 0.5, "reactions": {"u": "-rate*u", "p": "x + 10*y"}"#,
+            r#"{"name": "u", "diffusivity": 0.0, "initial_value": 2.0}, {"name": "p", "diffusivity": 0.0}"#,
+        ).unwrap();
+
+        for _ in 0..10 {
+            simulator.step();
+        }
+
+        let expected = 2.0 * 0.95f64.powi(10);
+        assert!(simulator.field("u").unwrap().data().iter().all(|u| (u - expected).abs() < 1e-12));
+        assert!((simulator.field("p").unwrap().get([3, 2, 0]) - 23.0).abs() < 1e-12);
+        assert!((simulator.time() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn diffusion_spreads_each_species_without_reactions() {
+        let region = r#"[{"min": [0, 0], "max": [1, 1], "value": 16.0}]"#;
+        let mut simulator = load(
+            "",
+            &format!(r#"{{"name": "u", "diffusivity": 1.0, "initial_regions": {region}}}"#),
+        ).unwrap();
+
+        for _ in 0..50 {
+            simulator.step();
+        }
+
+        let u = simulator.field("u").unwrap().data();
+        assert!((u.iter().sum::<f64>() - 16.0).abs() < 1e-9);
+        assert!(u.iter().all(|v| (v - 1.0).abs() < 0.1));
+    }
+
+    #[test]
+    fn species_diffuse_out_through_absorbing_edges() {
+        let species = r#"{"name": "u", "diffusivity": 1.0, "initial_value": 1.0}"#;
+        let mut absorbing = load_bounded(r#"{"type": "dirichlet", "value": 0.0}"#, "", species).unwrap();
+        let mut insulated = load_bounded(r#"{"type": "neumann"}"#, "", species).unwrap();
+
+        for _ in 0..20 {
+            absorbing.step();
+            insulated.step();
+        }
+
+        let total = |s: &ReactionDiffusionSimulator| s.field("u").unwrap().data().iter().sum::<f64>();
+        assert!(total(&absorbing) < 8.0, "{}", total(&absorbing));
+        assert!((total(&insulated) - 16.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn preset_provides_species_and_can_be_overridden() {
+        let simulator = load(r#", "preset": "gray_scott", "feed": 0.04"#, r#"{"name": "v", "diffusivity": 0.05}"#).unwrap();
+
+        let names = simulator.species().iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["u", "v"]);
+        // Middle of the grid is seeded to start patterns
+        assert_eq!(simulator.field("u").unwrap().get([0, 0, 0]), 1.0);
+        assert_eq!(simulator.field("u").unwrap().get([1, 1, 0]), 0.5);
+        assert!(load(r#", "preset": "brusselator""#, "").is_err());
+    }
+
+    #[test]
+    fn invalid_reactions_are_rejected() {
+        let u = r#"{"name": "u", "diffusivity": 0.1}"#;
+        assert!(load(r#", "reactions": {"w": "1"}"#, u).is_err());
+        assert!(load(r#", "reactions": {"u": "rate*u"}"#, u).is_err());
+        assert!(load(r#", "reactions": {"u": 1.0}"#, u).is_err());
+        assert!(load("", r#"{"name": "u"}"#).is_err());
+        assert!(load(r#", "scheme": "implicit""#, u).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+pub mod proto {
+    use nalgebra as na;
+
+    use crate::{
+        particle::proto::ParticleProto,
+        proto::{BrownianObject, EulerMethodObject},
+        SimFloat,
+    };
+
+    /// Translational and rotational state of a rigid body in 2D.
+    /// Translation is handled by the inner particle
+    pub struct RigidBody2 {
+        pub particle: ParticleProto<2>,
+        /// Rotation angle in radians, counter-clockwise
+        pub orientation: SimFloat,
+        pub angular_velocity: SimFloat,
+        pub inertia: SimFloat,
+        pub torque: SimFloat,
+    }
+
+    /// Translational and rotational state of a rigid body in 3D.
+    /// Translation is handled by the inner particle
+    pub struct RigidBody3 {
+        pub particle: ParticleProto<3>,
+        /// Rotation from body frame to world frame
+        pub orientation: na::UnitQuaternion<SimFloat>,
+        /// Angular velocity in body frame
+        pub angular_velocity: na::Vector3<SimFloat>,
+        /// Principal moments of inertia in body frame
+        pub inertia: na::Vector3<SimFloat>,
+        /// Accumulated torque in world frame
+        pub torque: na::Vector3<SimFloat>,
+    }
+
+    pub trait RotationalObject {
+        /// Integrates rotation over `delta` and clears accumulated torque
+        fn step_rotation(&mut self, delta: SimFloat);
+    }
+
+    impl RigidBody2 {
+        pub fn new(particle: ParticleProto<2>, inertia: SimFloat) -> Self {
+            Self {
+                particle,
+                orientation: 0.0,
+                angular_velocity: 0.0,
+                inertia,
+                torque: 0.0,
+            }
+        }
+
+        pub fn apply_torque(&mut self, torque: SimFloat) {
+            self.torque += torque;
+        }
+
+        /// Accumulates torque of `force` applied at world point `point`.
+        /// Linear part of the force is not applied.
+        pub fn apply_force_at(&mut self, force: na::Vector2<SimFloat>, point: na::Point2<SimFloat>) {
+            let r = point - self.particle.position;
+            self.torque += r.perp(&force);
+        }
+
+        /// Unit vector pointing along body's local x axis
+        pub fn heading(&self) -> na::Vector2<SimFloat> {
+            na::Vector2::new(self.orientation.cos(), self.orientation.sin())
+        }
+
+        pub fn rotational_energy(&self) -> SimFloat {
+            0.5 * self.inertia * self.angular_velocity * self.angular_velocity
+        }
+    }
+
+    impl RigidBody3 {
+        pub fn new(particle: ParticleProto<3>, inertia: na::Vector3<SimFloat>) -> Self {
+            Self {
+                particle,
+                orientation: na::UnitQuaternion::identity(),
+                angular_velocity: na::Vector3::zeros(),
+                inertia,
+                torque: na::Vector3::zeros(),
+            }
+        }
+
+        pub fn apply_torque(&mut self, torque: na::Vector3<SimFloat>) {
+            self.torque += torque;
+        }
+
+        /// Accumulates torque of `force` applied at world point `point`.
+        /// Linear part of the force is not applied.
+        pub fn apply_force_at(&mut self, force: na::Vector3<SimFloat>, point: na::Point3<SimFloat>) {
+            let r = point - self.particle.position;
+            self.torque += r.cross(&force);
+        }
+
+        /// Angular velocity in world frame
+        pub fn world_angular_velocity(&self) -> na::Vector3<SimFloat> {
+            self.orientation * self.angular_velocity
+        }
+
+        pub fn rotational_energy(&self) -> SimFloat {
+            0.5 * self.angular_velocity.dot(&self.inertia.component_mul(&self.angular_velocity))
+        }
+    }
+
+    impl EulerMethodObject<2> for RigidBody2 {
+        fn step(&mut self, force: na::Vector2<SimFloat>, delta: SimFloat) {
+            self.particle.step(force, delta);
+        }
+    }
+
+    impl EulerMethodObject<3> for RigidBody3 {
+        fn step(&mut self, force: na::Vector3<SimFloat>, delta: SimFloat) {
+            self.particle.step(force, delta);
+        }
+    }
+
+    impl BrownianObject<2> for RigidBody2 {
+        fn step_overdamped(
+            &mut self,
+            force: na::Vector2<SimFloat>,
+            noise: na::Vector2<SimFloat>,
+            kt: SimFloat,
+            delta: SimFloat,
+        ) {
+            self.particle.step_overdamped(force, noise, kt, delta);
+        }
+    }
+
+    impl BrownianObject<3> for RigidBody3 {
+        fn step_overdamped(
+            &mut self,
+            force: na::Vector3<SimFloat>,
+            noise: na::Vector3<SimFloat>,
+            kt: SimFloat,
+            delta: SimFloat,
+        ) {
+            self.particle.step_overdamped(force, noise, kt, delta);
+        }
+    }
+
+    impl RotationalObject for RigidBody2 {
+        fn step_rotation(&mut self, delta: SimFloat) {
+            self.angular_velocity += self.torque / self.inertia * delta;
+            self.orientation = (self.orientation + self.angular_velocity * delta)
+                .rem_euclid(std::f64::consts::TAU);
+            self.torque = 0.0;
+        }
+    }
+
+    impl RotationalObject for RigidBody3 {
+        fn step_rotation(&mut self, delta: SimFloat) {
+            // Euler's equations in body frame: I dw/dt = t - w x Iw
+            let torque = self.orientation.inverse() * self.torque;
+            let momentum = self.inertia.component_mul(&self.angular_velocity);
+            let acceleration = (torque - self.angular_velocity.cross(&momentum))
+                .component_div(&self.inertia);
+            self.angular_velocity += acceleration * delta;
+
+            // Exponential map keeps orientation a unit quaternion
+            self.orientation *= na::UnitQuaternion::from_scaled_axis(self.angular_velocity * delta);
+            self.torque = na::Vector3::zeros();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, TAU};
+
+    use nalgebra as na;
+
+    use super::proto::*;
+    use crate::particle::proto::ParticleProto;
+
+    #[test]
+    fn torque_spins_body_and_is_cleared() {
+        let mut body = RigidBody2::new(ParticleProto::new(), 2.0);
+        body.apply_torque(4.0);
+
+        body.step_rotation(0.5);
+
+        assert_eq!(body.angular_velocity, 1.0);
+        assert_eq!(body.orientation, 0.5);
+        assert_eq!(body.torque, 0.0);
+        assert_eq!(body.rotational_energy(), 1.0);
+    }
+
+    #[test]
+    fn orientation_wraps_into_full_turn() {
+        let mut body = RigidBody2::new(ParticleProto::new(), 1.0);
+        body.angular_velocity = -FRAC_PI_2;
+
+        body.step_rotation(1.0);
+
+        assert!((body.orientation - 3.0 * FRAC_PI_2).abs() < 1e-12);
+        assert!(body.orientation < TAU);
+        assert!((body.heading() - na::Vector2::new(0.0, -1.0)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn force_at_point_gives_cross_product_torque() {
+        let mut body = RigidBody2::new(ParticleProto::new(), 1.0);
+        body.particle.position = na::Point2::new(1.0, 1.0);
+
+        body.apply_force_at(na::Vector2::new(0.0, 3.0), na::Point2::new(3.0, 1.0));
+
+        assert_eq!(body.torque, 6.0);
+        assert_eq!(body.particle.velocity, na::Vector2::zeros());
+    }
+
+    #[test]
+    fn free_rotation_keeps_energy_and_unit_orientation() {
+        let mut body = RigidBody3::new(ParticleProto::new(), na::Vector3::new(1.0, 2.0, 3.0));
+        body.angular_velocity = na::Vector3::new(0.1, 1.0, 0.1);
+        let energy = body.rotational_energy();
+
+        for _ in 0..1000 {
+            body.step_rotation(1e-4);
+        }
+
+        assert!((body.rotational_energy() - energy).abs() / energy < 1e-3);
+        assert!((body.orientation.norm() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn world_torque_is_applied_in_body_frame() {
+        let mut body = RigidBody3::new(ParticleProto::new(), na::Vector3::new(1.0, 1.0, 1.0));
+        // Body x axis points along world y
+        body.orientation = na::UnitQuaternion::from_axis_angle(&na::Vector3::z_axis(), FRAC_PI_2);
+        body.apply_torque(na::Vector3::new(0.0, 1.0, 0.0));
+
+        body.step_rotation(1.0);
+
+        assert!((body.angular_velocity - na::Vector3::new(1.0, 0.0, 0.0)).norm() < 1e-12);
+        assert!((body.world_angular_velocity() - na::Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-12);
+        assert_eq!(body.torque, na::Vector3::zeros());
+    }
+}
@@ -0,0 +1,366 @@
+use std::{collections::HashMap, fmt, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    units::{Dimension, Unit, UnitError},
+    Property, PropertyError, SimFloat,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PropertyType {
+    Bool,
+    Int,
+    /// Accepts Int as well
+    Float,
+    Vector2,
+    Vector3,
+    Vector4,
+    Matrix,
+    Array,
+    String,
+    Nested,
+    Any,
+}
+
+impl PropertyType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PropertyType::Bool => "Bool",
+            PropertyType::Int => "Int",
+            PropertyType::Float => "Float",
+            PropertyType::Vector2 => "Vector2",
+            PropertyType::Vector3 => "Vector3",
+            PropertyType::Vector4 => "Vector4",
+            PropertyType::Matrix => "Matrix",
+            PropertyType::Array => "Array",
+            PropertyType::String => "String",
+            PropertyType::Nested => "Nested",
+            PropertyType::Any => "any value",
+        }
+    }
+
+    pub fn matches(&self, property: &Property) -> bool {
+        match (self, property) {
+            (PropertyType::Any, _) => true,
+            (PropertyType::Float, Property::Int(_)) => true,
+            (t, p) => t.name() == p.type_name(),
+        }
+    }
+}
+
+/// Declaration of a single property
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PropertySpec {
+    #[serde(rename = "type")]
+    pub kind: PropertyType,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    /// Inserted when an optional property is missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Property>,
+    /// Bounds of every number in the property, inclusive unless `exclusive_min`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<SimFloat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<SimFloat>,
+    /// Excludes `min` itself, e.g. for masses
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exclusive_min: bool,
+    /// Unit of the value, e.g. `m/s`. Quantities given with units must have
+    /// the same dimension and are converted to internal units on load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+}
+
+impl PropertySpec {
+    pub fn required(kind: PropertyType) -> Self {
+        Self { kind, required: true, default: None, min: None, max: None, exclusive_min: false, unit: None }
+    }
+
+    pub fn optional(kind: PropertyType) -> Self {
+        Self { required: false, ..Self::required(kind) }
+    }
+
+    pub fn with_default(mut self, default: Property) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub fn with_range(mut self, min: Option<SimFloat>, max: Option<SimFloat>) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// Requires values strictly above zero
+    pub fn positive(mut self) -> Self {
+        self.min = Some(0.0);
+        self.exclusive_min = true;
+        self
+    }
+
+    pub fn with_unit(mut self, unit: &str) -> Self {
+        self.unit = Some(unit.to_string());
+        self
+    }
+
+    /// Every violation of `property` found under `path`
+    fn check(&self, path: &str, property: &Property, violations: &mut Vec<PropertyError>) {
+        if !self.kind.matches(property) {
+            violations.push(PropertyError::WrongType {
+                path: path.to_string(),
+                expected: self.kind.name(),
+                found: property.type_name(),
+            });
+            return;
+        }
+
+        if self.min.is_none() && self.max.is_none() { return }
+
+        let mut numbers = vec![];
+        collect_numbers(property, &mut numbers);
+        if let Some(value) = numbers.into_iter().find(|v| {
+            let below = |min| if self.exclusive_min { *v <= min } else { *v < min };
+            self.min.is_some_and(below) || self.max.is_some_and(|max| *v > max)
+        }) {
+            violations.push(PropertyError::OutOfRange {
+                path: path.to_string(),
+                value,
+                min: self.min,
+                max: self.max,
+                exclusive_min: self.exclusive_min,
+            });
+        }
+    }
+}
+
+fn collect_numbers(property: &Property, numbers: &mut Vec<SimFloat>) {
+    match property {
+        Property::Matrix(m) => numbers.extend_from_slice(m.data()),
+        Property::Array(a) => a.iter().for_each(|p| collect_numbers(p, numbers)),
+        p => numbers.extend(p.try_components().unwrap_or_default()),
+    }
+}
+
+/// Properties expected in a property map. Properties not declared are allowed
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Schema {
+    properties: HashMap<String, PropertySpec>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Properties the particle simulator itself reads
+    pub fn particle() -> Self {
+        Schema::new()
+            .with("position", PropertySpec::required(PropertyType::Vector2).with_unit("m"))
+            .with("velocity", PropertySpec::required(PropertyType::Vector2).with_unit("m/s"))
+            .with("name", PropertySpec::optional(PropertyType::String))
+            .with("mass", PropertySpec::optional(PropertyType::Float)
+                .with_default(Property::Float(1.0))
+                .positive()
+                .with_unit("kg"))
+            .with("radius", PropertySpec::optional(PropertyType::Float)
+                .with_default(Property::Float(1.0))
+                .with_range(Some(0.0), None)
+                .with_unit("m"))
+            .with("mobility", PropertySpec::optional(PropertyType::Float)
+                .with_default(Property::Float(1.0))
+                .positive())
+    }
+
+    /// Particle properties plus rotational state
+    pub fn rigid_body() -> Self {
+        Self::particle()
+            .with("orientation", PropertySpec::optional(PropertyType::Float)
+                .with_default(Property::Float(0.0))
+                .with_unit("rad"))
+            .with("angular_velocity", PropertySpec::optional(PropertyType::Float)
+                .with_default(Property::Float(0.0))
+                .with_unit("rad/s"))
+            .with("inertia", PropertySpec::optional(PropertyType::Float).positive().with_unit("kg m^2"))
+    }
+
+    /// Simulation config of the particle simulator
+    pub fn particle_simulation() -> Self {
+        Schema::new()
+            .with("name", PropertySpec::required(PropertyType::String))
+            .with("g_const", PropertySpec::required(PropertyType::Float).with_unit("m^3 kg^-1 s^-2"))
+            .with("k_boltzmann", PropertySpec::optional(PropertyType::Float).positive())
+    }
+
+    pub fn with(mut self, name: &str, spec: PropertySpec) -> Self {
+        self.properties.insert(name.to_string(), spec);
+        self
+    }
+
+    /// Declarations of `other` replace ones with the same name
+    pub fn merged(mut self, other: &Schema) -> Self {
+        self.properties.extend(other.properties.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.properties.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PropertySpec> {
+        self.properties.get(name)
+    }
+
+    /// Default value of property `name`, if declared
+    pub fn default_value(&self, name: &str) -> Option<&Property> {
+        self.properties.get(name).and_then(|s| s.default.as_ref())
+    }
+
+    /// Dimensions of properties declaring a unit
+    pub fn dimensions(&self) -> Result<HashMap<String, Dimension>, UnitError> {
+        self.properties.iter()
+            .filter_map(|(name, spec)| spec.unit.as_ref().map(|unit| (name, unit)))
+            .map(|(name, unit)| match Unit::parse(unit) {
+                Ok(unit) => Ok((name.clone(), unit.dimension)),
+                Err(message) => Err(UnitError { path: format!("schema.{name}"), message }),
+            })
+            .collect()
+    }
+
+    /// All violations in `map`, sorted by property name
+    pub fn validate(&self, map: &HashMap<String, Property>) -> Vec<PropertyError> {
+        let mut names = self.properties.keys().collect::<Vec<_>>();
+        names.sort();
+
+        let mut violations = vec![];
+        for name in names {
+            let spec = &self.properties[name];
+            match map.get(name) {
+                Some(property) => spec.check(name, property, &mut violations),
+                None if spec.required => violations.push(PropertyError::Missing { path: name.clone() }),
+                None => {}
+            }
+        }
+
+        violations
+    }
+
+    /// Inserts defaults of missing properties
+    pub fn apply_defaults(&self, map: &mut HashMap<String, Property>) {
+        for (name, spec) in self.properties.iter() {
+            if let Some(default) = spec.default.as_ref() {
+                map.entry(name.clone()).or_insert_with(|| default.clone());
+            }
+        }
+    }
+
+    /// Validates every map, applying defaults. Violations are prefixed with
+    /// `owner[index]`, e.g. `initial_objects[3].mass`
+    pub fn validate_all<'a>(
+        &self,
+        owner: &str,
+        maps: impl Iterator<Item = &'a mut HashMap<String, Property>>,
+        violations: &mut Vec<PropertyError>,
+    ) {
+        for (i, map) in maps.enumerate() {
+            let parent = format!("{owner}[{i}]");
+            violations.extend(self.validate(map).into_iter().map(|e| e.within(&parent)));
+            self.apply_defaults(map);
+        }
+    }
+}
+
+/// Built-in particle schema, source of defaults for particles read outside of loading
+pub fn particle_schema() -> &'static Schema {
+    static SCHEMA: OnceLock<Schema> = OnceLock::new();
+    SCHEMA.get_or_init(Schema::particle)
+}
+
+/// Every violation found while validating a configuration
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaViolations(pub Vec<PropertyError>);
+
+impl fmt::Display for SchemaViolations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} schema violation(s):", self.0.len())?;
+        for violation in self.0.iter() {
+            write!(f, "\n  {violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SchemaViolations {}
+
+impl From<SchemaViolations> for std::io::Error {
+    fn from(violations: SchemaViolations) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, violations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn map(entries: &[(&str, Property)]) -> HashMap<String, Property> {
+        entries.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+    }
+
+    #[test]
+    fn every_violation_is_reported_in_name_order() {
+        let particle = map(&[
+            ("position", Property::Float(0.0)),
+            ("mass", Property::Float(0.0)),
+            ("radius", Property::Float(-1.0)),
+        ]);
+
+        let violations = Schema::particle().validate(&particle);
+        let paths = violations.iter().map(|v| v.path()).collect::<Vec<_>>();
+        assert_eq!(paths, ["mass", "position", "radius", "velocity"]);
+        assert!(matches!(violations[0], PropertyError::OutOfRange { exclusive_min: true, .. }));
+        assert!(matches!(violations[1], PropertyError::WrongType { expected: "Vector2", found: "Float", .. }));
+        assert!(matches!(violations[3], PropertyError::Missing { .. }));
+    }
+
+    #[test]
+    fn ranges_apply_to_every_component() {
+        let schema = Schema::new().with(
+            "color",
+            PropertySpec::required(PropertyType::Vector3).with_range(Some(0.0), Some(1.0)),
+        );
+
+        assert!(schema.validate(&map(&[("color", Property::Vector3([0.0, 0.5, 1.0]))])).is_empty());
+        assert!(matches!(
+            schema.validate(&map(&[("color", Property::Vector3([0.0, 1.5, 1.0]))]))[..],
+            [PropertyError::OutOfRange { value: 1.5, .. }],
+        ));
+    }
+
+    #[test]
+    fn defaults_fill_missing_properties_only() {
+        let mut particle = map(&[("mass", Property::Int(3))]);
+        Schema::particle().apply_defaults(&mut particle);
+
+        assert_eq!(particle["mass"], Property::Int(3));
+        assert_eq!(particle["radius"], Property::Float(1.0));
+        assert!(!particle.contains_key("position"));
+    }
+
+    #[test]
+    fn schema_is_read_from_configuration() {
+        let schema: Schema = serde_json::from_str(r#"{
+            "charge": {"type": "float", "required": true, "unit": "C"},
+            "mass": {"type": "float", "default": 2.0}
+        }"#).unwrap();
+        let merged = Schema::particle().merged(&schema);
+
+        assert_eq!(merged.default_value("mass"), Some(&Property::Float(2.0)));
+        assert!(merged.get("charge").unwrap().required);
+        assert!(merged.dimensions().unwrap().contains_key("charge"));
+    }
+}
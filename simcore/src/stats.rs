@@ -8,6 +8,12 @@ struct TimedData<T> {
     data: T,
 }
 
+#[derive(Serialize)]
+struct SavedSeries<'a, M, T> {
+    metadata: M,
+    series: &'a [TimedData<T>],
+}
+
 pub struct Timeseries<T> {
     start_time: Instant,
     series: Vec<TimedData<T>>,
@@ -27,6 +33,19 @@ impl<T> Timeseries<T> {
         std::fs::write(filename, serde_json::to_string(&self.series)?)
     }
 
+    /// Saves series wrapped in an object together with arbitrary run
+    /// information, such as seeds
+    pub fn save_with_metadata<M>(&self, filename: &str, metadata: M) -> std::io::Result<()>
+        where T: Serialize, M: Serialize
+    {
+        let output = SavedSeries {
+            metadata,
+            series: &self.series,
+        };
+
+        std::fs::write(filename, serde_json::to_string(&output)?)
+    }
+
     pub fn record(&mut self, data: T, time: Option<f64>) {
         self.series.push(TimedData {
             time: time.unwrap_or((Instant::now() - self.start_time).as_secs_f64()),
@@ -34,3 +53,51 @@ impl<T> Timeseries<T> {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn saved(name: &str, save: impl FnOnce(&str) -> std::io::Result<()>) -> serde_json::Value {
+        let path = std::env::temp_dir().join(format!("simcore-stats-{}-{name}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        save(path).unwrap();
+        let value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        value
+    }
+
+    fn series() -> Timeseries<u32> {
+        let mut series = Timeseries::new();
+        series.record(1, Some(0.0));
+        series.record(2, Some(0.5));
+        series
+    }
+
+    #[test]
+    fn save_writes_bare_array() {
+        let series = series();
+        let value = saved("bare", |path| series.save(path));
+
+        assert_eq!(value, json!([{"time": 0.0, "data": 1}, {"time": 0.5, "data": 2}]));
+    }
+
+    #[test]
+    fn save_with_metadata_wraps_series() {
+        let series = series();
+        let value = saved("metadata", |path| series.save_with_metadata(path, json!({"seed": 7})));
+
+        assert_eq!(value["metadata"], json!({"seed": 7}));
+        assert_eq!(value["series"][1], json!({"time": 0.5, "data": 2}));
+    }
+
+    #[test]
+    fn record_without_time_uses_elapsed_time() {
+        let mut series = Timeseries::new();
+        series.record((), None);
+
+        assert!(series.series[0].time >= 0.0);
+    }
+}
@@ -0,0 +1,317 @@
+pub mod proto {
+    use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+
+    use crate::{
+        particle::proto::ParticleProto,
+        random::Rng,
+        Property, PropertyError, PropertyMap, SimFloat,
+    };
+
+    /// Sum of m v^2 over all particles, i.e. twice the kinetic energy
+    pub fn twice_kinetic<const N: usize>(objects: &[ParticleProto<N>]) -> SimFloat {
+        objects.iter().fold(0.0, |a, p| a + p.mass() * p.velocity.magnitude_squared())
+    }
+
+    /// Instantaneous temperature from equipartition: N_dof k T = sum m v^2
+    pub fn temperature<const N: usize>(objects: &[ParticleProto<N>], k_boltzmann: SimFloat) -> SimFloat {
+        let dof = (objects.len() * N) as SimFloat;
+        if dof == 0.0 { return 0.0 }
+
+        twice_kinetic(objects) / (dof * k_boltzmann)
+    }
+
+    pub enum Thermostat {
+        /// Velocity rescaling towards target temperature with relaxation time `tau`
+        Berendsen {
+            target_temperature: SimFloat,
+            tau: SimFloat,
+        },
+        /// Stochastic friction and noise, integrated exactly for the velocity part
+        Langevin {
+            target_temperature: SimFloat,
+            friction: SimFloat,
+        },
+        /// Deterministic thermostat chain with thermostat "velocities" `chain`
+        NoseHoover {
+            target_temperature: SimFloat,
+            tau: SimFloat,
+            chain: Vec<SimFloat>,
+        },
+    }
+
+    impl Thermostat {
+        /// Reads thermostat from `thermostat` entry of simulation config
+        pub fn from_property(property: &Property) -> IoResult<Self> {
+            let config = property.try_nested().ok_or_else(|| IoError::new(
+                ErrorKind::InvalidData,
+                "Thermostat should be an object",
+            ))?;
+
+            // Relaxation times divide velocities and set chain masses, so zero is no thermostat at all
+            let positive = |key: &str| -> IoResult<SimFloat> {
+                let value = config.get_float(key)?;
+                if value > 0.0 { return Ok(value) }
+                Err(PropertyError::OutOfRange {
+                    path: key.to_string(),
+                    value,
+                    min: Some(0.0),
+                    max: None,
+                    exclusive_min: true,
+                }.into())
+            };
+
+            let kind = config.get_str_opt("type")?.unwrap_or_default();
+            let target_temperature = config.get_float("target_temperature")?;
+
+            match kind {
+                "berendsen" => Ok(Thermostat::Berendsen {
+                    target_temperature,
+                    tau: positive("tau")?,
+                }),
+                "langevin" => Ok(Thermostat::Langevin {
+                    target_temperature,
+                    friction: positive("friction")?,
+                }),
+                "nose_hoover" => {
+                    let chain_length = config.get_count_or("chain_length", 3)?;
+
+                    Ok(Thermostat::NoseHoover {
+                        target_temperature,
+                        tau: positive("tau")?,
+                        chain: vec![0.0; chain_length.max(1)],
+                    })
+                }
+                _ => Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown thermostat type `{kind}`"),
+                )),
+            }
+        }
+
+        pub fn target_temperature(&self) -> SimFloat {
+            match self {
+                Thermostat::Berendsen { target_temperature, .. } => *target_temperature,
+                Thermostat::Langevin { target_temperature, .. } => *target_temperature,
+                Thermostat::NoseHoover { target_temperature, .. } => *target_temperature,
+            }
+        }
+
+        /// Applies thermostat to particle velocities after an integration step of `delta`.
+        /// `rng` is only used by stochastic thermostats
+        pub fn apply<const N: usize>(
+            &mut self,
+            objects: &mut [ParticleProto<N>],
+            delta: SimFloat,
+            k_boltzmann: SimFloat,
+            rng: &mut Rng,
+        ) {
+            match self {
+                Thermostat::Berendsen { target_temperature, tau } => {
+                    let current = temperature(objects, k_boltzmann);
+                    if current <= 0.0 { return }
+
+                    let scale = (1.0 + delta / *tau * (*target_temperature / current - 1.0))
+                        .max(0.0)
+                        .sqrt();
+                    for p in objects.iter_mut() {
+                        p.velocity *= scale;
+                    }
+                }
+                Thermostat::Langevin { target_temperature, friction } => {
+                    let decay = (-*friction * delta).exp();
+                    for p in objects.iter_mut() {
+                        let sigma = ((1.0 - decay * decay) * k_boltzmann * *target_temperature / p.mass()).sqrt();
+                        p.velocity = p.velocity * decay + rng.gaussian_vector::<N>() * sigma;
+                    }
+                }
+                Thermostat::NoseHoover { target_temperature, tau, chain } => {
+                    let kt = k_boltzmann * *target_temperature;
+                    let dof = (objects.len() * N) as SimFloat;
+                    let masses = (0..chain.len())
+                        .map(|j| if j == 0 { dof * kt * *tau * *tau } else { kt * *tau * *tau })
+                        .collect::<Vec<_>>();
+
+                    // Two half-steps of the Martyna-Tuckerman-Klein chain propagator
+                    let mut scale = 1.0;
+                    for _ in 0..2 {
+                        scale *= nose_hoover_half_step(
+                            chain,
+                            &masses,
+                            twice_kinetic(objects) * scale * scale,
+                            dof,
+                            kt,
+                            delta,
+                        );
+                    }
+
+                    for p in objects.iter_mut() {
+                        p.velocity *= scale;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Propagates chain over `delta / 2`. Returns velocity scaling factor
+    fn nose_hoover_half_step(
+        chain: &mut [SimFloat],
+        masses: &[SimFloat],
+        mut twice_kinetic: SimFloat,
+        dof: SimFloat,
+        kt: SimFloat,
+        delta: SimFloat,
+    ) -> SimFloat {
+        let last = chain.len() - 1;
+        let force = |chain: &[SimFloat], j: usize, twice_kinetic: SimFloat| {
+            if j == 0 {
+                (twice_kinetic - dof * kt) / masses[0]
+            } else {
+                (masses[j - 1] * chain[j - 1] * chain[j - 1] - kt) / masses[j]
+            }
+        };
+
+        for j in (0..=last).rev() {
+            if j < last { chain[j] *= (-chain[j + 1] * delta / 8.0).exp() }
+            chain[j] += force(chain, j, twice_kinetic) * delta / 4.0;
+            if j < last { chain[j] *= (-chain[j + 1] * delta / 8.0).exp() }
+        }
+
+        let scale = (-chain[0] * delta / 2.0).exp();
+        twice_kinetic *= scale * scale;
+
+        for j in 0..=last {
+            if j < last { chain[j] *= (-chain[j + 1] * delta / 8.0).exp() }
+            chain[j] += force(chain, j, twice_kinetic) * delta / 4.0;
+            if j < last { chain[j] *= (-chain[j + 1] * delta / 8.0).exp() }
+        }
+
+        scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::proto::*;
+    use crate::{particle::proto::ParticleProto, random::Rng, Property, PropertyError};
+
+    fn gas(count: usize, seed: u64) -> Vec<ParticleProto<2>> {
+        let mut rng = Rng::new(seed);
+        (0..count).map(|_| {
+            let mut particle = ParticleProto::new();
+            particle.velocity = rng.gaussian_vector();
+            particle.additional_properties.insert("mass".to_string(), Property::Float(2.0));
+            particle
+        }).collect()
+    }
+
+    fn config(entries: &[(&str, Property)]) -> Property {
+        Property::Nested(entries.iter().map(|(k, v)| (k.to_string(), v.clone())).collect::<HashMap<_, _>>())
+    }
+
+    #[test]
+    fn temperature_follows_equipartition() {
+        let mut objects = gas(2, 0);
+        objects[0].velocity = [1.0, 0.0].into();
+        objects[1].velocity = [0.0, -2.0].into();
+
+        // 2 * (1 + 4) / (4 dof * 0.5)
+        assert_eq!(temperature(&objects, 0.5), 5.0);
+        assert_eq!(temperature::<2>(&[], 1.0), 0.0);
+    }
+
+    #[test]
+    fn berendsen_with_tau_of_one_step_reaches_target() {
+        let mut objects = gas(50, 1);
+        let mut thermostat = Thermostat::Berendsen { target_temperature: 3.0, tau: 0.1 };
+
+        thermostat.apply(&mut objects, 0.1, 1.0, &mut Rng::new(0));
+
+        assert!((temperature(&objects, 1.0) - 3.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn langevin_equilibrates_to_target() {
+        let mut objects = gas(200, 2);
+        let mut thermostat = Thermostat::Langevin { target_temperature: 0.5, friction: 1.0 };
+        let mut rng = Rng::new(3);
+
+        let mut average = 0.0;
+        for step in 0..2000 {
+            thermostat.apply(&mut objects, 0.01, 1.0, &mut rng);
+            if step >= 1000 { average += temperature(&objects, 1.0) / 1000.0 }
+        }
+
+        assert!((average - 0.5).abs() < 0.05, "{average}");
+    }
+
+    #[test]
+    fn nose_hoover_drives_temperature_towards_target() {
+        let mut objects = gas(100, 4);
+        let mut thermostat = Thermostat::from_property(&config(&[
+            ("type", Property::String("nose_hoover".to_string())),
+            ("target_temperature", Property::Float(4.0)),
+            ("tau", Property::Float(0.1)),
+        ])).unwrap();
+        let start = temperature(&objects, 1.0);
+
+        for _ in 0..100 {
+            thermostat.apply(&mut objects, 0.01, 1.0, &mut Rng::new(0));
+        }
+
+        assert!(temperature(&objects, 1.0) > start);
+        assert!(matches!(thermostat, Thermostat::NoseHoover { ref chain, .. } if chain.len() == 3));
+    }
+
+    #[test]
+    fn unknown_type_and_missing_parameters_are_rejected() {
+        let unknown = config(&[
+            ("type", Property::String("andersen".to_string())),
+            ("target_temperature", Property::Float(1.0)),
+        ]);
+        let missing_tau = config(&[
+            ("type", Property::String("berendsen".to_string())),
+            ("target_temperature", Property::Float(1.0)),
+        ]);
+
+        assert!(Thermostat::from_property(&unknown).is_err());
+        assert!(Thermostat::from_property(&missing_tau).is_err());
+        assert!(Thermostat::from_property(&Property::Float(1.0)).is_err());
+
+        let negative_chain = config(&[
+            ("type", Property::String("nose_hoover".to_string())),
+            ("target_temperature", Property::Float(1.0)),
+            ("tau", Property::Float(1.0)),
+            ("chain_length", Property::Int(-2)),
+        ]);
+        assert!(Thermostat::from_property(&negative_chain).is_err());
+
+        for (kind, key) in [("berendsen", "tau"), ("nose_hoover", "tau"), ("langevin", "friction")] {
+            for value in [0.0, -1.0] {
+                let thermostat = config(&[
+                    ("type", Property::String(kind.to_string())),
+                    ("target_temperature", Property::Float(1.0)),
+                    (key, Property::Float(value)),
+                ]);
+                assert!(Thermostat::from_property(&thermostat).is_err(), "{kind} {key} = {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn parameters_of_wrong_type_are_reported() {
+        let thermostat = config(&[
+            ("type", Property::String("langevin".to_string())),
+            ("target_temperature", Property::Float(1.0)),
+            ("friction", Property::String("high".to_string())),
+        ]);
+
+        let error = Thermostat::from_property(&thermostat).err().unwrap();
+        let inner = error.get_ref().and_then(|e| e.downcast_ref::<PropertyError>());
+        assert!(
+            matches!(inner, Some(PropertyError::WrongType { path, expected: "Float", found: "String" }) if path == "friction"),
+            "{error}",
+        );
+    }
+}
@@ -0,0 +1,402 @@
+use std::{fmt, io::{Error as IoError, ErrorKind}, ops::Mul};
+
+use serde::{Deserialize, Serialize};
+
+use crate::SimFloat;
+
+const BASE_SYMBOLS: [&str; 6] = ["m", "kg", "s", "A", "K", "mol"];
+
+/// Exponents of length, mass, time, current, temperature and amount of substance
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Dimension(pub [i8; 6]);
+
+impl Dimension {
+    pub const DIMENSIONLESS: Self = Dimension([0; 6]);
+    pub const LENGTH: Self = Dimension([1, 0, 0, 0, 0, 0]);
+    pub const MASS: Self = Dimension([0, 1, 0, 0, 0, 0]);
+    pub const TIME: Self = Dimension([0, 0, 1, 0, 0, 0]);
+    pub const CURRENT: Self = Dimension([0, 0, 0, 1, 0, 0]);
+    pub const TEMPERATURE: Self = Dimension([0, 0, 0, 0, 1, 0]);
+    pub const AMOUNT: Self = Dimension([0, 0, 0, 0, 0, 1]);
+
+    pub fn powi(self, exponent: i8) -> Self {
+        Dimension(self.0.map(|e| e * exponent))
+    }
+}
+
+impl Mul for Dimension {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Dimension(std::array::from_fn(|i| self.0[i] + other.0[i]))
+    }
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == Self::DIMENSIONLESS {
+            return write!(f, "1");
+        }
+
+        let parts = self.0.iter().zip(BASE_SYMBOLS)
+            .filter(|(e, _)| **e != 0)
+            .map(|(e, s)| if *e == 1 { s.to_string() } else { format!("{s}^{e}") })
+            .collect::<Vec<_>>();
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Size of a unit in SI base units
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Unit {
+    pub scale: SimFloat,
+    pub dimension: Dimension,
+}
+
+impl Unit {
+    const fn new(scale: SimFloat, dimension: [i8; 6]) -> Self {
+        Self { scale, dimension: Dimension(dimension) }
+    }
+
+    fn powi(self, exponent: i8) -> Self {
+        Self { scale: self.scale.powi(exponent as i32), dimension: self.dimension.powi(exponent) }
+    }
+
+    /// Parses a product of units like `kg m^2 s^-2`, `m/s^2` or `J/K`.
+    /// A `/` divides by the single unit following it
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut unit = Unit::new(1.0, [0; 6]);
+        let mut divide = false;
+        let mut empty = true;
+
+        let spaced = text.replace('/', " / ").replace('*', " ");
+        for token in spaced.split_whitespace() {
+            if token == "/" {
+                if divide {
+                    return Err(format!("Unit `{text}` has two `/` in a row"));
+                }
+                divide = true;
+                continue;
+            }
+
+            let (symbol, exponent) = match token.split_once('^') {
+                Some((s, e)) => (s, e.parse::<i8>().map_err(|_| format!("Invalid exponent in unit `{text}`"))?),
+                None => (token, 1),
+            };
+            let factor = named_unit(symbol).ok_or_else(|| format!("Unknown unit `{symbol}`"))?;
+
+            unit = unit * factor.powi(if divide { -exponent } else { exponent });
+            divide = false;
+            empty = false;
+        }
+
+        if divide || empty {
+            return Err(format!("Incomplete unit `{text}`"));
+        }
+
+        Ok(unit)
+    }
+}
+
+impl Mul for Unit {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self { scale: self.scale * other.scale, dimension: self.dimension * other.dimension }
+    }
+}
+
+const UNITS: &[(&str, Unit)] = &[
+    ("1", Unit::new(1.0, [0, 0, 0, 0, 0, 0])),
+    ("m", Unit::new(1.0, [1, 0, 0, 0, 0, 0])),
+    ("g", Unit::new(1e-3, [0, 1, 0, 0, 0, 0])),
+    ("s", Unit::new(1.0, [0, 0, 1, 0, 0, 0])),
+    ("A", Unit::new(1.0, [0, 0, 0, 1, 0, 0])),
+    ("K", Unit::new(1.0, [0, 0, 0, 0, 1, 0])),
+    ("mol", Unit::new(1.0, [0, 0, 0, 0, 0, 1])),
+    ("Hz", Unit::new(1.0, [0, 0, -1, 0, 0, 0])),
+    ("N", Unit::new(1.0, [1, 1, -2, 0, 0, 0])),
+    ("Pa", Unit::new(1.0, [-1, 1, -2, 0, 0, 0])),
+    ("J", Unit::new(1.0, [2, 1, -2, 0, 0, 0])),
+    ("W", Unit::new(1.0, [2, 1, -3, 0, 0, 0])),
+    ("C", Unit::new(1.0, [0, 0, 1, 1, 0, 0])),
+    ("V", Unit::new(1.0, [2, 1, -3, -1, 0, 0])),
+    ("eV", Unit::new(1.602176634e-19, [2, 1, -2, 0, 0, 0])),
+];
+
+/// Units that don't take SI prefixes
+const FIXED_UNITS: &[(&str, Unit)] = &[
+    ("rad", Unit::new(1.0, [0, 0, 0, 0, 0, 0])),
+    ("deg", Unit::new(std::f64::consts::PI / 180.0, [0, 0, 0, 0, 0, 0])),
+    ("min", Unit::new(60.0, [0, 0, 1, 0, 0, 0])),
+    ("h", Unit::new(3600.0, [0, 0, 1, 0, 0, 0])),
+    ("day", Unit::new(86400.0, [0, 0, 1, 0, 0, 0])),
+    ("yr", Unit::new(3.15576e7, [0, 0, 1, 0, 0, 0])),
+    ("AU", Unit::new(1.495978707e11, [1, 0, 0, 0, 0, 0])),
+    ("au", Unit::new(1.495978707e11, [1, 0, 0, 0, 0, 0])),
+    ("ly", Unit::new(9.4607304725808e15, [1, 0, 0, 0, 0, 0])),
+    ("pc", Unit::new(3.085677581491367e16, [1, 0, 0, 0, 0, 0])),
+    ("M_sun", Unit::new(1.98847e30, [0, 1, 0, 0, 0, 0])),
+    ("M_earth", Unit::new(5.9722e24, [0, 1, 0, 0, 0, 0])),
+    ("R_sun", Unit::new(6.957e8, [1, 0, 0, 0, 0, 0])),
+    ("R_earth", Unit::new(6.371e6, [1, 0, 0, 0, 0, 0])),
+];
+
+const PREFIXES: &[(&str, SimFloat)] = &[
+    ("Y", 1e24), ("Z", 1e21), ("E", 1e18), ("P", 1e15), ("T", 1e12), ("G", 1e9), ("M", 1e6), ("k", 1e3),
+    ("h", 1e2), ("da", 1e1), ("d", 1e-1), ("c", 1e-2), ("m", 1e-3), ("u", 1e-6), ("µ", 1e-6), ("n", 1e-9),
+    ("p", 1e-12), ("f", 1e-15), ("a", 1e-18),
+];
+
+fn named_unit(symbol: &str) -> Option<Unit> {
+    let find = |table: &[(&str, Unit)], name: &str| table.iter().find(|(n, _)| *n == name).map(|(_, u)| *u);
+
+    if let Some(unit) = find(FIXED_UNITS, symbol).or_else(|| find(UNITS, symbol)) {
+        return Some(unit);
+    }
+
+    PREFIXES.iter().find_map(|(prefix, factor)| {
+        let unit = find(UNITS, symbol.strip_prefix(prefix)?)?;
+        Some(Unit { scale: unit.scale * factor, ..unit })
+    })
+}
+
+/// Number or bracketed list of numbers followed by a unit, e.g. `5.97e24 kg` or
+/// `[1, 0] AU`. None if `text` doesn't start like a quantity
+pub fn parse_quantity(text: &str) -> Option<Result<(Vec<SimFloat>, Unit), String>> {
+    let text = text.trim();
+
+    let (numbers, unit) = if let Some(rest) = text.strip_prefix('[') {
+        let (list, unit) = rest.split_once(']')?;
+        let numbers = list.split(',').map(|n| n.trim().parse::<SimFloat>().ok()).collect::<Option<Vec<_>>>()?;
+        (numbers, unit)
+    } else {
+        let (number, unit) = text.split_once(char::is_whitespace)?;
+        (vec![number.parse::<SimFloat>().ok()?], unit)
+    };
+
+    Some(Unit::parse(unit).map(|u| (numbers, u)))
+}
+
+/// Internal base units as unit expressions. Missing ones are SI
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UnitSystemConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mass: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<String>,
+}
+
+/// Failure to read a quantity or a dimension mismatch, at `path`
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnitError {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for UnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for UnitError {}
+
+impl From<UnitError> for IoError {
+    fn from(error: UnitError) -> Self {
+        IoError::new(ErrorKind::InvalidData, error)
+    }
+}
+
+/// Units quantities are stored in during simulation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnitSystem {
+    /// SI size of each internal base unit
+    base: [SimFloat; 6],
+}
+
+impl UnitSystem {
+    pub fn si() -> Self {
+        Self { base: [1.0; 6] }
+    }
+
+    pub fn from_config(config: &UnitSystemConfig) -> Result<Self, UnitError> {
+        let mut system = Self::si();
+        let bases = [
+            ("length", &config.length, Dimension::LENGTH),
+            ("mass", &config.mass, Dimension::MASS),
+            ("time", &config.time, Dimension::TIME),
+            ("current", &config.current, Dimension::CURRENT),
+            ("temperature", &config.temperature, Dimension::TEMPERATURE),
+            ("amount", &config.amount, Dimension::AMOUNT),
+        ];
+
+        for (i, (name, text, dimension)) in bases.into_iter().enumerate() {
+            let Some(text) = text else { continue };
+            let error = |message| UnitError { path: format!("units.{name}"), message };
+
+            let unit = Unit::parse(text).map_err(error)?;
+            if unit.dimension != dimension {
+                return Err(error(format!("`{text}` is {}, expected {dimension}", unit.dimension)));
+            }
+            system.base[i] = unit.scale;
+        }
+
+        Ok(system)
+    }
+
+    /// SI size of the internal unit of `dimension`
+    pub fn scale(&self, dimension: Dimension) -> SimFloat {
+        self.base.iter().zip(dimension.0).map(|(b, e)| b.powi(e as i32)).product()
+    }
+
+    /// Value of SI quantity in internal units
+    pub fn to_internal(&self, si_value: SimFloat, dimension: Dimension) -> SimFloat {
+        si_value / self.scale(dimension)
+    }
+
+    /// Replaces quantity strings in `value` with numbers in internal units.
+    /// `expected` gives dimension of a key path relative to `path`, and plain
+    /// numbers are taken to be in internal units already
+    pub fn convert_json(
+        &self,
+        value: &mut serde_json::Value,
+        path: &str,
+        expected: &dyn Fn(&str) -> Option<Dimension>,
+    ) -> Result<(), UnitError> {
+        self.convert_value(value, path, "", expected)
+    }
+
+    fn convert_value(
+        &self,
+        value: &mut serde_json::Value,
+        path: &str,
+        key: &str,
+        expected: &dyn Fn(&str) -> Option<Dimension>,
+    ) -> Result<(), UnitError> {
+        use serde_json::Value;
+
+        let full_path = || if key.is_empty() { path.to_string() } else { format!("{path}.{key}") };
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map.iter_mut() {
+                    let key = if key.is_empty() { k.clone() } else { format!("{key}.{k}") };
+                    self.convert_value(v, path, &key, expected)?;
+                }
+            }
+            Value::Array(items) => {
+                for item in items.iter_mut() {
+                    self.convert_value(item, path, key, expected)?;
+                }
+            }
+            Value::String(text) => {
+                let dimension = expected(key);
+                let (numbers, unit) = match parse_quantity(text) {
+                    None => return Ok(()),
+                    Some(Ok(quantity)) => quantity,
+                    // Might be a name that happens to start with a number
+                    Some(Err(_)) if dimension.is_none() => return Ok(()),
+                    Some(Err(message)) => return Err(UnitError { path: full_path(), message }),
+                };
+
+                if let Some(dimension) = dimension.filter(|d| *d != unit.dimension) {
+                    return Err(UnitError {
+                        path: full_path(),
+                        message: format!("`{text}` is {}, expected {dimension}", unit.dimension),
+                    });
+                }
+
+                let mut converted = numbers.iter()
+                    .map(|n| serde_json::json!(self.to_internal(n * unit.scale, unit.dimension)))
+                    .collect::<Vec<_>>();
+                *value = if text.trim_start().starts_with('[') {
+                    Value::Array(converted)
+                } else {
+                    converted.remove(0)
+                };
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: SimFloat, b: SimFloat) -> bool {
+        (a - b).abs() <= 1e-12 * a.abs().max(b.abs())
+    }
+
+    #[test]
+    fn compound_units_parse_to_si() {
+        let newton = Unit::parse("kg m/s^2").unwrap();
+        assert_eq!(newton.dimension, Unit::parse("N").unwrap().dimension);
+        assert!(close(newton.scale, 1.0));
+
+        let speed = Unit::parse("km/h").unwrap();
+        assert_eq!(speed.dimension, Dimension::LENGTH * Dimension::TIME.powi(-1));
+        assert!(close(speed.scale, 1000.0 / 3600.0));
+
+        assert!(close(Unit::parse("mg").unwrap().scale, 1e-6));
+        assert!(close(Unit::parse("µs").unwrap().scale, 1e-6));
+        assert_eq!(Dimension([2, 1, -2, 0, 0, 0]).to_string(), "m^2 kg s^-2");
+    }
+
+    #[test]
+    fn malformed_units_are_rejected() {
+        assert!(Unit::parse("furlong").is_err());
+        assert!(Unit::parse("m/").is_err());
+        assert!(Unit::parse("m //s").is_err());
+        assert!(Unit::parse("m^x").is_err());
+        assert!(Unit::parse("").is_err());
+    }
+
+    #[test]
+    fn quantities_need_a_leading_number() {
+        let (numbers, unit) = parse_quantity("[1, 0.5] AU").unwrap().unwrap();
+        assert_eq!(numbers, [1.0, 0.5]);
+        assert_eq!(unit.dimension, Dimension::LENGTH);
+
+        assert!(parse_quantity("earth").is_none());
+        assert!(parse_quantity("5").is_none());
+        assert!(parse_quantity("5 apples").unwrap().is_err());
+    }
+
+    #[test]
+    fn quantities_convert_to_internal_units() {
+        let system = UnitSystem::from_config(&UnitSystemConfig {
+            length: Some("AU".to_string()),
+            time: Some("day".to_string()),
+            ..Default::default()
+        }).unwrap();
+
+        let mut value = serde_json::json!({"a": "1.495978707e11 m", "v": "[2, 0] AU/day", "name": "3 body problem"});
+        system.convert_json(&mut value, "root", &|_| None).unwrap();
+        assert!(close(value["a"].as_f64().unwrap(), 1.0));
+        assert_eq!(value["v"], serde_json::json!([2.0, 0.0]));
+        assert_eq!(value["name"], "3 body problem");
+    }
+
+    #[test]
+    fn mismatched_dimensions_are_reported_with_path() {
+        let mut value = serde_json::json!({"orbit": {"radius": "2 s"}});
+        let error = UnitSystem::si()
+            .convert_json(&mut value, "root", &|k| (k == "orbit.radius").then_some(Dimension::LENGTH))
+            .unwrap_err();
+        assert_eq!(error.path, "root.orbit.radius");
+
+        let error = UnitSystem::from_config(&UnitSystemConfig { mass: Some("m".to_string()), ..Default::default() });
+        assert_eq!(error.unwrap_err().path, "units.mass");
+    }
+}
@@ -0,0 +1,350 @@
+pub mod proto {
+    use std::{
+        collections::HashMap,
+        io::{Error as IoError, ErrorKind, Result as IoResult},
+    };
+
+    use crate::{
+        diffusion::proto::{diffusion_operator, face_diffusivity},
+        field::proto::{scalar_map, Field, FieldBoundary, FieldMapConfig, FieldSolver, FieldValue},
+        Property, PropertyMap, SimFloat,
+    };
+
+    /// Oscillating point source adding `amplitude * sin(2 pi frequency t + phase)`
+    /// to the acceleration of one cell
+    #[derive(Clone, Copy, Debug)]
+    pub struct WaveSource<T> {
+        pub cell: [usize; 3],
+        pub amplitude: T,
+        pub frequency: SimFloat,
+        pub phase: SimFloat,
+    }
+
+    impl<T: FieldValue> WaveSource<T> {
+        pub fn value(&self, time: SimFloat) -> T {
+            self.amplitude * (std::f64::consts::TAU * self.frequency * time + self.phase).sin()
+        }
+
+        /// Reads source from `sources` entry of simulation config. Position is
+        /// in world coordinates and snapped to the nearest cell
+        pub fn from_property(property: &Property, field: &Field<T>) -> IoResult<Self> {
+            let config = property.try_nested().ok_or_else(|| IoError::new(
+                ErrorKind::InvalidData,
+                "Wave source should be an object",
+            ))?;
+
+            let position = config.get("position").and_then(|p| p.try_components()).ok_or_else(|| IoError::new(
+                ErrorKind::InvalidData,
+                "Wave source requires `position`",
+            ))?;
+
+            let grid = field.grid;
+            if position.len() != grid.dimension {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Wave source position should have {} components", grid.dimension),
+                ));
+            }
+
+            let mut cell = [0; 3];
+            for (axis, x) in position.iter().enumerate() {
+                let index = ((x - grid.origin[axis]) / grid.spacing).round();
+                if index < 0.0 || index >= grid.shape[axis] as SimFloat {
+                    return Err(IoError::new(
+                        ErrorKind::InvalidData,
+                        format!("Wave source at {position:?} is outside of the grid"),
+                    ));
+                }
+                cell[axis] = index as usize;
+            }
+
+            let amplitude = config.get("amplitude")
+                .and_then(T::from_property)
+                .ok_or_else(|| IoError::new(
+                    ErrorKind::InvalidData,
+                    "Wave source requires `amplitude` matching field type",
+                ))?;
+
+            Ok(Self {
+                cell,
+                amplitude,
+                frequency: config.get_float("frequency")?,
+                phase: config.get_float_or("phase", 0.0)?,
+            })
+        }
+    }
+
+    /// Absorbing layer damping, growing quadratically from 0 to `strength` over
+    /// `width` cells next to every non-periodic side
+    pub fn sponge_map<T>(field: &Field<T>, width: usize, strength: SimFloat) -> Field<SimFloat> {
+        let grid = field.grid;
+        let mut sponge = Field::new(grid, [[FieldBoundary::Neumann(0.0); 2]; 3], 0.0);
+        if width == 0 { return sponge }
+
+        for cell in grid.cells() {
+            let mut sigma: SimFloat = 0.0;
+            for (axis, c) in cell.into_iter().enumerate().take(grid.dimension) {
+                let distances = [c, grid.shape[axis] - 1 - c];
+                for (side, distance) in distances.into_iter().enumerate() {
+                    let periodic = matches!(field.boundaries[axis][side], FieldBoundary::Periodic);
+                    if periodic || distance >= width { continue }
+
+                    let depth = (width - distance) as SimFloat / width as SimFloat;
+                    sigma = sigma.max(strength * depth * depth);
+                }
+            }
+            sponge.set(cell, sigma);
+        }
+
+        sponge
+    }
+
+    /// Leapfrog solver of u'' = div(c^2 grad u) - damping u' + sources
+    pub struct WaveSolver<T> {
+        /// Squared wave speed per cell
+        speed_squared: Field<SimFloat>,
+        /// Uniform damping plus absorbing layer
+        damping: Field<SimFloat>,
+        sources: Vec<WaveSource<T>>,
+        previous: Vec<T>,
+        delta: SimFloat,
+        time: SimFloat,
+    }
+
+    impl<T: FieldValue> WaveSolver<T> {
+        /// Fails if timestep `delta` breaks the CFL condition c dt / h <= 1 / sqrt(d)
+        pub fn new(
+            field: &Field<T>,
+            speed: &Field<SimFloat>,
+            damping: Field<SimFloat>,
+            sources: Vec<WaveSource<T>>,
+            delta: SimFloat,
+        ) -> IoResult<Self> {
+            let grid = field.grid;
+            let max_speed = speed.data().iter().fold(0.0, |m: SimFloat, c| m.max(c.abs()));
+            let limit = grid.spacing / (max_speed * (grid.dimension as SimFloat).sqrt());
+            if delta > limit {
+                return Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("Timestep {delta} exceeds wave equation stability limit {limit}"),
+                ));
+            }
+
+            let mut speed_squared = speed.clone();
+            for c in speed_squared.data_mut() {
+                *c *= *c;
+            }
+
+            Ok(Self {
+                speed_squared,
+                damping,
+                sources,
+                // Field starts at rest
+                previous: field.data().to_vec(),
+                delta,
+                time: 0.0,
+            })
+        }
+
+        /// Reads wave speed from `wave_speed` map or float, `damping`, `sponge`
+        /// (with `width` in cells and `strength`) and named `sources` from simulation config
+        pub fn from_config(
+            field: &Field<T>,
+            maps: &HashMap<String, FieldMapConfig>,
+            simulation_properties: &HashMap<String, Property>,
+            delta: SimFloat,
+        ) -> IoResult<Self> {
+            let speed = scalar_map(field.grid, &field.boundaries, maps, simulation_properties, "wave_speed")?;
+
+            let mut damping = match simulation_properties.get("sponge") {
+                Some(sponge) => {
+                    let config = sponge.try_nested().ok_or_else(|| IoError::new(
+                        ErrorKind::InvalidData,
+                        "Sponge should be an object",
+                    ))?;
+                    let width = config.get_count("width")?;
+                    sponge_map(field, width, config.get_float("strength")?)
+                }
+                None => sponge_map(field, 0, 0.0),
+            };
+
+            if let Some(d) = simulation_properties.get_float_opt("damping")? {
+                for sigma in damping.data_mut() {
+                    *sigma += d;
+                }
+            }
+
+            let sources = match simulation_properties.get("sources") {
+                Some(sources) => sources.try_nested()
+                    .ok_or_else(|| IoError::new(ErrorKind::InvalidData, "Wave sources should be an object"))?
+                    .values()
+                    .map(|s| WaveSource::from_property(s, field))
+                    .collect::<IoResult<Vec<_>>>()?,
+                None => Vec::new(),
+            };
+
+            Self::new(field, &speed, damping, sources, delta)
+        }
+
+        pub fn sources(&self) -> &[WaveSource<T>] {
+            &self.sources
+        }
+
+        /// Kinetic plus elastic energy of the field, integrated over the grid
+        pub fn energy(&self, field: &Field<T>) -> SimFloat {
+            let grid = field.grid;
+            let inv_h2 = 1.0 / (grid.spacing * grid.spacing);
+
+            let mut energy = 0.0;
+            for (index, cell) in grid.cells().enumerate() {
+                let velocity = (field.data()[index] - self.previous[index]) * (1.0 / self.delta);
+                energy += 0.5 * velocity.dot(&velocity);
+
+                let c2 = self.speed_squared.get(cell);
+                for axis in 0..grid.dimension {
+                    // Low side face of the first cell is only missing without periodicity
+                    let periodic = matches!(field.boundaries[axis][0], FieldBoundary::Periodic);
+                    let faces: &[isize] = if cell[axis] == 0 && !periodic { &[1, -1] } else { &[1] };
+
+                    for &offset in faces {
+                        // Same face coefficient as the operator, so energy is conserved across speed jumps
+                        let c2_face = face_diffusivity(c2, self.speed_squared.neighbour(cell, axis, offset));
+                        let difference = field.neighbour(cell, axis, offset) - field.get(cell);
+                        energy += 0.5 * c2_face * difference.dot(&difference) * inv_h2;
+                    }
+                }
+            }
+
+            energy * grid.cell_volume()
+        }
+    }
+
+    impl<T: FieldValue> FieldSolver<T> for WaveSolver<T> {
+        fn step(
+            &mut self,
+            field: &mut Field<T>,
+            _simulation_properties: &HashMap<String, Property>,
+            delta: SimFloat,
+        ) {
+            let grid = field.grid;
+            let mut acceleration = vec![T::zero(); grid.len()];
+            diffusion_operator(field, &self.speed_squared, &mut acceleration);
+
+            for source in self.sources.iter() {
+                acceleration[grid.index(source.cell)] += source.value(self.time);
+            }
+
+            for (index, a) in acceleration.into_iter().enumerate() {
+                let u = field.data()[index];
+                let half_damping = 0.5 * self.damping.data()[index] * delta;
+
+                let next = (u * 2.0 - self.previous[index] * (1.0 - half_damping) + a * (delta * delta))
+                    * (1.0 / (1.0 + half_damping));
+
+                self.previous[index] = u;
+                field.data_mut()[index] = next;
+            }
+
+            self.delta = delta;
+            self.time += delta;
+        }
+
+        fn record_stats(&self, field: &Field<T>, stats: &mut HashMap<String, Property>) {
+            stats.insert("field_energy".to_string(), Property::Float(self.energy(field)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::proto::*;
+    use crate::{
+        field::proto::{Field, FieldBoundary, FieldSolver, Grid},
+        Property,
+    };
+
+    fn field(values: &[f64], boundary: FieldBoundary<f64>) -> Field<f64> {
+        let grid = Grid::new(&[values.len()], 1.0, &[]).unwrap();
+        let mut field = Field::new(grid, [[boundary; 2]; 3], 0.0);
+        field.data_mut().copy_from_slice(values);
+        field
+    }
+
+    fn pulse(n: usize) -> Vec<f64> {
+        (0..n).map(|i| (-((i as f64 - n as f64 / 2.0) / 3.0).powi(2)).exp()).collect()
+    }
+
+    #[test]
+    fn timestep_above_cfl_limit_is_rejected() {
+        let u = field(&[0.0; 4], FieldBoundary::Periodic);
+        let speed = field(&[1.0, 2.0, 1.0, 1.0], FieldBoundary::Periodic);
+        let damping = sponge_map(&u, 0, 0.0);
+
+        assert!(WaveSolver::new(&u, &speed, damping.clone(), vec![], 0.6).is_err());
+        assert!(WaveSolver::new(&u, &speed, damping, vec![], 0.5).is_ok());
+    }
+
+    #[test]
+    fn energy_uses_harmonic_mean_of_speed_on_faces() {
+        // Step in the field sits on the face next to a non-propagating cell
+        let u = field(&[0.0, 1.0], FieldBoundary::Neumann(0.0));
+        let speed = field(&[1.0, 0.0], FieldBoundary::Neumann(0.0));
+        let solver = WaveSolver::new(&u, &speed, sponge_map(&u, 0, 0.0), vec![], 0.1).unwrap();
+
+        assert_eq!(solver.energy(&u), 0.0);
+    }
+
+    #[test]
+    fn energy_is_conserved_across_speed_jump() {
+        let n = 64;
+        let mut u = field(&pulse(n), FieldBoundary::Periodic);
+        let speeds = (0..n).map(|i| if i < n / 2 { 1.0 } else { 0.5 }).collect::<Vec<_>>();
+        let speed = field(&speeds, FieldBoundary::Periodic);
+        let mut solver = WaveSolver::new(&u, &speed, sponge_map(&u, 0, 0.0), vec![], 0.05).unwrap();
+
+        solver.step(&mut u, &HashMap::new(), 0.05);
+        let initial = solver.energy(&u);
+        for _ in 0..2000 {
+            solver.step(&mut u, &HashMap::new(), 0.05);
+        }
+
+        let drift = (solver.energy(&u) - initial).abs() / initial;
+        assert!(drift < 0.01, "{drift}");
+    }
+
+    #[test]
+    fn sponge_absorbs_outgoing_waves() {
+        let n = 64;
+        let mut u = field(&pulse(n), FieldBoundary::Neumann(0.0));
+        let speed = field(&vec![1.0; n], FieldBoundary::Neumann(0.0));
+        let sponge = sponge_map(&u, 12, 2.0);
+        assert_eq!(sponge.get([n / 2, 0, 0]), 0.0);
+        assert_eq!(sponge.get([0, 0, 0]), 2.0);
+
+        let mut solver = WaveSolver::new(&u, &speed, sponge, vec![], 0.5).unwrap();
+        solver.step(&mut u, &HashMap::new(), 0.5);
+        let initial = solver.energy(&u);
+        for _ in 0..400 {
+            solver.step(&mut u, &HashMap::new(), 0.5);
+        }
+
+        assert!(solver.energy(&u) < 0.01 * initial);
+    }
+
+    #[test]
+    fn sources_snap_to_cells_inside_grid() {
+        let u = field(&[0.0; 8], FieldBoundary::Periodic);
+        let source = |position: f64| Property::Nested(HashMap::from([
+            ("position".to_string(), Property::Array(vec![Property::Float(position)])),
+            ("amplitude".to_string(), Property::Float(2.0)),
+            ("frequency".to_string(), Property::Float(0.25)),
+        ]));
+
+        let inside = WaveSource::from_property(&source(2.6), &u).unwrap();
+        assert_eq!(inside.cell, [3, 0, 0]);
+        assert!((inside.value(1.0) - 2.0).abs() < 1e-12);
+        assert!(WaveSource::from_property(&source(7.6), &u).is_err());
+    }
+}
@@ -1,6 +1,14 @@
 pub mod proto {
     use raylib::prelude::*;
-    use simcore::particle::proto::ParticleProto;
+    use simcore::{
+        boundary::proto::Boundary,
+        field::proto::Grid,
+        fluid::proto::{CellType, FluidSimulator},
+        lattice::proto::{LatticeBoltzmannSimulator, NodeType},
+        obstacle::proto::{Obstacle2, Shape2},
+        particle::proto::ParticleProto,
+        rigid::proto::RigidBody2,
+    };
 
     #[derive(Clone, Copy, Debug)]
     pub struct Camera {
@@ -13,6 +21,22 @@ pub mod proto {
         pub camera: Camera,
     }
 
+    /// Quantity shown by fluid heatmap
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum FluidView {
+        Dye,
+        Speed,
+    }
+
+    /// Quantity shown by lattice Boltzmann heatmap
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum LatticeView {
+        Speed,
+        Density,
+        /// Signed, blue for clockwise and red for counter-clockwise rotation
+        Vorticity,
+    }
+
     #[derive(Clone, Copy, Debug)]
     pub enum Position<T: Clone + Copy> {
         World(T, T),
@@ -42,25 +66,135 @@ pub mod proto {
             }
         }
 
-        fn point(&mut self, position: Position<f32>, radius: f32) {
+        fn to_view(&self, position: Position<f32>) -> Vector2 {
             match position {
-                Position::View(x, y) => {
-                    self.handle.draw_circle_v(Vector2::new(x, y), 10.0, Color::WHITE);
-                }
+                Position::View(x, y) => Vector2::new(x, y),
                 Position::World(x, y) => {
                     let resolution = Vector2::new(
                         self.handle.get_screen_width() as f32,
                         self.handle.get_screen_height() as f32,
                     );
+                    (Vector2::new(x, y) - self.camera.position) / self.camera.zoom
+                        + resolution / 2.0
+                }
+            }
+        }
+
+        fn point(&mut self, position: Position<f32>, radius: f32) {
+            match position {
+                Position::View(x, y) => {
+                    self.handle.draw_circle_v(Vector2::new(x, y), 10.0, Color::WHITE);
+                }
+                Position::World(_, _) => {
                     self.handle.draw_circle_v(
-                        (Vector2::new(x, y) - self.camera.position) / self.camera.zoom
-                            + resolution / 2.0,
+                        self.to_view(position),
                         (radius / self.camera.zoom).max(1.0),
                         Color::WHITE,
                     );
                 }
             }
         }
+
+        fn line(&mut self, start: Position<f32>, end: Position<f32>, color: Color) {
+            let start = self.to_view(start);
+            let end = self.to_view(end);
+
+            self.handle.draw_line_v(start, end, color);
+        }
+
+        fn circle_outline(&mut self, center: Position<f32>, radius: f32, color: Color) {
+            let radius = match center {
+                Position::View(_, _) => radius,
+                Position::World(_, _) => radius / self.camera.zoom,
+            };
+            let center = self.to_view(center);
+
+            self.handle.draw_circle_lines_v(center, radius.max(1.0), color);
+        }
+
+        fn rigid_body(&mut self, body: &RigidBody2) {
+            let radius = body.particle.radius();
+
+            let center = body.particle.position;
+            let tip = center + body.heading() * radius;
+
+            self.point(Position::World(center.x as f32, center.y as f32), radius as f32);
+            self.line(
+                Position::World(center.x as f32, center.y as f32),
+                Position::World(tip.x as f32, tip.y as f32),
+                Color::RED,
+            );
+        }
+
+        fn obstacle(&mut self, obstacle: &Obstacle2) {
+            const COLOR: Color = Color::LIGHTGRAY;
+
+            match &obstacle.shape {
+                Shape2::Segment { start, end } => self.line(
+                    Position::World(start[0] as f32, start[1] as f32),
+                    Position::World(end[0] as f32, end[1] as f32),
+                    COLOR,
+                ),
+                Shape2::Circle { center, radius } => self.circle_outline(
+                    Position::World(center[0] as f32, center[1] as f32),
+                    *radius as f32,
+                    COLOR,
+                ),
+                Shape2::Polygon { vertices } => {
+                    for (i, start) in vertices.iter().enumerate() {
+                        let end = vertices[(i + 1) % vertices.len()];
+                        self.line(
+                            Position::World(start[0] as f32, start[1] as f32),
+                            Position::World(end[0] as f32, end[1] as f32),
+                            COLOR,
+                        );
+                    }
+                }
+            }
+        }
+
+        /// Dark blue for 0 to red for 1 and above
+        fn heat_color(value: f32) -> Color {
+            let t = value.clamp(0.0, 1.0);
+            Color::color_from_hsv(240.0 * (1.0 - t), 1.0, 0.3 + 0.7 * t)
+        }
+
+        /// Fills every grid cell with a color of `value / max`.
+        /// Cells where `value` returns None are drawn as solid
+        fn heatmap(&mut self, grid: &Grid, max: f32, value: impl Fn([usize; 3]) -> Option<f32>) {
+            let spacing = grid.spacing as f32;
+            let size = spacing / self.camera.zoom;
+
+            for cell in grid.cells() {
+                let position = grid.position(cell);
+                let min = self.to_view(Position::World(
+                    position[0] as f32 - spacing / 2.0,
+                    position[1] as f32 - spacing / 2.0,
+                ));
+
+                let color = match value(cell) {
+                    Some(v) => Self::heat_color(v / max),
+                    None => Color::GRAY,
+                };
+                self.handle.draw_rectangle_v(min, Vector2::new(size, size), color);
+            }
+        }
+
+        fn rectangle_outline(&mut self, min: Position<f32>, max: Position<f32>, color: Color) {
+            let min = self.to_view(min);
+            let max = self.to_view(max);
+
+            self.handle.draw_rectangle_lines_ex(
+                Rectangle::new(
+                    min.x.min(max.x),
+                    min.y.min(max.y),
+                    (max.x - min.x).abs(),
+                    (max.y - min.y).abs(),
+                ),
+                1.0,
+                color,
+            );
+        }
     }
 
     pub struct RaylibVisualizer {
@@ -118,16 +252,29 @@ pub mod proto {
         pub fn draw_particles(
             &mut self,
             particles: &[ParticleProto<2>],
+            bodies: &[RigidBody2],
+            boundary: Option<&Boundary<2>>,
+            obstacles: &[Obstacle2],
             sim_name: &str,
             time: f64
         ) {
             let mut draw = self.begin_draw(self.camera);
             draw.handle.clear_background(Color::BLACK);
 
+            if let Some(boundary) = boundary {
+                draw.rectangle_outline(
+                    Position::World(boundary.min.x as f32, boundary.min.y as f32),
+                    Position::World(boundary.max.x as f32, boundary.max.y as f32),
+                    Color::GRAY,
+                );
+            }
+
+            for obstacle in obstacles.iter() {
+                draw.obstacle(obstacle);
+            }
+
             for particle in particles.iter() {
-                let radius = if let Some(r) = particle.additional_properties.get("radius") {
-                    r.float()
-                } else { 1.0 };
+                let radius = particle.radius();
 
                 draw.point(Position::World(
                     particle.position.x as f32,
@@ -135,6 +282,10 @@ pub mod proto {
                 ), radius as f32);
             }
 
+            for body in bodies.iter() {
+                draw.rigid_body(body);
+            }
+
             draw.text(
                 Position::View(10, 10),
                 &format!("Particle count: {}", particles.len())
@@ -148,6 +299,95 @@ pub mod proto {
                 &format!("Current simulation time: {time}")
             );
         }
+
+        pub fn draw_fluid(&mut self, fluid: &FluidSimulator, view: FluidView) {
+            let mut draw = self.begin_draw(self.camera);
+            draw.handle.clear_background(Color::BLACK);
+
+            let max = match view {
+                FluidView::Dye => fluid.dye().data().iter().fold(0.0, |m: f64, d| m.max(*d)),
+                FluidView::Speed => fluid.velocity().data().iter().fold(0.0, |m: f64, u| m.max(u.magnitude())),
+            };
+
+            draw.heatmap(fluid.grid(), (max as f32).max(f32::EPSILON), |cell| {
+                if fluid.cell_type(cell) == CellType::Solid { return None }
+
+                Some(match view {
+                    FluidView::Dye => fluid.dye().get(cell) as f32,
+                    FluidView::Speed => fluid.velocity().get(cell).magnitude() as f32,
+                })
+            });
+
+            draw.text(
+                Position::View(10, 10),
+                &format!("Current simulation: {}", fluid.sim_name())
+            );
+            draw.text(
+                Position::View(10, 40),
+                &format!("Current simulation time: {}", fluid.time())
+            );
+            draw.text(
+                Position::View(10, 70),
+                &format!("Showing: {view:?}, max {max:.3}")
+            );
+        }
+
+        /// Draws 2D lattice, or its middle z slice for 3D lattices
+        pub fn draw_lattice(&mut self, lattice: &LatticeBoltzmannSimulator, view: LatticeView) {
+            let mut draw = self.begin_draw(self.camera);
+            draw.handle.clear_background(Color::BLACK);
+
+            let grid = lattice.grid();
+            let slice = grid.shape[2] / 2;
+            let value = |cell: [usize; 3]| -> f64 {
+                match view {
+                    LatticeView::Speed => lattice.velocity().get(cell).magnitude(),
+                    LatticeView::Density => lattice.density().get(cell),
+                    LatticeView::Vorticity => {
+                        let gradient = lattice.velocity().gradient(cell);
+                        gradient[0].y - gradient[1].x
+                    }
+                }
+            };
+
+            let (min, max) = grid.cells()
+                .filter(|c| c[2] == slice && lattice.node_type(*c) != NodeType::Wall)
+                .map(value)
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+
+            // Density varies around 1, vorticity around 0
+            let (offset, range) = match view {
+                LatticeView::Speed => (0.0, max),
+                LatticeView::Density => (min, max - min),
+                LatticeView::Vorticity => {
+                    let extent = min.abs().max(max.abs());
+                    (-extent, 2.0 * extent)
+                }
+            };
+            let range = range.max(f64::EPSILON);
+
+            let mut plane = *grid;
+            plane.shape[2] = 1;
+            draw.heatmap(&plane, 1.0, |cell| {
+                let cell = [cell[0], cell[1], slice];
+                if lattice.node_type(cell) == NodeType::Wall { return None }
+
+                Some(((value(cell) - offset) / range) as f32)
+            });
+
+            draw.text(
+                Position::View(10, 10),
+                &format!("Current simulation: {}", lattice.sim_name())
+            );
+            draw.text(
+                Position::View(10, 40),
+                &format!("Current simulation time: {}", lattice.time())
+            );
+            draw.text(
+                Position::View(10, 70),
+                &format!("Showing: {view:?}, range {min:.4} to {max:.4}")
+            );
+        }
     }
 }
 